@@ -0,0 +1,380 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use disassembler::{decode, Disassembly};
+use instruction::Opcode;
+use platform::Variant;
+
+// How control gets from one basic block to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Fallthrough,
+    Jump,
+    // Taken when the skip at the end of the block skips.
+    Skip,
+    // BNNN, whose real target depends on a register. The edge goes to its
+    // base address.
+    Computed,
+}
+
+// A run of instructions only ever entered at the top and left at the
+// bottom. Blocks also end at calls, so every call is the last instruction
+// of its block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub start: u32,
+    // One past the last byte of the last instruction.
+    pub end: u32,
+    pub successors: Vec<(u32, Edge)>,
+    pub call: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    ComputedJump { address: u32, base: u32 },
+    // Named after the field of `Quirks` the instruction depends on.
+    QuirkSensitive { address: u32, quirk: &'static str },
+    // FX55, FX33 or 5XY2 writing from `start` up to `end` while I points
+    // at code.
+    SelfModifying { address: u32, start: u32, end: u32 },
+    // Bytes no path reaches which still decode as instructions.
+    Unreachable { start: u32, end: u32 },
+}
+
+impl Finding {
+    pub fn address(&self) -> u32 {
+        match *self {
+            Finding::ComputedJump { address, .. } |
+            Finding::QuirkSensitive { address, .. } |
+            Finding::SelfModifying { address, .. } => address,
+            Finding::Unreachable { start, .. } => start,
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Finding::ComputedJump { address, base } =>
+                write!(f, "#{:03X}: computed jump from #{:03X}", address, base),
+            Finding::QuirkSensitive { address, quirk } =>
+                write!(f, "#{:03X}: depends on the {} quirk", address, quirk),
+            Finding::SelfModifying { address, start, end } =>
+                write!(f, "#{:03X}: writes code at #{:03X}-#{:03X}", address, start, end - 1),
+            Finding::Unreachable { start, end } =>
+                write!(f, "#{:03X}-#{:03X}: unreachable code", start, end - 1),
+        }
+    }
+}
+
+// The control-flow graph of a disassembled ROM, its call graph, and what
+// is worth a second look in it.
+pub struct Analysis<'a> {
+    disassembly: &'a Disassembly,
+    blocks: BTreeMap<u32, Block>,
+    // Every subroutine, and the load address, with what it calls.
+    calls: BTreeMap<u32, BTreeSet<u32>>,
+    findings: Vec<Finding>,
+}
+
+pub fn analyse(disassembly: &Disassembly) -> Analysis<'_> {
+    let mut analysis = Analysis {
+        disassembly,
+        blocks: BTreeMap::new(),
+        calls: BTreeMap::new(),
+        findings: Vec::new(),
+    };
+    analysis.find_blocks();
+    analysis.find_calls();
+    analysis.find_quirks();
+    analysis.find_self_modification();
+    analysis.find_unreachable();
+    analysis.findings.sort_by_key(Finding::address);
+    analysis
+}
+
+impl<'a> Analysis<'a> {
+    pub fn blocks(&self) -> &BTreeMap<u32, Block> {
+        &self.blocks
+    }
+
+    pub fn calls(&self) -> &BTreeMap<u32, BTreeSet<u32>> {
+        &self.calls
+    }
+
+    // By address.
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    // Where control goes after the instruction at `address`, which must be
+    // one. Calls go on to the instruction after them.
+    fn successors(&self, address: u32) -> Vec<(u32, Edge)> {
+        let decoded = self.disassembly.instruction(address).unwrap();
+        let next = address + decoded.size;
+        let mut successors = match decoded.opcode {
+            Opcode::Jump { nnn } => vec![(nnn as u32, Edge::Jump)],
+            Opcode::JumpWithOffset { nnn } => vec![(nnn as u32, Edge::Computed)],
+            Opcode::Return | Opcode::Exit => Vec::new(),
+            _ if decoded.opcode.is_skip() => {
+                // XO-CHIP skips all of a long load, like the disassembler.
+                let skipped = match self.disassembly.instruction(next) {
+                    Some(skipped) if skipped.opcode == Opcode::LoadLongI &&
+                        self.disassembly.variant() == Variant::XoChip => skipped.size,
+                    _ => 2,
+                };
+                vec![(next, Edge::Fallthrough), (next + skipped, Edge::Skip)]
+            }
+            _ => vec![(next, Edge::Fallthrough)],
+        };
+        successors.retain(|&(target, _)| self.disassembly.instruction(target).is_some());
+        successors
+    }
+
+    fn find_blocks(&mut self) {
+        let instructions = self.disassembly.instructions();
+        let mut leaders = BTreeSet::new();
+        leaders.insert(self.disassembly.load_address());
+        for (&address, decoded) in instructions {
+            let successors = self.successors(address);
+            let ends_block = match decoded.opcode {
+                Opcode::Call { nnn } => {
+                    leaders.insert(nnn as u32);
+                    true
+                }
+                Opcode::Jump { .. } | Opcode::JumpWithOffset { .. } | Opcode::Return | Opcode::Exit => true,
+                _ => decoded.opcode.is_skip(),
+            };
+            if ends_block {
+                leaders.insert(address + decoded.size);
+                leaders.extend(successors.iter().map(|&(target, _)| target));
+            }
+        }
+
+        for &start in &leaders {
+            if self.disassembly.instruction(start).is_none() {
+                continue;
+            }
+            let mut address = start;
+            loop {
+                let decoded = self.disassembly.instruction(address).unwrap();
+                let next = address + decoded.size;
+                let ends_here = !matches!(self.successors(address).as_slice(), [(_, Edge::Fallthrough)]) ||
+                    leaders.contains(&next) || matches!(decoded.opcode, Opcode::Call { .. });
+                if !ends_here {
+                    address = next;
+                    continue;
+                }
+                let call = match decoded.opcode {
+                    Opcode::Call { nnn } if self.disassembly.instruction(nnn as u32).is_some() => Some(nnn as u32),
+                    _ => None,
+                };
+                self.blocks.insert(start, Block { start, end: next, successors: self.successors(address), call });
+                break;
+            }
+        }
+
+        for block in self.blocks.values() {
+            for &(target, edge) in &block.successors {
+                if edge == Edge::Computed {
+                    self.findings.push(Finding::ComputedJump { address: block.end - 2, base: target });
+                }
+            }
+        }
+    }
+
+    // A function is every block reached from its entry without calling
+    // or running into another function.
+    fn find_calls(&mut self) {
+        let mut entries: BTreeSet<u32> = self.blocks.values().filter_map(|block| block.call).collect();
+        entries.insert(self.disassembly.load_address());
+        for &entry in &entries {
+            let mut callees = BTreeSet::new();
+            let mut seen = BTreeSet::new();
+            let mut pending = vec![entry];
+            while let Some(start) = pending.pop() {
+                if !seen.insert(start) {
+                    continue;
+                }
+                let block = match self.blocks.get(&start) {
+                    Some(block) => block,
+                    None => continue,
+                };
+                callees.extend(block.call);
+                pending.extend(block.successors.iter().map(|&(target, _)| target)
+                    .filter(|target| !entries.contains(target) || *target == entry));
+            }
+            self.calls.insert(entry, callees);
+        }
+    }
+
+    fn find_quirks(&mut self) {
+        for (&address, decoded) in self.disassembly.instructions() {
+            let quirks: &[&'static str] = match decoded.opcode {
+                Opcode::ShiftRight { x, y } | Opcode::ShiftLeft { x, y } if x != y => &["shift_uses_vy"],
+                Opcode::StoreRegisters { .. } | Opcode::LoadRegisters { .. } => &["load_store_increment"],
+                // B0NN reads V0 either way.
+                Opcode::JumpWithOffset { nnn } if nnn & 0xF00 != 0 => &["jump_uses_vx"],
+                Opcode::Or { .. } | Opcode::And { .. } | Opcode::Xor { .. } => &["logic_resets_vf"],
+                Opcode::Draw { .. } => &["clip_sprites", "display_wait"],
+                _ => &[],
+            };
+            for &quirk in quirks {
+                self.findings.push(Finding::QuirkSensitive { address, quirk });
+            }
+        }
+    }
+
+    // Follows I through the graph, as far as it is a constant, and flags
+    // stores made while it points at code. I is unknown on entry to a
+    // function and after anything that moves it by a register or a quirk.
+    fn find_self_modification(&mut self) {
+        let mut entry_index: BTreeMap<u32, Option<u32>> = BTreeMap::new();
+        let mut pending: Vec<(u32, Option<u32>)> = self.calls.keys().map(|&entry| (entry, None)).collect();
+        while let Some((start, index)) = pending.pop() {
+            let merged = match entry_index.get(&start) {
+                None => index,
+                Some(&known) if known == index => continue,
+                Some(_) => None,
+            };
+            if entry_index.get(&start) == Some(&merged) {
+                continue;
+            }
+            entry_index.insert(start, merged);
+            let block = match self.blocks.get(&start) {
+                Some(block) => block,
+                None => continue,
+            };
+            let after = self.track_index(block, merged, &mut Vec::new());
+            let after = if block.call.is_some() { None } else { after };
+            pending.extend(block.successors.iter().map(|&(target, _)| (target, after)));
+        }
+
+        let mut findings = Vec::new();
+        for (start, &index) in &entry_index {
+            if let Some(block) = self.blocks.get(start) {
+                self.track_index(block, index, &mut findings);
+            }
+        }
+        self.findings.extend(findings);
+    }
+
+    // I at the end of `block` given I at its start, noting stores into code.
+    fn track_index(&self, block: &Block, mut index: Option<u32>, findings: &mut Vec<Finding>) -> Option<u32> {
+        for (&address, decoded) in self.disassembly.instructions().range(block.start..block.end) {
+            let written = match decoded.opcode {
+                Opcode::StoreRegisters { x } => Some(x as u32 + 1),
+                Opcode::StoreBcd { .. } => Some(3),
+                Opcode::SaveRange { x, y } => Some((x as i32 - y as i32).unsigned_abs() + 1),
+                _ => None,
+            };
+            if let (Some(start), Some(length)) = (index, written) {
+                let end = start + length;
+                if (start..end).any(|target| self.disassembly.is_code(target)) {
+                    findings.push(Finding::SelfModifying { address, start, end });
+                }
+            }
+            index = match decoded.opcode {
+                Opcode::LoadI { nnn } => Some(nnn as u32),
+                Opcode::LoadLongI | Opcode::LoadWideI { .. } => Some(decoded.long_address),
+                Opcode::AddToI { .. } | Opcode::LoadFont { .. } | Opcode::LoadBigFont { .. } |
+                Opcode::StoreRegisters { .. } | Opcode::LoadRegisters { .. } => None,
+                _ => index,
+            };
+        }
+        index
+    }
+
+    // Gaps between the code, up to the first data anything refers to,
+    // which decode from end to end are most likely code nothing jumps to
+    // any more.
+    fn find_unreachable(&mut self) {
+        let disassembly = self.disassembly;
+        let rom = disassembly.rom();
+        let mut offset = 0;
+        while offset < rom.len() {
+            let address = disassembly.load_address() + offset as u32;
+            if disassembly.is_code(address) {
+                offset += 1;
+                continue;
+            }
+            let mut end = offset;
+            while end < rom.len() && !disassembly.is_code(disassembly.load_address() + end as u32) {
+                end += 1;
+            }
+            let end_address = disassembly.load_address() + end as u32;
+            let data = disassembly.labels().range(address..end_address).next()
+                .map_or(end_address, |(&label, _)| label);
+            let data_offset = (data - disassembly.load_address()) as usize;
+            let mut cursor = offset;
+            while cursor < data_offset {
+                match decode(rom, cursor, disassembly.variant()) {
+                    Some(decoded) if cursor + decoded.size as usize <= data_offset => cursor += decoded.size as usize,
+                    _ => break,
+                }
+            }
+            if cursor == data_offset && data > address {
+                self.findings.push(Finding::Unreachable { start: address, end: data });
+            }
+            offset = end;
+        }
+    }
+
+    fn name(&self, address: u32) -> String {
+        self.disassembly.label(address).unwrap_or_else(|| format!("#{:03X}", address))
+    }
+
+    // What was found, as text: the call graph, then the findings.
+    pub fn report(&self) -> String {
+        let mut report = format!("{} basic blocks, {} functions\n", self.blocks.len(), self.calls.len());
+        for (&entry, callees) in &self.calls {
+            let callees: Vec<String> = callees.iter().map(|&callee| self.name(callee)).collect();
+            if callees.is_empty() {
+                report.push_str(&format!("{} calls nothing\n", self.name(entry)));
+            } else {
+                report.push_str(&format!("{} calls {}\n", self.name(entry), callees.join(", ")));
+            }
+        }
+        for finding in &self.findings {
+            report.push_str(&format!("{}\n", finding));
+        }
+        report
+    }
+
+    // The graph in Graphviz DOT. Blocks are boxes listing their code, and
+    // calls are dotted edges into the subroutine. Blocks with a finding
+    // are drawn in red.
+    pub fn dot(&self) -> String {
+        let mut dot = "digraph rom {\n    node [shape=box, fontname=\"monospace\"];\n".to_string();
+        for block in self.blocks.values() {
+            let mut label = match self.disassembly.label(block.start) {
+                Some(name) => format!("{}:\\l", name),
+                None => String::new(),
+            };
+            for (&address, decoded) in self.disassembly.instructions().range(block.start..block.end) {
+                label.push_str(&format!("#{:03X}  {}\\l", address, self.disassembly.render(decoded)));
+            }
+            let flagged = self.findings.iter()
+                .any(|finding| (block.start..block.end).contains(&finding.address()) &&
+                     !matches!(finding, Finding::QuirkSensitive { .. }));
+            let colour = if flagged { ", color=red" } else { "" };
+            dot.push_str(&format!("    b{:03X} [label=\"{}\"{}];\n", block.start, label.replace('"', "\\\""), colour));
+        }
+        for block in self.blocks.values() {
+            for &(target, edge) in &block.successors {
+                let style = match edge {
+                    Edge::Fallthrough => "",
+                    Edge::Jump => " [label=\"jump\"]",
+                    Edge::Skip => " [label=\"skip\"]",
+                    Edge::Computed => " [label=\"computed\", style=dashed]",
+                };
+                dot.push_str(&format!("    b{:03X} -> b{:03X}{};\n", block.start, target, style));
+            }
+            if let Some(callee) = block.call {
+                dot.push_str(&format!("    b{:03X} -> b{:03X} [label=\"call\", style=dotted];\n", block.start, callee));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
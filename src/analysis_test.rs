@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod analysis_test {
+
+    use analysis::{ analyse, Edge, Finding };
+    use assembler::assemble;
+    use disassembler::{ disassemble, Disassembly };
+    use platform::Variant;
+
+    fn disassembled(source: &str) -> Disassembly {
+        let assembly = assemble(source).unwrap_or_else(|error| panic!("{}", error));
+        disassemble(&assembly.rom, 0x200, Variant::Chip8)
+    }
+
+    #[test]
+    fn blocks_end_at_branches_calls_and_their_targets() {
+        let disassembly = disassembled("    CALL sub\n    SE V0, 1\n    JP other\n    LD V1, 1\nother:\n    ADD V0, 1\n    JP other\nsub:\n    RET");
+        let analysis = analyse(&disassembly);
+        let blocks = analysis.blocks();
+
+        assert_eq!(blocks.keys().cloned().collect::<Vec<_>>(), vec![0x200, 0x202, 0x204, 0x206, 0x208, 0x20C]);
+        assert_eq!(blocks[&0x200].call, Some(0x20C));
+        assert_eq!(blocks[&0x200].successors, vec![(0x202, Edge::Fallthrough)]);
+        assert_eq!(blocks[&0x202].successors, vec![(0x204, Edge::Fallthrough), (0x206, Edge::Skip)]);
+        assert_eq!(blocks[&0x208].end, 0x20C);
+        assert_eq!(blocks[&0x208].successors, vec![(0x208, Edge::Jump)]);
+        assert!(blocks[&0x20C].successors.is_empty());
+    }
+
+    #[test]
+    fn the_call_graph_lists_what_each_function_calls() {
+        let disassembly = disassembled("    CALL first\nhalt:\n    JP halt\nfirst:\n    CALL second\n    CALL second\n    RET\nsecond:\n    RET");
+        let calls = analyse(&disassembly).calls().clone();
+
+        assert_eq!(calls.keys().cloned().collect::<Vec<_>>(), vec![0x200, 0x204, 0x20A]);
+        assert_eq!(calls[&0x200].iter().cloned().collect::<Vec<_>>(), vec![0x204]);
+        assert_eq!(calls[&0x204].iter().cloned().collect::<Vec<_>>(), vec![0x20A]);
+        assert!(calls[&0x20A].is_empty());
+    }
+
+    #[test]
+    fn computed_jumps_and_quirky_instructions_are_flagged() {
+        let disassembly = disassembled("    SHR V1, V2\n    SHR V3, V3\n    OR V0, V1\n    JP V0, table\ntable:\n    JP #321");
+        let findings = analyse(&disassembly).findings().to_vec();
+
+        assert_eq!(findings, vec![
+            Finding::QuirkSensitive { address: 0x200, quirk: "shift_uses_vy" },
+            Finding::QuirkSensitive { address: 0x204, quirk: "logic_resets_vf" },
+            Finding::ComputedJump { address: 0x206, base: 0x208 },
+            Finding::QuirkSensitive { address: 0x206, quirk: "jump_uses_vx" },
+        ]);
+    }
+
+    #[test]
+    fn stores_through_i_into_code_are_self_modifying() {
+        let source = "    CALL patch\nhere:\n    LD V0, 1\n    JP here\npatch:\n    LD I, here\n    LD B, V0\n    LD I, buffer\n    LD [I], V1\n    RET\nbuffer:\n    DB 0, 0";
+        let findings = analyse(&disassembled(source)).findings().to_vec();
+
+        assert!(findings.contains(&Finding::SelfModifying { address: 0x208, start: 0x202, end: 0x205 }), "{:?}", findings);
+        assert_eq!(findings.iter().filter(|finding| matches!(finding, Finding::SelfModifying { .. })).count(), 1);
+    }
+
+    #[test]
+    fn code_nothing_reaches_is_unreachable_but_sprites_are_not() {
+        let source = "    LD I, ball\nhalt:\n    JP halt\n    LD V0, 1\n    RET\nball:\n    DB #3C, #42";
+        let findings = analyse(&disassembled(source)).findings().to_vec();
+
+        assert_eq!(findings, vec![Finding::Unreachable { start: 0x204, end: 0x208 }]);
+    }
+
+    #[test]
+    fn dot_draws_blocks_with_their_code_and_edges() {
+        let dot = analyse(&disassembled("    CALL sub\nhalt:\n    JP halt\nsub:\n    RET")).dot();
+
+        assert!(dot.starts_with("digraph rom {\n"), "{}", dot);
+        assert!(dot.contains("    b204 [label=\"sub_204:\\l#204  RET\\l\"];\n"), "{}", dot);
+        assert!(dot.contains("    b200 -> b204 [label=\"call\", style=dotted];\n"), "{}", dot);
+        assert!(dot.contains("    b202 -> b202 [label=\"jump\"];\n"), "{}", dot);
+        assert!(dot.ends_with("}\n"));
+    }
+}
@@ -0,0 +1,738 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use cpu::START;
+use instruction::Opcode;
+
+// Assembles the language the disassembler writes:
+//
+//     ; comments run to the end of the line
+//     SPEED = 3                 ; constants, also `SPEED EQU 3`
+//         ORG #200              ; where what follows goes
+//     loop:                     ; labels
+//         LD V0, SPEED * 2      ; expressions
+//         LD I, ball
+//         DRW V0, V1, 4
+//         JP loop
+//     ball:
+//         DB 0b11110000, #F0    ; bytes, also "strings"
+//         DW #1234              ; big endian words
+//         INCLUDE "more.asm"    ; relative to the including file
+//
+// Numbers are decimal, hex after `#`, `$` or `0x`, or binary after
+// `0b`. Expressions have the usual C operators, precedence included.
+// Mnemonics and registers may be in any case, symbols may not.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembleError {
+    pub file: String,
+    // Line and column count from 1. Errors about a whole file, such as
+    // not being able to read it, have line 0.
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{}:{}:{}: {}", self.file, self.line, self.column, self.message)
+        }
+    }
+}
+
+impl error::Error for AssembleError {}
+
+// A ROM fresh from the assembler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembly {
+    // Address of the first byte of `rom`.
+    pub origin: u32,
+    pub rom: Vec<u8>,
+    // Every label and constant with its value.
+    pub symbols: BTreeMap<String, i64>,
+}
+
+impl Assembly {
+    // The symbols as `name = value` lines, by value, which the assembler
+    // can include and debuggers can read. Negative values are written in
+    // decimal, as hex would print them in two's complement.
+    pub fn symbol_file(&self) -> String {
+        let mut symbols: Vec<(&String, &i64)> = self.symbols.iter().collect();
+        symbols.sort_by_key(|&(name, value)| (*value, name.clone()));
+        symbols.iter()
+            .map(|&(name, &value)| match value {
+                value if value < 0 => format!("{} = {}\n", name, value),
+                value => format!("{} = #{:03X}\n", name, value),
+            })
+            .collect()
+    }
+}
+
+// Assembles `source`. Includes are looked up from the current directory.
+pub fn assemble(source: &str) -> Result<Assembly, AssembleError> {
+    let mut assembler = Assembler::new();
+    assembler.read(source, "<source>", Path::new(""))?;
+    assembler.finish()
+}
+
+pub fn assemble_file(path: &Path) -> Result<Assembly, AssembleError> {
+    let mut assembler = Assembler::new();
+    assembler.read_file(path, None)?;
+    assembler.finish()
+}
+
+// Includes nested deeper than this are taken to be a loop.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+// Constants defined in terms of each other deeper than this are too.
+const MAX_CONSTANT_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Location {
+    file: String,
+    line: usize,
+    column: usize,
+}
+
+impl Location {
+    fn at(&self, column: usize) -> Location {
+        Location { column, ..self.clone() }
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, AssembleError> {
+        Err(AssembleError { file: self.file.clone(), line: self.line, column: self.column, message })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Name(String),
+    Number(i64),
+    Text(String),
+    Punctuation(&'static str),
+}
+
+#[derive(Debug, Clone)]
+struct Spanned {
+    token: Token,
+    column: usize,
+}
+
+const PUNCTUATION: &[&str] = &[
+    "<<", ">>", "+", "-", "*", "/", "%", "&", "|", "^", "~", "(", ")", ",", ":", "[", "]", "=",
+];
+
+fn tokenize(text: &str, location: &Location) -> Result<Vec<Spanned>, AssembleError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let here = location.at(start + 1);
+        let token = if c.is_whitespace() {
+            i += 1;
+            continue;
+        } else if c == ';' {
+            break;
+        } else if c.is_alphabetic() || c == '_' || c == '.' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                i += 1;
+            }
+            Token::Name(chars[start..i].iter().collect())
+        } else if c.is_ascii_digit() || c == '#' || c == '$' {
+            let (radix, skip) = match (c, chars.get(i + 1)) {
+                ('#', _) | ('$', _) => (16, 1),
+                ('0', Some('x')) | ('0', Some('X')) => (16, 2),
+                ('0', Some('b')) | ('0', Some('B')) => (2, 2),
+                _ => (10, 0),
+            };
+            i += skip;
+            let digits_start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let digits: String = chars[digits_start..i].iter().filter(|&&c| c != '_').collect();
+            match i64::from_str_radix(&digits, radix) {
+                Ok(value) => Token::Number(value),
+                Err(_) => return here.error(format!("bad number {}", chars[start..i].iter().collect::<String>())),
+            }
+        } else if c == '"' {
+            i += 1;
+            let text_start = i;
+            while i < chars.len() && chars[i] != '"' {
+                i += 1;
+            }
+            if i == chars.len() {
+                return here.error("unterminated string".to_string());
+            }
+            i += 1;
+            Token::Text(chars[text_start..i - 1].iter().collect())
+        } else {
+            let rest: String = chars[i..].iter().take(2).collect();
+            match PUNCTUATION.iter().find(|&&punctuation| rest.starts_with(punctuation)) {
+                Some(&punctuation) => {
+                    i += punctuation.len();
+                    Token::Punctuation(punctuation)
+                }
+                None => return here.error(format!("unexpected character {}", c)),
+            }
+        };
+        tokens.push(Spanned { token, column: start + 1 });
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expression {
+    Number(i64),
+    Symbol(String, usize),
+    Unary(&'static str, Box<Expression>),
+    Binary(&'static str, Box<Expression>, Box<Expression>),
+}
+
+// Binary operators from the loosest to the tightest.
+const PRECEDENCE: &[&[&str]] = &[
+    &["|"],
+    &["^"],
+    &["&"],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+// Reads an expression from tokens, which must all be used up.
+struct ExpressionParser<'a> {
+    tokens: &'a [Spanned],
+    position: usize,
+    location: &'a Location,
+    // Where to point when the tokens run out.
+    end_column: usize,
+}
+
+impl<'a> ExpressionParser<'a> {
+    fn parse(tokens: &'a [Spanned], location: &'a Location, end_column: usize) -> Result<Expression, AssembleError> {
+        let mut parser = ExpressionParser { tokens, position: 0, location, end_column };
+        let expression = parser.binary(0)?;
+        match parser.tokens.get(parser.position) {
+            Some(token) => location.at(token.column).error("expected an operator".to_string()),
+            None => Ok(expression),
+        }
+    }
+
+    fn column(&self) -> usize {
+        self.tokens.get(self.position).map_or(self.end_column, |token| token.column)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expression, AssembleError> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        while let Some(&Spanned { token: Token::Punctuation(operator), .. }) = self.tokens.get(self.position) {
+            if !PRECEDENCE[level].contains(&operator) {
+                break;
+            }
+            self.position += 1;
+            let right = self.binary(level + 1)?;
+            left = Expression::Binary(operator, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expression, AssembleError> {
+        let column = self.column();
+        let token = match self.tokens.get(self.position) {
+            Some(token) => token.token.clone(),
+            None => return self.location.at(column).error("expected a value".to_string()),
+        };
+        self.position += 1;
+        match token {
+            Token::Number(value) => Ok(Expression::Number(value)),
+            Token::Name(name) => Ok(Expression::Symbol(name, column)),
+            Token::Punctuation(operator @ "-") | Token::Punctuation(operator @ "~") =>
+                Ok(Expression::Unary(operator, Box::new(self.unary()?))),
+            Token::Punctuation("(") => {
+                let expression = self.binary(0)?;
+                match self.tokens.get(self.position) {
+                    Some(&Spanned { token: Token::Punctuation(")"), .. }) => {
+                        self.position += 1;
+                        Ok(expression)
+                    }
+                    _ => self.location.at(self.column()).error("expected )".to_string()),
+                }
+            }
+            _ => self.location.at(column).error("expected a value".to_string()),
+        }
+    }
+}
+
+// An expression and the column it starts at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Value {
+    expression: Expression,
+    column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Operand {
+    V(u8),
+    I,
+    // [I]
+    Memory,
+    Dt,
+    St,
+    K,
+    F,
+    Hf,
+    B,
+    R,
+    Long(Value),
+    Value(Value),
+}
+
+// Names an operand can have, which are therefore no good as symbols.
+fn keyword(name: &str) -> Option<Operand> {
+    let upper = name.to_ascii_uppercase();
+    let operand = match upper.as_str() {
+        "I" => Operand::I,
+        "DT" => Operand::Dt,
+        "ST" => Operand::St,
+        "K" => Operand::K,
+        "F" => Operand::F,
+        "HF" => Operand::Hf,
+        "B" => Operand::B,
+        "R" => Operand::R,
+        _ if upper.len() == 2 && upper.starts_with('V') =>
+            Operand::V(u8::from_str_radix(&upper[1..], 16).ok()?),
+        _ => return None,
+    };
+    Some(operand)
+}
+
+enum Data {
+    Value(Value),
+    Text(String),
+}
+
+enum Contents {
+    Instruction(String, Vec<Operand>),
+    Bytes(Vec<Data>),
+    Words(Vec<Value>),
+}
+
+struct Statement {
+    location: Location,
+    address: u32,
+    contents: Contents,
+}
+
+struct Assembler {
+    statements: Vec<Statement>,
+    labels: HashMap<String, i64>,
+    constants: HashMap<String, (Expression, Location)>,
+    address: u32,
+    origin: Option<u32>,
+    depth: usize,
+}
+
+impl Assembler {
+    fn new() -> Assembler {
+        Assembler {
+            statements: Vec::new(),
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            address: START as u32,
+            origin: None,
+            depth: 0,
+        }
+    }
+
+    fn read_file(&mut self, path: &Path, from: Option<&Location>) -> Result<(), AssembleError> {
+        let file = path.display().to_string();
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                let location = from.cloned().unwrap_or(Location { file, line: 0, column: 0 });
+                return location.error(format!("could not read {}: {}", path.display(), error));
+            }
+        };
+        if self.depth == MAX_INCLUDE_DEPTH {
+            return from.unwrap().error(format!("includes nested too deeply at {}", file));
+        }
+        self.depth += 1;
+        let directory = path.parent().map_or_else(PathBuf::new, Path::to_path_buf);
+        self.read(&source, &file, &directory)?;
+        self.depth -= 1;
+        Ok(())
+    }
+
+    // The first pass: places every statement and label.
+    fn read(&mut self, source: &str, file: &str, directory: &Path) -> Result<(), AssembleError> {
+        for (number, text) in source.lines().enumerate() {
+            let location = Location { file: file.to_string(), line: number + 1, column: 1 };
+            let tokens = tokenize(text, &location)?;
+            self.read_line(&tokens, &location, text.chars().count() + 1, directory)?;
+        }
+        Ok(())
+    }
+
+    fn read_line(&mut self, tokens: &[Spanned], location: &Location, end_column: usize, directory: &Path)
+                 -> Result<(), AssembleError> {
+        let mut tokens = tokens;
+        if let Some(Spanned { token: Token::Name(name), column }) = tokens.first() {
+            let here = location.at(*column);
+            match tokens.get(1).map(|token| &token.token) {
+                Some(Token::Punctuation(":")) => {
+                    self.define(name, &here)?;
+                    self.labels.insert(name.clone(), self.address as i64);
+                    tokens = &tokens[2..];
+                }
+                Some(Token::Punctuation("=")) => {
+                    return self.define_constant(name, &tokens[2..], &here, end_column);
+                }
+                Some(Token::Name(equ)) if equ.eq_ignore_ascii_case("EQU") => {
+                    return self.define_constant(name, &tokens[2..], &here, end_column);
+                }
+                _ => {}
+            }
+        }
+
+        let (mnemonic, column) = match tokens.first() {
+            None => return Ok(()),
+            Some(Spanned { token: Token::Name(name), column }) => (name.to_ascii_uppercase(), *column),
+            Some(token) => return location.at(token.column).error("expected a mnemonic".to_string()),
+        };
+        let here = location.at(column);
+        let groups = operand_groups(&tokens[1..], &here, end_column)?;
+        let contents = match mnemonic.as_str() {
+            "ORG" => {
+                let address = match groups.as_slice() {
+                    [group] => self.evaluate(&value(group, location, end_column)?.expression, location, 0)?,
+                    _ => return here.error("ORG takes an address".to_string()),
+                };
+                if address < 0 || self.origin.is_some_and(|origin| address < origin as i64) {
+                    return here.error(format!("ORG #{:X} is below the start of the ROM", address));
+                }
+                self.address = address as u32;
+                if self.statements.is_empty() {
+                    self.origin = Some(self.address);
+                }
+                return Ok(());
+            }
+            "INCLUDE" => {
+                return match groups.as_slice() {
+                    [[Spanned { token: Token::Text(name), .. }]] => self.read_file(&directory.join(name), Some(&here)),
+                    _ => here.error("INCLUDE takes a file name in quotes".to_string()),
+                };
+            }
+            "DB" | "BYTE" => {
+                let mut data = Vec::new();
+                for group in &groups {
+                    data.push(match group {
+                        [Spanned { token: Token::Text(text), .. }] => Data::Text(text.clone()),
+                        _ => Data::Value(value(group, location, end_column)?),
+                    });
+                }
+                Contents::Bytes(data)
+            }
+            "DW" | "WORD" => {
+                let values = groups.iter().map(|group| value(group, location, end_column)).collect::<Result<_, _>>()?;
+                Contents::Words(values)
+            }
+            _ => {
+                let operands = groups.iter().map(|group| operand(group, location, end_column)).collect::<Result<_, _>>()?;
+                Contents::Instruction(mnemonic, operands)
+            }
+        };
+
+        let size = match contents {
+            Contents::Instruction(ref mnemonic, ref operands) => instruction_size(mnemonic, operands),
+            Contents::Bytes(ref data) => data.iter().map(|data| match *data {
+                Data::Value(_) => 1,
+                Data::Text(ref text) => text.len() as u32,
+            }).sum(),
+            Contents::Words(ref values) => 2 * values.len() as u32,
+        };
+        if self.origin.is_none() {
+            self.origin = Some(self.address);
+        }
+        self.statements.push(Statement { location: here, address: self.address, contents });
+        self.address += size;
+        Ok(())
+    }
+
+    fn define(&self, name: &str, location: &Location) -> Result<(), AssembleError> {
+        if keyword(name).is_some() {
+            return location.error(format!("{} is a register name", name));
+        }
+        if self.labels.contains_key(name) || self.constants.contains_key(name) {
+            return location.error(format!("{} is already defined", name));
+        }
+        Ok(())
+    }
+
+    fn define_constant(&mut self, name: &str, tokens: &[Spanned], location: &Location, end_column: usize)
+                       -> Result<(), AssembleError> {
+        self.define(name, location)?;
+        let value = value(tokens, location, end_column)?;
+        self.constants.insert(name.to_string(), (value.expression, location.clone()));
+        Ok(())
+    }
+
+    fn evaluate(&self, expression: &Expression, location: &Location, depth: usize) -> Result<i64, AssembleError> {
+        let value = match *expression {
+            Expression::Number(value) => value,
+            Expression::Symbol(ref name, column) => {
+                if let Some(&value) = self.labels.get(name) {
+                    return Ok(value);
+                }
+                match self.constants.get(name) {
+                    Some(_) if depth == MAX_CONSTANT_DEPTH =>
+                        return location.at(column).error(format!("{} is defined in terms of itself", name)),
+                    Some((expression, defined)) => self.evaluate(expression, defined, depth + 1)?,
+                    None => return location.at(column).error(format!("unknown symbol {}", name)),
+                }
+            }
+            Expression::Unary(operator, ref operand) => {
+                let operand = self.evaluate(operand, location, depth)?;
+                if operator == "-" { operand.wrapping_neg() } else { !operand }
+            }
+            Expression::Binary(operator, ref left, ref right) => {
+                let left = self.evaluate(left, location, depth)?;
+                let right = self.evaluate(right, location, depth)?;
+                match operator {
+                    "|" => left | right,
+                    "^" => left ^ right,
+                    "&" => left & right,
+                    "<<" => left.wrapping_shl(right as u32),
+                    ">>" => left.wrapping_shr(right as u32),
+                    "+" => left.wrapping_add(right),
+                    "-" => left.wrapping_sub(right),
+                    "*" => left.wrapping_mul(right),
+                    _ if right == 0 => return location.error("division by zero".to_string()),
+                    "/" => left.wrapping_div(right),
+                    _ => left.wrapping_rem(right),
+                }
+            }
+        };
+        Ok(value)
+    }
+
+    // `value` if it lies in `min..=max`.
+    fn ranged(&self, value: &Value, location: &Location, min: i64, max: i64, what: &str) -> Result<i64, AssembleError> {
+        let number = self.evaluate(&value.expression, location, 0)?;
+        if number < min || number > max {
+            return location.at(value.column).error(format!("{} does not fit in {}", number, what));
+        }
+        Ok(number)
+    }
+
+    fn byte(&self, value: &Value, location: &Location) -> Result<u8, AssembleError> {
+        Ok(self.ranged(value, location, -0x80, 0xFF, "a byte")? as u8)
+    }
+
+    fn word(&self, value: &Value, location: &Location) -> Result<u16, AssembleError> {
+        Ok(self.ranged(value, location, -0x8000, 0xFFFF, "a word")? as u16)
+    }
+
+    fn address(&self, value: &Value, location: &Location) -> Result<u16, AssembleError> {
+        Ok(self.ranged(value, location, 0, 0xFFF, "12 bits")? as u16)
+    }
+
+    fn nibble(&self, value: &Value, location: &Location) -> Result<u8, AssembleError> {
+        Ok(self.ranged(value, location, 0, 0xF, "a nibble")? as u8)
+    }
+
+    // The second pass: evaluates everything now that all labels are known.
+    fn finish(self) -> Result<Assembly, AssembleError> {
+        let origin = self.origin.unwrap_or(START as u32);
+        let mut rom = Vec::new();
+        for statement in &self.statements {
+            let location = &statement.location;
+            let bytes = match statement.contents {
+                Contents::Instruction(ref mnemonic, ref operands) => self.encode(mnemonic, operands, location)?
+                    .iter()
+                    .flat_map(|word| vec![(word >> 8) as u8, *word as u8])
+                    .collect(),
+                Contents::Bytes(ref data) => {
+                    let mut bytes = Vec::new();
+                    for data in data {
+                        match *data {
+                            Data::Value(ref value) => bytes.push(self.byte(value, location)?),
+                            Data::Text(ref text) => bytes.extend(text.bytes()),
+                        }
+                    }
+                    bytes
+                }
+                Contents::Words(ref values) => {
+                    let mut bytes = Vec::new();
+                    for value in values {
+                        let word = self.word(value, location)?;
+                        bytes.extend(&[(word >> 8) as u8, word as u8]);
+                    }
+                    bytes
+                }
+            };
+            let offset = (statement.address - origin) as usize;
+            if rom.len() < offset + bytes.len() {
+                rom.resize(offset + bytes.len(), 0);
+            }
+            rom[offset..offset + bytes.len()].copy_from_slice(&bytes);
+        }
+
+        let mut symbols: BTreeMap<String, i64> = self.labels.clone().into_iter().collect();
+        for (name, (expression, location)) in &self.constants {
+            symbols.insert(name.clone(), self.evaluate(expression, location, 0)?);
+        }
+        Ok(Assembly { origin, rom, symbols })
+    }
+
+    // The words of one instruction.
+    fn encode(&self, mnemonic: &str, operands: &[Operand], location: &Location) -> Result<Vec<u16>, AssembleError> {
+        use self::Operand::*;
+
+        let at = location;
+        let opcode = match (mnemonic, operands) {
+            ("CLS", []) => Opcode::ClearScreen,
+            ("RET", []) => Opcode::Return,
+            ("SYS", [Value(a)]) => Opcode::Sys { nnn: self.address(a, at)? },
+            ("JP", [Value(a)]) => Opcode::Jump { nnn: self.address(a, at)? },
+            ("JP", [V(0), Value(a)]) => Opcode::JumpWithOffset { nnn: self.address(a, at)? },
+            ("CALL", [Value(a)]) => Opcode::Call { nnn: self.address(a, at)? },
+            ("SE", [V(x), V(y)]) => Opcode::SkipIfRegistersEqual { x: *x, y: *y },
+            ("SE", [V(x), Value(nn)]) => Opcode::SkipIfEqual { x: *x, nn: self.byte(nn, at)? },
+            ("SNE", [V(x), V(y)]) => Opcode::SkipIfRegistersNotEqual { x: *x, y: *y },
+            ("SNE", [V(x), Value(nn)]) => Opcode::SkipIfNotEqual { x: *x, nn: self.byte(nn, at)? },
+            ("LD", [V(x), V(y)]) => Opcode::Move { x: *x, y: *y },
+            ("LD", [V(x), Value(nn)]) => Opcode::Load { x: *x, nn: self.byte(nn, at)? },
+            ("LD", [I, Value(a)]) => Opcode::LoadI { nnn: self.address(a, at)? },
+            ("LD", [I, Long(a)]) => return Ok(vec![Opcode::LoadLongI.encode(), self.word(a, at)?]),
+            ("LD", [V(x), Dt]) => Opcode::ReadDelayTimer { x: *x },
+            ("LD", [V(x), K]) => Opcode::WaitForKey { x: *x },
+            ("LD", [Dt, V(x)]) => Opcode::SetDelayTimer { x: *x },
+            ("LD", [St, V(x)]) => Opcode::SetSoundTimer { x: *x },
+            ("LD", [F, V(x)]) => Opcode::LoadFont { x: *x },
+            ("LD", [Hf, V(x)]) => Opcode::LoadBigFont { x: *x },
+            ("LD", [B, V(x)]) => Opcode::StoreBcd { x: *x },
+            ("LD", [Memory, V(x)]) => Opcode::StoreRegisters { x: *x },
+            ("LD", [V(x), Memory]) => Opcode::LoadRegisters { x: *x },
+            ("LD", [R, V(x)]) => Opcode::SaveFlags { x: *x },
+            ("LD", [V(x), R]) => Opcode::LoadFlags { x: *x },
+            ("ADD", [V(x), V(y)]) => Opcode::AddRegisters { x: *x, y: *y },
+            ("ADD", [V(x), Value(nn)]) => Opcode::Add { x: *x, nn: self.byte(nn, at)? },
+            ("ADD", [I, V(x)]) => Opcode::AddToI { x: *x },
+            ("OR", [V(x), V(y)]) => Opcode::Or { x: *x, y: *y },
+            ("AND", [V(x), V(y)]) => Opcode::And { x: *x, y: *y },
+            ("XOR", [V(x), V(y)]) => Opcode::Xor { x: *x, y: *y },
+            ("SUB", [V(x), V(y)]) => Opcode::Subtract { x: *x, y: *y },
+            ("SUBN", [V(x), V(y)]) => Opcode::SubtractReversed { x: *x, y: *y },
+            ("SHR", [V(x), V(y)]) => Opcode::ShiftRight { x: *x, y: *y },
+            ("SHR", [V(x)]) => Opcode::ShiftRight { x: *x, y: *x },
+            ("SHL", [V(x), V(y)]) => Opcode::ShiftLeft { x: *x, y: *y },
+            ("SHL", [V(x)]) => Opcode::ShiftLeft { x: *x, y: *x },
+            ("RND", [V(x), Value(nn)]) => Opcode::Random { x: *x, nn: self.byte(nn, at)? },
+            ("DRW", [V(x), V(y), Value(n)]) => Opcode::Draw { x: *x, y: *y, n: self.nibble(n, at)? },
+            ("SKP", [V(x)]) => Opcode::SkipIfKeyPressed { x: *x },
+            ("SKNP", [V(x)]) => Opcode::SkipIfKeyNotPressed { x: *x },
+            ("SCD", [Value(n)]) => Opcode::ScrollDown { n: self.nibble(n, at)? },
+            ("SCR", []) => Opcode::ScrollRight,
+            ("SCL", []) => Opcode::ScrollLeft,
+            ("EXIT", []) => Opcode::Exit,
+            ("LOW", []) => Opcode::LowResolution,
+            ("HIGH", []) => Opcode::HighResolution,
+            ("SCU", [Value(n)]) => Opcode::ScrollUp { n: self.nibble(n, at)? },
+            ("SAVE", [V(x), V(y)]) => Opcode::SaveRange { x: *x, y: *y },
+            ("LOAD", [V(x), V(y)]) => Opcode::LoadRange { x: *x, y: *y },
+            ("PLANE", [Value(n)]) => Opcode::SelectPlanes { n: self.nibble(n, at)? },
+            ("AUDIO", []) => Opcode::LoadAudio,
+            ("PITCH", [V(x)]) => Opcode::SetPitch { x: *x },
+            ("CBG", []) => Opcode::CycleBackground,
+            ("ADDN", [V(x), V(y)]) => Opcode::AddNibbles { x: *x, y: *y },
+            ("COL", [V(x), V(y)]) => Opcode::ColourZones { x: *x, y: *y },
+            ("COL", [V(x), V(y), Value(n)]) => Opcode::ColourRows { x: *x, y: *y, n: self.nibble(n, at)? },
+            ("SKP2", [V(x)]) => Opcode::SkipIfSecondKeyPressed { x: *x },
+            ("SKNP2", [V(x)]) => Opcode::SkipIfSecondKeyNotPressed { x: *x },
+            ("OUT", [V(x)]) => Opcode::OutputPort { x: *x },
+            ("IN", [V(x)]) => Opcode::InputPort { x: *x },
+            ("MEGAOFF", []) => Opcode::MegaOff,
+            ("MEGAON", []) => Opcode::MegaOn,
+            ("LDHI", [Value(a)]) => {
+                let address = self.ranged(a, at, 0, 0xFF_FFFF, "24 bits")?;
+                return Ok(vec![Opcode::LoadWideI { nn: (address >> 16) as u8 }.encode(), address as u16]);
+            }
+            ("LDPAL", [Value(nn)]) => Opcode::LoadPalette { nn: self.byte(nn, at)? },
+            ("SPRW", [Value(nn)]) => Opcode::SpriteWidth { nn: self.byte(nn, at)? },
+            ("SPRH", [Value(nn)]) => Opcode::SpriteHeight { nn: self.byte(nn, at)? },
+            ("ALPHA", [Value(nn)]) => Opcode::ScreenAlpha { nn: self.byte(nn, at)? },
+            ("DIGISND", [Value(n)]) => Opcode::PlaySample { n: self.nibble(n, at)? },
+            ("STOPSND", []) => Opcode::StopSample,
+            ("BMODE", [Value(n)]) => Opcode::SetBlendMode { n: self.nibble(n, at)? },
+            ("CCOL", [Value(nn)]) => Opcode::CollisionColour { nn: self.byte(nn, at)? },
+            _ if MNEMONICS.contains(&mnemonic) => return at.error(format!("wrong operands for {}", mnemonic)),
+            _ => return at.error(format!("unknown mnemonic {}", mnemonic)),
+        };
+        Ok(vec![opcode.encode()])
+    }
+}
+
+const MNEMONICS: &[&str] = &[
+    "CLS", "RET", "SYS", "JP", "CALL", "SE", "SNE", "LD", "ADD", "OR", "AND", "XOR", "SUB", "SUBN", "SHR",
+    "SHL", "RND", "DRW", "SKP", "SKNP", "SCD", "SCR", "SCL", "EXIT", "LOW", "HIGH", "SCU", "SAVE", "LOAD",
+    "PLANE", "AUDIO", "PITCH", "CBG", "ADDN", "COL", "SKP2", "SKNP2", "OUT", "IN", "MEGAOFF", "MEGAON",
+    "LDHI", "LDPAL", "SPRW", "SPRH", "ALPHA", "DIGISND", "STOPSND", "BMODE", "CCOL",
+];
+
+// Sizes must be known in the first pass, before operands can be worked out.
+fn instruction_size(mnemonic: &str, operands: &[Operand]) -> u32 {
+    match (mnemonic, operands) {
+        ("LD", [Operand::I, Operand::Long(_)]) | ("LDHI", _) => 4,
+        _ => 2,
+    }
+}
+
+// Splits operands at their commas.
+fn operand_groups<'a>(tokens: &'a [Spanned], location: &Location, end_column: usize)
+                      -> Result<Vec<&'a [Spanned]>, AssembleError> {
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
+    let groups: Vec<&[Spanned]> = tokens.split(|token| token.token == Token::Punctuation(",")).collect();
+    for (index, group) in groups.iter().enumerate() {
+        if group.is_empty() {
+            // Point at the comma after the gap, or the end of the line.
+            let column = tokens.iter().filter(|token| token.token == Token::Punctuation(","))
+                .nth(index).map_or(end_column, |token| token.column);
+            return location.at(column).error("missing operand".to_string());
+        }
+    }
+    Ok(groups)
+}
+
+fn value(tokens: &[Spanned], location: &Location, end_column: usize) -> Result<Value, AssembleError> {
+    let column = tokens.first().map_or(end_column, |token| token.column);
+    let expression = ExpressionParser::parse(tokens, location, end_column)?;
+    Ok(Value { expression, column })
+}
+
+fn operand(tokens: &[Spanned], location: &Location, end_column: usize) -> Result<Operand, AssembleError> {
+    let names: Vec<Option<&str>> = tokens.iter().map(|token| match token.token {
+        Token::Name(ref name) => Some(name.as_str()),
+        Token::Punctuation(punctuation) => Some(punctuation),
+        _ => None,
+    }).collect();
+    match names.as_slice() {
+        [Some("["), Some(name), Some("]")] if name.eq_ignore_ascii_case("I") => Ok(Operand::Memory),
+        [Some(name)] if keyword(name).is_some() && matches!(tokens[0].token, Token::Name(_)) =>
+            Ok(keyword(name).unwrap()),
+        [Some(long), ..] if long.eq_ignore_ascii_case("LONG") && tokens.len() > 1 =>
+            Ok(Operand::Long(value(&tokens[1..], location, end_column)?)),
+        _ => Ok(Operand::Value(value(tokens, location, end_column)?)),
+    }
+}
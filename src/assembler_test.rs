@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod assembler_test {
+
+    use std::env;
+    use std::fs;
+
+    use assembler::{ assemble, assemble_file, AssembleError };
+    use disassembler::disassemble;
+    use platform::Variant;
+
+    fn error(line: usize, column: usize, message: &str) -> AssembleError {
+        AssembleError { file: "<source>".to_string(), line, column, message: message.to_string() }
+    }
+
+    #[test]
+    fn assembles_instructions_from_the_load_address() {
+        let assembly = assemble("    LD V5, #11\n    add v5, 1\n    LD [I], V5\n    JP #200").unwrap();
+
+        assert_eq!(assembly.origin, 0x200);
+        assert_eq!(assembly.rom, vec![0x65, 0x11, 0x75, 0x01, 0xF5, 0x55, 0x12, 0x00]);
+    }
+
+    #[test]
+    fn labels_may_be_used_before_they_are_defined() {
+        let assembly = assemble("start:\n    CALL draw\n    JP start\ndraw:\n    RET").unwrap();
+
+        assert_eq!(assembly.rom, vec![0x22, 0x04, 0x12, 0x00, 0x00, 0xEE]);
+        assert_eq!(assembly.symbols["draw"], 0x204);
+    }
+
+    #[test]
+    fn constants_and_expressions_are_worked_out() {
+        let source = "WIDTH = 8\nHEIGHT EQU WIDTH / 2\n    LD V0, (WIDTH + 1) * 2\n    LD V1, -1\n    DRW V0, V1, HEIGHT | 1";
+        let assembly = assemble(source).unwrap();
+
+        assert_eq!(assembly.rom, vec![0x60, 18, 0x61, 0xFF, 0xD0, 0x15]);
+        assert_eq!(assembly.symbols["HEIGHT"], 4);
+    }
+
+    #[test]
+    fn data_directives_emit_bytes_and_big_endian_words() {
+        let assembly = assemble("    ORG #300\n    DB 0b10100101, $0F, \"AB\"\n    DW #1234").unwrap();
+
+        assert_eq!(assembly.origin, 0x300);
+        assert_eq!(assembly.rom, vec![0xA5, 0x0F, b'A', b'B', 0x12, 0x34]);
+    }
+
+    #[test]
+    fn long_loads_take_four_bytes() {
+        let assembly = assemble("    LD I, LONG data\n    LDHI #012345\ndata:").unwrap();
+
+        assert_eq!(assembly.rom, vec![0xF0, 0x00, 0x02, 0x08, 0x01, 0x01, 0x23, 0x45]);
+    }
+
+    #[test]
+    fn errors_carry_their_line_and_column() {
+        assert_eq!(assemble("    CLS\n    FOO V0"), Err(error(2, 5, "unknown mnemonic FOO")));
+        assert_eq!(assemble("    JP nowhere"), Err(error(1, 8, "unknown symbol nowhere")));
+        assert_eq!(assemble("    LD V0, 256"), Err(error(1, 12, "256 does not fit in a byte")));
+        assert_eq!(assemble("    DRW V0, V1"), Err(error(1, 5, "wrong operands for DRW")));
+        assert_eq!(assemble("a:\na:"), Err(error(2, 1, "a is already defined")));
+        assert_eq!(assemble("    LD V0, (1"), Err(error(1, 14, "expected )")));
+        assert_eq!(assemble("    LD V0,, V1"), Err(error(1, 11, "missing operand")));
+        assert_eq!(assemble("    LD B, V0\nB:"), Err(error(2, 1, "B is a register name")));
+        assert!(assemble("X = Y\nY = X\n    LD V0, X").unwrap_err().message.ends_with("is defined in terms of itself"));
+    }
+
+    #[test]
+    fn includes_are_read_relative_to_the_including_file() {
+        let directory = env::temp_dir().join(format!("chip8-assembler-test-{}", ::std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("main.asm"), "    INCLUDE \"sprites.asm\"\n    LD I, ball\n").unwrap();
+        fs::write(directory.join("sprites.asm"), "BALL_ADDRESS = #300\nball = BALL_ADDRESS\n").unwrap();
+
+        let assembly = assemble_file(&directory.join("main.asm"));
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(assembly.unwrap().rom, vec![0xA3, 0x00]);
+    }
+
+    #[test]
+    fn symbol_files_list_symbols_by_value() {
+        let assembly = assemble("SIZE = 5\nstart:\n    JP start\nend:").unwrap();
+
+        assert_eq!(assembly.symbol_file(), "SIZE = #005\nstart = #200\nend = #202\n");
+    }
+
+    #[test]
+    fn symbol_files_can_be_included_back() {
+        let symbols = assemble("BACK = -2\nstart:\n    JP start").unwrap().symbol_file();
+        assert_eq!(symbols, "BACK = -2\nstart = #200\n");
+
+        let directory = env::temp_dir().join(format!("chip8-assembler-symbols-{}", ::std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("game.sym"), symbols).unwrap();
+        fs::write(directory.join("main.asm"), "    INCLUDE \"game.sym\"\n    ADD V0, BACK & #FF\n    JP start\n").unwrap();
+
+        let assembly = assemble_file(&directory.join("main.asm"));
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(assembly.unwrap().rom, vec![0x70, 0xFE, 0x12, 0x00]);
+    }
+
+    #[test]
+    fn disassembled_roms_reassemble_to_the_same_bytes() {
+        let programs: &[(&[u8], Variant)] = &[
+            // Code, a subroutine, a computed jump, sprites and stray data.
+            (&[0x00, 0xE0, 0xA2, 0x12, 0x60, 0x05, 0x22, 0x0C, 0x30, 0x05, 0xB2, 0x10,
+               0xD0, 0x15, 0x00, 0xEE, 0x12, 0x10, 0x3C, 0x42, 0xFF, 0x01, 0x02, 0x03], Variant::Chip8),
+            // SUPER-CHIP and XO-CHIP extras, with a skip over a long load.
+            (&[0x00, 0xFF, 0xF1, 0x30, 0x30, 0x00, 0xF0, 0x00, 0x02, 0x0E, 0x51, 0x22,
+               0x12, 0x0C, 0xAA], Variant::XoChip),
+            // An odd byte out at the end.
+            (&[0x12, 0x00, 0x7F], Variant::Chip8),
+        ];
+        for &(rom, variant) in programs {
+            let listing = disassemble(rom, 0x200, variant).listing();
+            let assembly = assemble(&listing).unwrap_or_else(|error| panic!("{}\n{}", error, listing));
+            assert_eq!(assembly.rom, rom.to_vec(), "\n{}", listing);
+            assert_eq!(assembly.origin, 0x200);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+// XO-CHIP sound: a 128 bit pattern played in a loop while the sound timer
+// runs, one bit per sample. The pitch register sets the sample rate.
+pub const PATTERN_SIZE: usize = 16;
+pub const DEFAULT_PITCH: u8 = 64;
+
+// Without a pattern loaded the buzzer plays a square wave.
+const DEFAULT_PATTERN: [u8; PATTERN_SIZE] = [
+    0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF,
+    0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF,
+];
+
+#[derive(Clone)]
+pub struct Audio {
+    pattern: [u8; PATTERN_SIZE],
+    pitch: u8,
+}
+
+impl Default for Audio {
+    fn default() -> Audio {
+        Audio::new()
+    }
+}
+
+impl Audio {
+    pub fn new() -> Audio {
+        Audio {
+            pattern: DEFAULT_PATTERN,
+            pitch: DEFAULT_PITCH,
+        }
+    }
+
+    pub fn pattern(&self) -> &[u8; PATTERN_SIZE] {
+        &self.pattern
+    }
+
+    pub fn set_pattern(&mut self, pattern: [u8; PATTERN_SIZE]) {
+        self.pattern = pattern;
+    }
+
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    pub fn set_pitch(&mut self, pitch: u8) {
+        self.pitch = pitch;
+    }
+
+    // Pattern bits per second: 4000 Hz at the default pitch, doubling
+    // every 48 steps above it.
+    pub fn sample_rate(&self) -> f64 {
+        4000.0 * 2f64.powf((self.pitch as f64 - 64.0) / 48.0)
+    }
+
+    // The pattern as one sample per bit, most significant bit first.
+    pub fn samples(&self) -> Vec<bool> {
+        self.pattern.iter()
+            .flat_map(|byte| (0..8).map(move |bit| byte & (0x80 >> bit) != 0))
+            .collect()
+    }
+}
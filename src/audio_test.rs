@@ -0,0 +1,37 @@
+#[cfg(test)]
+mod audio_test {
+
+    use audio::{ Audio, DEFAULT_PITCH };
+
+    #[test]
+    fn starts_with_a_square_wave_at_4000_hz() {
+        let audio = Audio::new();
+
+        assert_eq!(audio.pitch(), DEFAULT_PITCH);
+        assert_eq!(audio.sample_rate(), 4000.0);
+        assert_eq!(audio.samples().len(), 128);
+        assert!(!audio.samples()[0]);
+        assert!(audio.samples()[8]);
+    }
+
+    #[test]
+    fn pitch_doubles_the_rate_every_48_steps() {
+        let mut audio = Audio::new();
+        audio.set_pitch(DEFAULT_PITCH + 48);
+
+        assert_eq!(audio.sample_rate(), 8000.0);
+    }
+
+    #[test]
+    fn samples_follow_the_pattern_msb_first() {
+        let mut audio = Audio::new();
+        let mut pattern = [0; 16];
+        pattern[0] = 0x40;
+        audio.set_pattern(pattern);
+
+        let samples = audio.samples();
+        assert!(!samples[0]);
+        assert!(samples[1]);
+        assert_eq!(samples.iter().filter(|&&sample| sample).count(), 1);
+    }
+}
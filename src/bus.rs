@@ -0,0 +1,48 @@
+use ram::Ram;
+use display::Display;
+use keypad::Keypad;
+use audio::Audio;
+use colour::ColourBoard;
+use port::Port;
+use megachip::{MegaDisplay, SamplePlayer};
+use random::{RandomSource, SeededRandom};
+
+// Everything the CPU reaches outside its own registers.
+pub struct Bus {
+    pub ram: Ram,
+    pub display: Display,
+    pub keypad: Keypad,
+    pub audio: Audio,
+    // CHIP-8X hardware.
+    pub colour: ColourBoard,
+    pub second_keypad: Keypad,
+    pub port: Port,
+    // MegaChip8 hardware.
+    pub mega_display: MegaDisplay,
+    pub sampler: SamplePlayer,
+    // Feeds CXNN.
+    pub random: Box<dyn RandomSource>,
+}
+
+impl Default for Bus {
+    fn default() -> Bus {
+        Bus::new()
+    }
+}
+
+impl Bus {
+    pub fn new() -> Bus {
+        Bus {
+            ram: Ram::new(),
+            display: Display::new(),
+            keypad: Keypad::new(),
+            audio: Audio::new(),
+            colour: ColourBoard::new(),
+            second_keypad: Keypad::new(),
+            port: Port::new(),
+            mega_display: MegaDisplay::new(),
+            sampler: SamplePlayer::new(),
+            random: Box::new(SeededRandom::new()),
+        }
+    }
+}
@@ -0,0 +1,385 @@
+// RCA CDP1802 COSMAC microprocessor, the CPU of the COSMAC VIP.
+//
+// Every instruction takes two machine cycles of eight clocks each, except
+// the long branches and skips which take three.
+pub const REGISTER_COUNT: usize = 16;
+
+// Everything the 1802 reaches through its pins: memory, the N lines
+// driven by OUT and INP, and the EF1 to EF4 flag inputs.
+pub trait SystemBus {
+    fn read(&mut self, address: u16) -> u8;
+    fn write(&mut self, address: u16, value: u8);
+    // OUT 1 to 7 put a byte from memory on the data bus.
+    fn output(&mut self, port: u8, value: u8);
+    // INP 1 to 7 read a byte from the data bus.
+    fn input(&mut self, port: u8) -> u8;
+    // True while flag line EF1 to EF4 is asserted.
+    fn flag(&self, line: u8) -> bool;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cdp1802 {
+    r: [u16; REGISTER_COUNT],
+    d: u8,
+    df: bool,
+    p: u8,
+    x: u8,
+    t: u8,
+    ie: bool,
+    q: bool,
+    idle: bool,
+}
+
+impl Default for Cdp1802 {
+    fn default() -> Cdp1802 {
+        Cdp1802::new()
+    }
+}
+
+impl Cdp1802 {
+    pub fn new() -> Cdp1802 {
+        Cdp1802 {
+            r: [0; REGISTER_COUNT],
+            d: 0,
+            df: false,
+            p: 0,
+            x: 0,
+            t: 0,
+            ie: true,
+            q: false,
+            idle: false,
+        }
+    }
+
+    // The CLEAR input: X, P, R0 and Q cleared, interrupts enabled. The
+    // other registers keep whatever they held.
+    pub fn reset(&mut self) {
+        self.x = 0;
+        self.p = 0;
+        self.r[0] = 0;
+        self.q = false;
+        self.ie = true;
+        self.idle = false;
+    }
+
+    pub fn register(&self, n: usize) -> u16 {
+        self.r[n]
+    }
+
+    pub fn set_register(&mut self, n: usize, value: u16) {
+        self.r[n] = value;
+    }
+
+    pub fn d(&self) -> u8 {
+        self.d
+    }
+
+    pub fn df(&self) -> bool {
+        self.df
+    }
+
+    pub fn p(&self) -> u8 {
+        self.p
+    }
+
+    pub fn x(&self) -> u8 {
+        self.x
+    }
+
+    pub fn t(&self) -> u8 {
+        self.t
+    }
+
+    pub fn interrupts_enabled(&self) -> bool {
+        self.ie
+    }
+
+    // The Q output, which drives the VIP's beeper.
+    pub fn q(&self) -> bool {
+        self.q
+    }
+
+    // IDL was executed and neither an interrupt nor DMA has happened since.
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    // Takes an interrupt if they are enabled: X and P are saved in T and
+    // execution continues with R1 as program counter and R2 as X.
+    // Returns whether it was taken. Takes one machine cycle.
+    pub fn interrupt(&mut self) -> bool {
+        if !self.ie {
+            return false;
+        }
+        self.t = self.x << 4 | self.p;
+        self.x = 2;
+        self.p = 1;
+        self.ie = false;
+        self.idle = false;
+        true
+    }
+
+    // One DMA out cycle: the byte at R0 goes to the device and R0 moves on.
+    pub fn dma_out<B: SystemBus>(&mut self, bus: &mut B) -> u8 {
+        let value = bus.read(self.r[0]);
+        self.r[0] = self.r[0].wrapping_add(1);
+        self.idle = false;
+        value
+    }
+
+    fn fetch<B: SystemBus>(&mut self, bus: &mut B) -> u8 {
+        let p = self.p as usize;
+        let value = bus.read(self.r[p]);
+        self.r[p] = self.r[p].wrapping_add(1);
+        value
+    }
+
+    fn rx(&self) -> u16 {
+        self.r[self.x as usize]
+    }
+
+    fn increment(&mut self, n: usize) {
+        self.r[n] = self.r[n].wrapping_add(1);
+    }
+
+    fn decrement(&mut self, n: usize) {
+        self.r[n] = self.r[n].wrapping_sub(1);
+    }
+
+    fn add(&mut self, a: u8, b: u8, carry: bool) {
+        let sum = a as u16 + b as u16 + carry as u16;
+        self.d = sum as u8;
+        self.df = sum > 0xFF;
+    }
+
+    // DF ends up set when there was no borrow.
+    fn subtract(&mut self, minuend: u8, subtrahend: u8, borrow: bool) {
+        let difference = minuend as i16 - subtrahend as i16 - borrow as i16;
+        self.d = difference as u8;
+        self.df = difference >= 0;
+    }
+
+    // Condition tested by the branch and skip instructions 3N and CN.
+    fn condition<B: SystemBus>(&self, low: u8, bus: &B) -> bool {
+        match low & 0x7 {
+            0 => true,
+            1 => self.q,
+            2 => self.d == 0,
+            3 => self.df,
+            line => bus.flag(line - 3),
+        }
+    }
+
+    fn short_branch<B: SystemBus>(&mut self, taken: bool, bus: &mut B) {
+        let p = self.p as usize;
+        if taken {
+            let target = bus.read(self.r[p]);
+            self.r[p] = (self.r[p] & 0xFF00) | target as u16;
+        } else {
+            self.increment(p);
+        }
+    }
+
+    fn long_branch<B: SystemBus>(&mut self, taken: bool, bus: &mut B) {
+        let p = self.p as usize;
+        if taken {
+            let high = bus.read(self.r[p]) as u16;
+            let low = bus.read(self.r[p].wrapping_add(1)) as u16;
+            self.r[p] = high << 8 | low;
+        } else {
+            self.r[p] = self.r[p].wrapping_add(2);
+        }
+    }
+
+    fn long_skip(&mut self, taken: bool) {
+        if taken {
+            let p = self.p as usize;
+            self.r[p] = self.r[p].wrapping_add(2);
+        }
+    }
+
+    // Executes one instruction and returns the machine cycles it took.
+    // While idle nothing happens but time passing.
+    pub fn step<B: SystemBus>(&mut self, bus: &mut B) -> u32 {
+        if self.idle {
+            return 2;
+        }
+
+        let opcode = self.fetch(bus);
+        let n = (opcode & 0x0F) as usize;
+        let x = self.x as usize;
+
+        match opcode >> 4 {
+            0x0 if n == 0 => self.idle = true,
+            0x0 => self.d = bus.read(self.r[n]),
+            0x1 => self.increment(n),
+            0x2 => self.decrement(n),
+            0x3 => {
+                let taken = match n {
+                    0x8 => {
+                        // SKP
+                        self.increment(self.p as usize);
+                        return 2;
+                    }
+                    _ if n < 0x8 => self.condition(n as u8, bus),
+                    _ => !self.condition(n as u8, bus),
+                };
+                self.short_branch(taken, bus);
+            }
+            0x4 => {
+                self.d = bus.read(self.r[n]);
+                self.increment(n);
+            }
+            0x5 => bus.write(self.r[n], self.d),
+            0x6 => match n {
+                0x0 => self.increment(x),
+                0x8 => {}
+                0x1..=0x7 => {
+                    let value = bus.read(self.rx());
+                    bus.output(n as u8, value);
+                    self.increment(x);
+                }
+                _ => {
+                    let value = bus.input(n as u8 - 8);
+                    bus.write(self.rx(), value);
+                    self.d = value;
+                }
+            },
+            0x7 => self.execute_7n(n, bus),
+            0x8 => self.d = self.r[n] as u8,
+            0x9 => self.d = (self.r[n] >> 8) as u8,
+            0xA => self.r[n] = (self.r[n] & 0xFF00) | self.d as u16,
+            0xB => self.r[n] = (self.r[n] & 0x00FF) | (self.d as u16) << 8,
+            0xC => {
+                match n {
+                    0x4 => {}
+                    0x5 => self.long_skip(!self.q),
+                    0x6 => self.long_skip(self.d != 0),
+                    0x7 => self.long_skip(!self.df),
+                    0x8 => self.long_skip(true),
+                    0xC => self.long_skip(self.ie),
+                    0xD => self.long_skip(self.q),
+                    0xE => self.long_skip(self.d == 0),
+                    0xF => self.long_skip(self.df),
+                    _ if n < 0x8 => {
+                        let taken = self.condition(n as u8, bus);
+                        self.long_branch(taken, bus);
+                    }
+                    _ => {
+                        let taken = !self.condition(n as u8, bus);
+                        self.long_branch(taken, bus);
+                    }
+                }
+                return 3;
+            }
+            0xD => self.p = n as u8,
+            0xE => self.x = n as u8,
+            _ => self.execute_fn(n, bus),
+        }
+
+        2
+    }
+
+    // Return, stack and arithmetic with carry instructions, 70 to 7F.
+    fn execute_7n<B: SystemBus>(&mut self, n: usize, bus: &mut B) {
+        let x = self.x as usize;
+        match n {
+            0x0 | 0x1 => {
+                // RET and DIS
+                let value = bus.read(self.rx());
+                self.increment(x);
+                self.x = value >> 4;
+                self.p = value & 0x0F;
+                self.ie = n == 0x0;
+            }
+            0x2 => {
+                self.d = bus.read(self.rx());
+                self.increment(x);
+            }
+            0x3 => {
+                bus.write(self.rx(), self.d);
+                self.decrement(x);
+            }
+            0x4 => {
+                let value = bus.read(self.rx());
+                let (d, df) = (self.d, self.df);
+                self.add(value, d, df);
+            }
+            0x5 => {
+                let value = bus.read(self.rx());
+                let (d, df) = (self.d, self.df);
+                self.subtract(value, d, !df);
+            }
+            0x6 => {
+                let carry = self.df;
+                self.df = self.d & 0x01 != 0;
+                self.d = self.d >> 1 | (carry as u8) << 7;
+            }
+            0x7 => {
+                let value = bus.read(self.rx());
+                let (d, df) = (self.d, self.df);
+                self.subtract(d, value, !df);
+            }
+            0x8 => bus.write(self.rx(), self.t),
+            0x9 => {
+                // MARK
+                self.t = self.x << 4 | self.p;
+                bus.write(self.r[2], self.t);
+                self.x = self.p;
+                self.decrement(2);
+            }
+            0xA => self.q = false,
+            0xB => self.q = true,
+            0xC => {
+                let value = self.fetch(bus);
+                let (d, df) = (self.d, self.df);
+                self.add(value, d, df);
+            }
+            0xD => {
+                let value = self.fetch(bus);
+                let (d, df) = (self.d, self.df);
+                self.subtract(value, d, !df);
+            }
+            0xE => {
+                let carry = self.df;
+                self.df = self.d & 0x80 != 0;
+                self.d = self.d << 1 | carry as u8;
+            }
+            _ => {
+                let value = self.fetch(bus);
+                let (d, df) = (self.d, self.df);
+                self.subtract(d, value, !df);
+            }
+        }
+    }
+
+    // Logic and arithmetic on memory at R(X), F0 to F7, and on the
+    // immediate byte, F8 to FF.
+    fn execute_fn<B: SystemBus>(&mut self, n: usize, bus: &mut B) {
+        let d = self.d;
+        match n {
+            0x6 => {
+                self.df = d & 0x01 != 0;
+                self.d = d >> 1;
+                return;
+            }
+            0xE => {
+                self.df = d & 0x80 != 0;
+                self.d = d << 1;
+                return;
+            }
+            _ => {}
+        }
+
+        let value = if n < 0x8 { bus.read(self.rx()) } else { self.fetch(bus) };
+        match n & 0x7 {
+            0x0 => self.d = value,
+            0x1 => self.d = d | value,
+            0x2 => self.d = d & value,
+            0x3 => self.d = d ^ value,
+            0x4 => self.add(value, d, false),
+            0x5 => self.subtract(value, d, false),
+            _ => self.subtract(d, value, false),
+        }
+    }
+}
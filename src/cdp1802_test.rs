@@ -0,0 +1,231 @@
+#[cfg(test)]
+mod cdp1802_test {
+
+    use cdp1802::{ Cdp1802, SystemBus };
+
+    struct TestBus {
+        memory: Vec<u8>,
+        outputs: Vec<(u8, u8)>,
+        flags: [bool; 4],
+    }
+
+    impl SystemBus for TestBus {
+        fn read(&mut self, address: u16) -> u8 {
+            self.memory[address as usize]
+        }
+
+        fn write(&mut self, address: u16, value: u8) {
+            self.memory[address as usize] = value;
+        }
+
+        fn output(&mut self, port: u8, value: u8) {
+            self.outputs.push((port, value));
+        }
+
+        fn input(&mut self, port: u8) -> u8 {
+            0x40 | port
+        }
+
+        fn flag(&self, line: u8) -> bool {
+            self.flags[line as usize - 1]
+        }
+    }
+
+    fn bus(program: &[u8]) -> TestBus {
+        let mut memory = vec![0; 0x1000];
+        memory[..program.len()].copy_from_slice(program);
+        TestBus { memory, outputs: Vec::new(), flags: [false; 4] }
+    }
+
+    fn run(cpu: &mut Cdp1802, bus: &mut TestBus, instructions: usize) -> u32 {
+        (0..instructions).map(|_| cpu.step(bus)).sum()
+    }
+
+    #[test]
+    fn immediate_loads_and_register_moves() {
+        // LDI 12; PLO 3; LDI 34; PHI 3; GLO 3
+        let mut bus = bus(&[0xF8, 0x12, 0xA3, 0xF8, 0x34, 0xB3, 0x83]);
+        let mut cpu = Cdp1802::new();
+
+        assert_eq!(run(&mut cpu, &mut bus, 5), 10);
+        assert_eq!(cpu.register(3), 0x3412);
+        assert_eq!(cpu.d(), 0x12);
+        assert_eq!(cpu.register(0), 7);
+    }
+
+    #[test]
+    fn addition_sets_df_on_carry() {
+        // LDI F0; ADI 20; ADCI 00
+        let mut bus = bus(&[0xF8, 0xF0, 0xFC, 0x20, 0x7C, 0x00]);
+        let mut cpu = Cdp1802::new();
+
+        run(&mut cpu, &mut bus, 2);
+        assert_eq!(cpu.d(), 0x10);
+        assert!(cpu.df());
+        run(&mut cpu, &mut bus, 1);
+        assert_eq!(cpu.d(), 0x11);
+        assert!(!cpu.df());
+    }
+
+    #[test]
+    fn subtraction_clears_df_on_borrow() {
+        // LDI 10; SMI 20; LDI 10; SDI 20
+        let mut bus = bus(&[0xF8, 0x10, 0xFF, 0x20, 0xF8, 0x10, 0xFD, 0x20]);
+        let mut cpu = Cdp1802::new();
+
+        run(&mut cpu, &mut bus, 2);
+        assert_eq!(cpu.d(), 0xF0);
+        assert!(!cpu.df());
+        run(&mut cpu, &mut bus, 2);
+        assert_eq!(cpu.d(), 0x10);
+        assert!(cpu.df());
+    }
+
+    #[test]
+    fn shifts_move_bits_through_df() {
+        // LDI 81; SHR; SHRC; SHL
+        let mut bus = bus(&[0xF8, 0x81, 0xF6, 0x76, 0xFE]);
+        let mut cpu = Cdp1802::new();
+
+        run(&mut cpu, &mut bus, 2);
+        assert_eq!(cpu.d(), 0x40);
+        assert!(cpu.df());
+        run(&mut cpu, &mut bus, 1);
+        assert_eq!(cpu.d(), 0xA0);
+        assert!(!cpu.df());
+        run(&mut cpu, &mut bus, 1);
+        assert_eq!(cpu.d(), 0x40);
+        assert!(cpu.df());
+    }
+
+    #[test]
+    fn short_branches_stay_in_the_page() {
+        // LDI 00; BZ 10
+        let mut bus = bus(&[0xF8, 0x00, 0x32, 0x10]);
+        let mut cpu = Cdp1802::new();
+
+        run(&mut cpu, &mut bus, 2);
+        assert_eq!(cpu.register(0), 0x10);
+    }
+
+    #[test]
+    fn branches_not_taken_skip_the_target() {
+        // BQ 10; BN1 20
+        let mut bus = bus(&[0x31, 0x10, 0x3C, 0x20]);
+        bus.flags[0] = true;
+        let mut cpu = Cdp1802::new();
+
+        run(&mut cpu, &mut bus, 2);
+        assert_eq!(cpu.register(0), 4);
+    }
+
+    #[test]
+    fn long_branches_take_three_cycles() {
+        // LBR 0123
+        let mut bus = bus(&[0xC0, 0x01, 0x23]);
+        let mut cpu = Cdp1802::new();
+
+        assert_eq!(cpu.step(&mut bus), 3);
+        assert_eq!(cpu.register(0), 0x0123);
+    }
+
+    #[test]
+    fn long_skips_pass_over_two_bytes() {
+        // SEQ; LSQ; BR 00; REQ
+        let mut bus = bus(&[0x7B, 0xCD, 0x30, 0x00, 0x7A]);
+        let mut cpu = Cdp1802::new();
+
+        run(&mut cpu, &mut bus, 3);
+        assert!(!cpu.q());
+        assert_eq!(cpu.register(0), 5);
+    }
+
+    #[test]
+    fn sep_and_sex_switch_registers() {
+        // LDI 10; PLO 3; SEP 3 ... at 10: SEX 5
+        let mut program = vec![0xF8, 0x10, 0xA3, 0xD3];
+        program.resize(0x10, 0);
+        program.push(0xE5);
+        let mut bus = bus(&program);
+        let mut cpu = Cdp1802::new();
+
+        run(&mut cpu, &mut bus, 4);
+        assert_eq!(cpu.p(), 3);
+        assert_eq!(cpu.x(), 5);
+        assert_eq!(cpu.register(3), 0x11);
+    }
+
+    #[test]
+    fn stack_stores_and_loads_through_x() {
+        // LDI 80; PLO 2; SEX 2; LDI 55; STXD; IRX; LDX
+        let mut bus = bus(&[0xF8, 0x80, 0xA2, 0xE2, 0xF8, 0x55, 0x73, 0x60, 0xF8, 0x00, 0xF0]);
+        let mut cpu = Cdp1802::new();
+
+        run(&mut cpu, &mut bus, 5);
+        assert_eq!(bus.memory[0x80], 0x55);
+        assert_eq!(cpu.register(2), 0x7F);
+        run(&mut cpu, &mut bus, 3);
+        assert_eq!(cpu.d(), 0x55);
+    }
+
+    #[test]
+    fn out_and_inp_use_memory_at_x() {
+        // LDI 80; PLO 5; SEX 5; OUT 2; INP 3
+        let mut bus = bus(&[0xF8, 0x80, 0xA5, 0xE5, 0x62, 0x6B]);
+        bus.memory[0x80] = 0x07;
+        let mut cpu = Cdp1802::new();
+
+        run(&mut cpu, &mut bus, 5);
+        assert_eq!(bus.outputs, vec![(2, 0x07)]);
+        assert_eq!(cpu.d(), 0x43);
+        assert_eq!(bus.memory[0x81], 0x43);
+    }
+
+    #[test]
+    fn interrupts_save_x_and_p_and_ret_restores_them() {
+        // At 0: IDL. Interrupt routine at 40: DEC 2; SAV; RET
+        let mut program = vec![0x00];
+        program.resize(0x40, 0);
+        program.extend_from_slice(&[0x22, 0x78, 0x70]);
+        let mut bus = bus(&program);
+        let mut cpu = Cdp1802::new();
+        cpu.set_register(1, 0x40);
+        cpu.set_register(2, 0x90);
+
+        cpu.step(&mut bus);
+        assert!(cpu.is_idle());
+        assert!(cpu.interrupt());
+        assert!(!cpu.interrupt());
+        assert_eq!((cpu.p(), cpu.x()), (1, 2));
+
+        run(&mut cpu, &mut bus, 3);
+        assert_eq!((cpu.p(), cpu.x()), (0, 0));
+        assert!(cpu.interrupts_enabled());
+        assert_eq!(cpu.register(2), 0x90);
+        assert!(!cpu.is_idle());
+    }
+
+    #[test]
+    fn mark_pushes_x_and_p() {
+        // SEX 4; MARK
+        let mut bus = bus(&[0xE4, 0x79]);
+        let mut cpu = Cdp1802::new();
+        cpu.set_register(2, 0x80);
+
+        run(&mut cpu, &mut bus, 2);
+        assert_eq!(bus.memory[0x80], 0x40);
+        assert_eq!(cpu.t(), 0x40);
+        assert_eq!(cpu.x(), 0);
+        assert_eq!(cpu.register(2), 0x7F);
+    }
+
+    #[test]
+    fn dma_reads_through_r0() {
+        let mut bus = bus(&[0xAB, 0xCD]);
+        let mut cpu = Cdp1802::new();
+
+        assert_eq!(cpu.dma_out(&mut bus), 0xAB);
+        assert_eq!(cpu.dma_out(&mut bus), 0xCD);
+        assert_eq!(cpu.register(0), 2);
+    }
+}
@@ -1,20 +1,404 @@
-use cpu::Cpu;
-use ram::Ram;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
-mod ram;
-mod cpu;
-mod instruction;
+use bus::Bus;
+use cpu::{Cpu, StepOutcome, START, RPL_FLAG_COUNT};
+use display::Display;
+use ram::{Ram, MEMORY_SIZE};
+use error::EmulatorError;
+use quirks::Quirks;
+use platform::Platform;
+use font::{Font, SMALL_FONT_SIZE, BIG_DIGITS_SIZE, BIG_HEX_SIZE};
+use audio::Audio;
+use colour::ColourBoard;
+use port::Port;
+use megachip::{MegaDisplay, SamplePlayer};
+use keypad::Keypad;
+use random::RandomSource;
 
-pub struct Chip8 {
+// Largest program that fits between the load address and the end of a 4 KB
+// memory. Platforms with more memory take larger ones, see `max_rom_size`.
+pub const MAX_ROM_SIZE: usize = MEMORY_SIZE - START as usize;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Empty,
+    TooLarge { size: usize, max: usize },
+    // A font binary that is not 80 bytes of small glyphs, optionally
+    // followed by 100 or 160 bytes of big ones.
+    InvalidFont { size: usize },
+    // A machine given less or more memory than it can have.
+    InvalidMemorySize { size: usize, min: usize, max: usize },
+    Io(io::Error),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Empty => write!(f, "ROM is empty"),
+            LoadError::TooLarge { size, max } =>
+                write!(f, "ROM is {} bytes, the largest that fits is {} bytes", size, max),
+            LoadError::InvalidFont { size } =>
+                write!(f, "font is {} bytes, expected {}, {} or {}", size, SMALL_FONT_SIZE,
+                       SMALL_FONT_SIZE + BIG_DIGITS_SIZE, SMALL_FONT_SIZE + BIG_HEX_SIZE),
+            LoadError::InvalidMemorySize { size, min, max } =>
+                write!(f, "memory is {} bytes, it must be between {} and {} bytes", size, min, max),
+            LoadError::Io(ref error) => write!(f, "could not read ROM: {}", error),
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(error: io::Error) -> LoadError {
+        LoadError::Io(error)
+    }
+}
+
+// The whole state of a machine at one moment, random generator
+// included, so that going back to it replays exactly the same run.
+#[derive(Clone)]
+pub struct Snapshot {
+    cpu: Cpu,
     ram: Ram,
-    cpu: Cpu
+    display: Display,
+    keypad: Keypad,
+    audio: Audio,
+    colour: ColourBoard,
+    second_keypad: Keypad,
+    port: Port,
+    mega_display: MegaDisplay,
+    sampler: SamplePlayer,
+    random_state: u64,
+    cycle_debt: u32,
+}
+
+impl Snapshot {
+    pub fn random_state(&self) -> u64 {
+        self.random_state
+    }
+}
+
+// A complete machine: CPU plus memory, display and keypad.
+// Hosts load a ROM, then call `run_frame` 60 times per second.
+pub struct Chip8 {
+    cpu: Cpu,
+    bus: Bus,
+    rom: Vec<u8>,
+    platform: Platform,
+    // Where RPL flags are kept for the ROM loaded from a file.
+    flags_path: Option<PathBuf>,
+    // Why the flags file last failed to save, until the host takes it.
+    flags_error: Option<io::Error>,
+    // Replaces the platform's built-in font when set.
+    custom_font: Option<Vec<u8>>,
+    // Cycles the last instruction of a frame ran into the next one.
+    cycle_debt: u32,
+}
+
+impl Default for Chip8 {
+    fn default() -> Chip8 {
+        Chip8::new()
+    }
 }
 
 impl Chip8 {
-    fn new () -> Chip8{
-        Chip8{
-            ram: Ram::new(),
-            cpu: Cpu::new()
+    pub fn new() -> Chip8 {
+        Chip8::with_platform(Platform::default())
+    }
+
+    pub fn with_quirks(quirks: Quirks) -> Chip8 {
+        Chip8::with_platform(Platform { quirks, ..Platform::default() })
+    }
+
+    pub fn with_platform(platform: Platform) -> Chip8 {
+        let mut bus = Bus::new();
+        bus.ram = Ram::for_platform(&platform);
+        Chip8 {
+            cpu: Cpu::for_platform(&platform),
+            bus,
+            rom: Vec::new(),
+            platform,
+            flags_path: None,
+            flags_error: None,
+            custom_font: None,
+            cycle_debt: 0,
+        }
+    }
+
+    pub fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    // Largest program that fits between the load address and the end of
+    // this platform's memory, or its interpreter's stack.
+    pub fn max_rom_size(&self) -> usize {
+        let end = self.platform.stack_address.map_or(self.platform.memory_size, |address| address as usize);
+        end - self.platform.load_address as usize
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.cpu.quirks()
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.cpu.set_quirks(quirks);
+    }
+
+    // Powers the machine on with `rom` loaded at the platform's load address.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), LoadError> {
+        if rom.is_empty() {
+            return Err(LoadError::Empty);
+        }
+        let max = self.max_rom_size();
+        if rom.len() > max {
+            return Err(LoadError::TooLarge { size: rom.len(), max });
+        }
+
+        self.rom = rom.to_vec();
+        self.flags_path = None;
+        self.cpu.set_rpl_flags([0; RPL_FLAG_COUNT]);
+        self.hard_reset();
+        Ok(())
+    }
+
+    // Puts a font binary at the platform's font address in place of the
+    // built-in one. It survives resets and ROM changes.
+    pub fn load_font(&mut self, glyphs: &[u8]) -> Result<(), LoadError> {
+        if !Font::is_valid_binary(glyphs) {
+            return Err(LoadError::InvalidFont { size: glyphs.len() });
+        }
+        self.bus.ram.load_glyphs(glyphs, self.platform.font_address);
+        self.custom_font = Some(glyphs.to_vec());
+        Ok(())
+    }
+
+    pub fn load_font_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), LoadError> {
+        let glyphs = fs::read(path)?;
+        self.load_font(&glyphs)
+    }
+
+    // Like `load_rom`, but RPL flags are also kept next to the ROM, in a
+    // file with the `flags` extension, and restored from it if present.
+    pub fn load_rom_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), LoadError> {
+        let path = path.as_ref();
+        let rom = fs::read(path)?;
+        self.load_rom(&rom)?;
+
+        let flags_path = path.with_extension("flags");
+        if let Ok(saved) = fs::read(&flags_path) {
+            let mut flags = [0; RPL_FLAG_COUNT];
+            let count = saved.len().min(RPL_FLAG_COUNT);
+            flags[..count].copy_from_slice(&saved[..count]);
+            self.cpu.set_rpl_flags(flags);
+        }
+        self.flags_path = Some(flags_path);
+        Ok(())
+    }
+
+    pub fn flags_path(&self) -> Option<&Path> {
+        self.flags_path.as_deref()
+    }
+
+    // Executes a single instruction. Timers are left alone. A flags file
+    // that cannot be written does not stop the program, see
+    // `take_flags_error`.
+    pub fn step(&mut self) -> Result<StepOutcome, EmulatorError> {
+        let flags = *self.cpu.rpl_flags();
+        let outcome = self.cpu.execute(&mut self.bus)?;
+        if flags != *self.cpu.rpl_flags() {
+            if let Err(error) = self.save_flags() {
+                self.flags_error = Some(error);
+            }
+        }
+        Ok(outcome)
+    }
+
+    fn save_flags(&self) -> io::Result<()> {
+        match self.flags_path {
+            Some(ref path) => fs::write(path, &self.cpu.rpl_flags()[..]),
+            None => Ok(()),
         }
     }
-}
\ No newline at end of file
+
+    // Why saving the RPL flags last failed, for the host to report or log.
+    // Taking it clears it.
+    pub fn take_flags_error(&mut self) -> Option<io::Error> {
+        self.flags_error.take()
+    }
+
+    // Runs one 60 Hz frame: `instructions_per_frame` instructions followed
+    // by a single timer tick and the vertical blank. Stops at the first
+    // error, without ticking, and stops early once the program has exited.
+    pub fn run_frame(&mut self, instructions_per_frame: usize) -> Result<(), EmulatorError> {
+        for _ in 0..instructions_per_frame {
+            if self.step()? == StepOutcome::Exited {
+                break;
+            }
+        }
+        self.end_frame();
+        Ok(())
+    }
+
+    // Runs one 60 Hz frame by time rather than by instruction count:
+    // instructions run until they have used `cycles_per_frame` VIP
+    // machine cycles, `VIP_CYCLES_PER_FRAME` for the VIP's own speed.
+    // An instruction that runs over the end of the frame takes its excess
+    // from the next. Waiting for a key, for the display or for input
+    // idles away the rest of the frame.
+    pub fn run_frame_cycles(&mut self, cycles_per_frame: u32) -> Result<(), EmulatorError> {
+        let mut used = self.cycle_debt;
+        self.cycle_debt = 0;
+        while used < cycles_per_frame {
+            if self.step()? != StepOutcome::Executed {
+                used = cycles_per_frame;
+                break;
+            }
+            used += self.cpu.last_cycles();
+        }
+        self.cycle_debt = used - cycles_per_frame;
+        self.end_frame();
+        Ok(())
+    }
+
+    fn end_frame(&mut self) {
+        self.cpu.tick_timers();
+        self.cpu.vblank();
+        self.bus.random.tick();
+    }
+
+    // Replaces the generator behind CXNN, e.g. with a `SeededRandom` for
+    // reproducible runs or a `VipRandom`.
+    pub fn set_random_source(&mut self, random: Box<dyn RandomSource>) {
+        self.bus.random = random;
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            cpu: self.cpu.clone(),
+            ram: self.bus.ram.clone(),
+            display: self.bus.display.clone(),
+            keypad: self.bus.keypad.clone(),
+            audio: self.bus.audio.clone(),
+            colour: self.bus.colour.clone(),
+            second_keypad: self.bus.second_keypad.clone(),
+            port: self.bus.port.clone(),
+            mega_display: self.bus.mega_display.clone(),
+            sampler: self.bus.sampler.clone(),
+            random_state: self.bus.random.state(),
+            cycle_debt: self.cycle_debt,
+        }
+    }
+
+    // Goes back to `snapshot`. The random generator keeps its kind but
+    // takes the saved state.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.cpu = snapshot.cpu.clone();
+        self.bus.ram = snapshot.ram.clone();
+        self.bus.display = snapshot.display.clone();
+        self.bus.keypad = snapshot.keypad.clone();
+        self.bus.audio = snapshot.audio.clone();
+        self.bus.colour = snapshot.colour.clone();
+        self.bus.second_keypad = snapshot.second_keypad.clone();
+        self.bus.port = snapshot.port.clone();
+        self.bus.mega_display = snapshot.mega_display.clone();
+        self.bus.sampler = snapshot.sampler.clone();
+        self.bus.random.set_state(snapshot.random_state);
+        self.cycle_debt = snapshot.cycle_debt;
+    }
+
+    // Restarts the program as the reset switch would: CPU, screen and keys
+    // go back to their initial state but memory is left as it is.
+    pub fn reset(&mut self) {
+        self.cpu.reset();
+        self.cycle_debt = 0;
+        self.bus.display.set_high_resolution(false);
+        self.bus.display.select_planes(0x1);
+        self.bus.audio = Audio::new();
+        self.bus.colour = ColourBoard::new();
+        self.bus.second_keypad.release_all();
+        self.bus.port = Port::new();
+        self.bus.mega_display = MegaDisplay::new();
+        self.bus.sampler = SamplePlayer::new();
+        self.bus.keypad.release_all();
+    }
+
+    // Like `reset`, but memory is wiped too and the loaded ROM copied back
+    // in, undoing anything the program wrote.
+    pub fn hard_reset(&mut self) {
+        self.reset();
+        self.bus.ram = Ram::for_platform(&self.platform);
+        if let Some(ref glyphs) = self.custom_font {
+            self.bus.ram.load_glyphs(glyphs, self.platform.font_address);
+        }
+        // MegaChip ROMs run past 64K, so addresses are worked out wide.
+        // `max_rom_size` keeps every byte inside memory.
+        for (offset, byte) in self.rom.iter().enumerate() {
+            self.bus.ram.write_long(self.platform.load_address as u32 + offset as u32, *byte).unwrap();
+        }
+    }
+
+    pub fn press_key(&mut self, key: u8) {
+        self.bus.keypad.press(key);
+    }
+
+    pub fn release_key(&mut self, key: u8) {
+        self.bus.keypad.release(key);
+    }
+
+    // The CHIP-8X second keypad.
+    pub fn press_second_key(&mut self, key: u8) {
+        self.bus.second_keypad.press(key);
+    }
+
+    pub fn release_second_key(&mut self, key: u8) {
+        self.bus.second_keypad.release(key);
+    }
+
+    // Hands a byte to the program's next FXFB.
+    pub fn send_to_port(&mut self, value: u8) {
+        self.bus.port.send(value);
+    }
+
+    pub fn port(&self) -> &Port {
+        &self.bus.port
+    }
+
+    pub fn colour_board(&self) -> &ColourBoard {
+        &self.bus.colour
+    }
+
+    pub fn mega_display(&self) -> &MegaDisplay {
+        &self.bus.mega_display
+    }
+
+    pub fn sampler(&self) -> &SamplePlayer {
+        &self.bus.sampler
+    }
+
+    pub fn display(&self) -> &Display {
+        &self.bus.display
+    }
+
+    pub fn sound_active(&self) -> bool {
+        self.cpu.sound_active()
+    }
+
+    // True once the program ended itself with 00FD.
+    pub fn has_exited(&self) -> bool {
+        self.cpu.has_exited()
+    }
+
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    pub fn ram(&self) -> &Ram {
+        &self.bus.ram
+    }
+
+    pub fn audio(&self) -> &Audio {
+        &self.bus.audio
+    }
+}
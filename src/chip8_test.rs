@@ -0,0 +1,523 @@
+#[cfg(test)]
+mod chip8_test {
+
+    use std::env;
+    use std::fs;
+
+    use chip8::{ Chip8, LoadError, MAX_ROM_SIZE };
+    use cpu::START;
+    use error::EmulatorError;
+    use quirks::Quirks;
+    use cpu::StepOutcome;
+    use platform::Platform;
+    use random::SeededRandom;
+
+    // Encodes operations in the byte order the CPU fetches them.
+    fn rom(operations: &[u16]) -> Vec<u8> {
+        operations.iter()
+            .flat_map(|operation| vec![(operation >> 8) as u8, (operation & 0x00FF) as u8])
+            .collect()
+    }
+
+    #[test]
+    fn load_rom_copies_program_to_start_address() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&[0x12, 0x34, 0x56]).unwrap();
+
+        assert_eq!(chip8.ram().read_bytes(START), 0x12);
+        assert_eq!(chip8.ram().read_bytes(START + 1), 0x34);
+        assert_eq!(chip8.ram().read_bytes(START + 2), 0x56);
+        assert_eq!(chip8.cpu().read_pc(), START);
+    }
+
+    #[test]
+    fn load_rom_rejects_empty_and_oversized_roms() {
+        let mut chip8 = Chip8::new();
+
+        match chip8.load_rom(&[]) {
+            Err(LoadError::Empty) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+
+        match chip8.load_rom(&vec![0; MAX_ROM_SIZE + 1]) {
+            Err(LoadError::TooLarge { size, max }) => {
+                assert_eq!(size, MAX_ROM_SIZE + 1);
+                assert_eq!(max, MAX_ROM_SIZE);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        assert!(chip8.load_rom(&vec![0; MAX_ROM_SIZE]).is_ok());
+    }
+
+    #[test]
+    fn load_rom_file_reads_rom_from_disk() {
+        let path = env::temp_dir().join("chip8_load_rom_file_test.ch8");
+        fs::write(&path, rom(&[0x6042])).unwrap();
+
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_file(&path).unwrap();
+        chip8.step().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(chip8.cpu().read_vx(0), 0x42);
+    }
+
+    #[test]
+    fn load_rom_file_reports_missing_file() {
+        let mut chip8 = Chip8::new();
+        match chip8.load_rom_file("/does/not/exist.ch8") {
+            Err(LoadError::Io(_)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn run_frame_executes_instructions_then_ticks_timers_once() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom(&[
+            0x6010, //v0 = 0x10
+            0xF015, //delay = v0
+            0x7101, //v1 += 1
+            0x1204, //jump back to v1 += 1
+        ])).unwrap();
+
+        chip8.run_frame(10).unwrap();
+
+        assert_eq!(chip8.cpu().timers().delay(), 0x0F);
+        assert_eq!(chip8.cpu().read_vx(1), 4);
+    }
+
+    #[test]
+    fn keys_pressed_on_the_machine_reach_the_cpu() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom(&[0xF00A])).unwrap();
+
+        chip8.step().unwrap();
+        chip8.press_key(0x5);
+        chip8.step().unwrap();
+        chip8.release_key(0x5);
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.cpu().read_vx(0), 0x5);
+    }
+
+    #[test]
+    fn reset_restarts_program_but_keeps_memory() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom(&[
+            0x6007, //v0 = 0x07
+            0xA300, //i = 0x300
+            0xF055, //memory[0x300] = v0
+            0xD001, //draw
+        ])).unwrap();
+        chip8.run_frame(4).unwrap();
+
+        chip8.reset();
+
+        assert_eq!(chip8.cpu().read_pc(), START);
+        assert_eq!(chip8.cpu().read_vx(0), 0);
+        assert!(chip8.display().pixels().iter().all(|pixel| !pixel));
+        assert_eq!(chip8.ram().read_bytes(0x300), 0x07);
+    }
+
+    #[test]
+    fn hard_reset_restores_memory_from_rom() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom(&[
+            0x60AA, //v0 = 0xAA
+            0xA200, //i = 0x200
+            0xF055, //overwrite first byte of the program
+        ])).unwrap();
+        chip8.run_frame(3).unwrap();
+        assert_eq!(chip8.ram().read_bytes(START), 0xAA);
+
+        chip8.hard_reset();
+
+        assert_eq!(chip8.ram().read_bytes(START), 0x60);
+        assert_eq!(chip8.cpu().read_pc(), START);
+    }
+
+    #[test]
+    fn run_frame_stops_at_errors_and_machine_stays_usable() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom(&[
+            0x6003, //v0 = 0x03
+            0xF015, //delay = v0
+            0x00EE, //return without call
+        ])).unwrap();
+
+        assert_eq!(chip8.run_frame(10), Err(EmulatorError::StackUnderflow { address: START + 4 }));
+        assert_eq!(chip8.cpu().timers().delay(), 0x03);
+
+        chip8.reset();
+        assert!(chip8.step().is_ok());
+    }
+
+    #[test]
+    fn quirks_are_chosen_at_construction_and_switchable_at_runtime() {
+        let mut chip8 = Chip8::with_quirks(Quirks::chip48());
+        assert_eq!(chip8.quirks(), Quirks::chip48());
+
+        chip8.load_rom(&rom(&[
+            0x6302, //v3 = 0x02
+            0xB300, //jump
+        ])).unwrap();
+        chip8.run_frame(2).unwrap();
+        assert_eq!(chip8.cpu().read_pc(), 0x302);
+
+        chip8.set_quirks(Quirks::cosmac_vip());
+        chip8.hard_reset();
+        chip8.run_frame(2).unwrap();
+        assert_eq!(chip8.cpu().read_pc(), 0x300);
+        assert_eq!(chip8.quirks(), Quirks::cosmac_vip());
+    }
+
+    #[test]
+    fn rpl_flags_are_persisted_next_to_the_rom() {
+        let path = env::temp_dir().join("chip8_rpl_flags_test.ch8");
+        let flags = path.with_extension("flags");
+        let _ = fs::remove_file(&flags);
+        fs::write(&path, rom(&[
+            0x602A, //v0 = 0x2A
+            0xF075, //save flags
+            0x6000, //v0 = 0x00
+            0xF085, //load flags
+        ])).unwrap();
+
+        let mut chip8 = Chip8::with_platform(Platform::super_chip());
+        chip8.load_rom_file(&path).unwrap();
+        assert_eq!(chip8.flags_path(), Some(flags.as_path()));
+        chip8.run_frame(2).unwrap();
+        assert_eq!(fs::read(&flags).unwrap()[0], 0x2A);
+
+        let mut chip8 = Chip8::with_platform(Platform::super_chip());
+        chip8.load_rom_file(&path).unwrap();
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        assert_eq!(chip8.cpu().read_vx(0), 0x2A);
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&flags).unwrap();
+    }
+
+    #[test]
+    fn failing_to_save_rpl_flags_does_not_stop_the_program() {
+        let path = env::temp_dir().join("chip8_rpl_flags_unwritable_test.ch8");
+        let flags = path.with_extension("flags");
+        // A directory where the flags file should go cannot be written.
+        fs::create_dir_all(&flags).unwrap();
+        fs::write(&path, rom(&[
+            0x602A, //v0 = 0x2A
+            0xF075, //save flags
+            0x6101, //v1 = 0x01
+        ])).unwrap();
+
+        let mut chip8 = Chip8::with_platform(Platform::super_chip());
+        chip8.load_rom_file(&path).unwrap();
+        let result = chip8.run_frame(3);
+        fs::remove_file(&path).unwrap();
+        fs::remove_dir(&flags).unwrap();
+
+        assert!(result.is_ok());
+        assert_eq!(chip8.cpu().read_vx(1), 0x01);
+        assert!(chip8.take_flags_error().is_some());
+        assert!(chip8.take_flags_error().is_none());
+    }
+
+    #[test]
+    fn exit_ends_the_frame_and_is_reported() {
+        let mut chip8 = Chip8::with_platform(Platform::super_chip());
+        chip8.load_rom(&rom(&[
+            0x00FF, //high resolution
+            0x00FD, //exit
+        ])).unwrap();
+
+        chip8.run_frame(10).unwrap();
+        assert!(chip8.has_exited());
+        assert!(chip8.display().is_high_resolution());
+        assert_eq!(chip8.step(), Ok(StepOutcome::Exited));
+
+        chip8.reset();
+        assert!(!chip8.has_exited());
+        assert!(!chip8.display().is_high_resolution());
+    }
+
+    #[test]
+    fn xo_chip_takes_roms_filling_64k() {
+        let mut chip8 = Chip8::with_platform(Platform::xo_chip());
+        let mut program = rom(&[0xF000, 0xFFF0]); //i = 0xFFF0
+        program.resize(chip8.max_rom_size(), 0x00);
+        assert!(chip8.load_rom(&program).is_ok());
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.cpu().read_i(), 0xFFF0);
+
+        program.push(0x00);
+        match chip8.load_rom(&program) {
+            Err(LoadError::TooLarge { max, .. }) => assert_eq!(max, 0x10000 - 0x200),
+            other => panic!("expected TooLarge, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn xo_chip_pc_wraps_past_the_top_of_memory() {
+        let mut chip8 = Chip8::with_platform(Platform::xo_chip());
+        let program: Vec<u8> = rom(&[0x6000]).into_iter().cycle().take(chip8.max_rom_size()).collect();
+        chip8.load_rom(&program).unwrap();
+        for _ in 0..program.len() / 2 - 1 {
+            chip8.step().unwrap();
+        }
+        assert_eq!(chip8.cpu().read_pc(), 0xFFFE);
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.cpu().read_pc(), 0x0000);
+    }
+
+    #[test]
+    fn hires_rom_boots_and_draws_on_the_lower_page() {
+        let mut chip8 = Chip8::with_platform(Platform::chip8_hires());
+        let mut program = rom(&[0x1260]);
+        program.resize(0xC0, 0x00);
+        program.extend(rom(&[
+            0x6030, //v0 = 0x30
+            0xF029, //i = font 0
+            0xD005, //draw at (0x30, 0x30)
+        ]));
+        chip8.load_rom(&program).unwrap();
+        chip8.run_frame(4).unwrap();
+
+        assert_eq!(chip8.display().height(), 64);
+        assert!(chip8.display().pixel(0x30, 0x30));
+
+        chip8.reset();
+        assert_eq!(chip8.cpu().read_pc(), START);
+        assert_eq!(chip8.display().height(), 32);
+    }
+
+    #[test]
+    fn chip8x_roms_load_at_0x300() {
+        let mut chip8 = Chip8::with_platform(Platform::chip8x());
+        chip8.load_rom(&rom(&[0x02A0])).unwrap();
+
+        assert_eq!(chip8.cpu().read_pc(), 0x300);
+        assert_eq!(chip8.ram().read_bytes(0x300), 0x02);
+
+        let background = chip8.colour_board().background();
+        chip8.step().unwrap();
+        assert_ne!(chip8.colour_board().background(), background);
+
+        chip8.reset();
+        assert_eq!(chip8.colour_board().background(), background);
+    }
+
+    #[test]
+    fn megachip_reset_leaves_mega_mode() {
+        let mut chip8 = Chip8::with_platform(Platform::megachip());
+        chip8.load_rom(&rom(&[0x0011])).unwrap();
+        chip8.step().unwrap();
+        assert!(chip8.mega_display().is_enabled());
+
+        chip8.reset();
+        assert!(!chip8.mega_display().is_enabled());
+        assert!(!chip8.sampler().is_playing());
+    }
+
+    #[test]
+    fn megachip_roms_may_run_past_64k() {
+        let mut chip8 = Chip8::with_platform(Platform::megachip());
+        let mut program = rom(&[0x0011]);
+        program.resize(0x20000, 0x00);
+        program[0x10000] = 0xAB;
+        chip8.load_rom(&program).unwrap();
+
+        assert_eq!(chip8.ram().read_long(0x200).unwrap(), 0x00);
+        assert_eq!(chip8.ram().read_long(0x201).unwrap(), 0x11);
+        assert_eq!(chip8.ram().read_long(0x10200).unwrap(), 0xAB);
+    }
+
+    #[test]
+    fn eti660_roms_load_at_0x600() {
+        let mut chip8 = Chip8::with_platform(Platform::eti660());
+        chip8.load_rom(&[0x12, 0x34]).unwrap();
+
+        assert_eq!(chip8.cpu().read_pc(), 0x600);
+        assert_eq!(chip8.ram().read_bytes(0x600), 0x12);
+        assert_eq!(chip8.max_rom_size(), 0x1000 - 0x600);
+    }
+
+    #[test]
+    fn cosmac_vip_programs_end_below_the_stack_of_12_calls() {
+        let mut chip8 = Chip8::with_platform(Platform::cosmac_vip());
+        assert_eq!(chip8.max_rom_size(), 0xEA0 - 0x200);
+
+        // A subroutine that calls itself.
+        chip8.load_rom(&rom(&[0x2200])).unwrap();
+        for _ in 0..12 {
+            chip8.step().unwrap();
+        }
+        match chip8.step() {
+            Err(EmulatorError::StackOverflow { .. }) => {}
+            other => panic!("expected StackOverflow, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn dream6800_has_its_own_font_and_memory_size() {
+        let mut chip8 = Chip8::with_platform(Platform::dream6800());
+        // V0 = 1, I = sprite of V0
+        chip8.load_rom(&rom(&[0x6001, 0xF029])).unwrap();
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+
+        let i = chip8.cpu().read_i();
+        assert_eq!(chip8.ram().read_long(i).unwrap(), 0x40);
+        assert_eq!(chip8.ram().size(), 0x800);
+    }
+
+    #[test]
+    fn dream6800_has_nothing_past_its_2k() {
+        let chip8 = Chip8::with_platform(Platform::dream6800());
+
+        assert!(chip8.ram().read_long(0x7FF).is_ok());
+        assert!(matches!(chip8.ram().read_long(0x800), Err(EmulatorError::MemoryOutOfBounds { .. })));
+        assert!(matches!(chip8.ram().read(0xFFF), Err(EmulatorError::MemoryOutOfBounds { .. })));
+    }
+
+    #[test]
+    fn custom_fonts_replace_the_built_in_one_across_resets() {
+        let mut chip8 = Chip8::new();
+        let mut glyphs = vec![0xAA; 80];
+        glyphs[5] = 0x11;
+        chip8.load_font(&glyphs).unwrap();
+        // V0 = 1, I = sprite of V0
+        chip8.load_rom(&rom(&[0x6001, 0xF029])).unwrap();
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+
+        let i = chip8.cpu().read_i();
+        assert_eq!(chip8.ram().read_long(i).unwrap(), 0x11);
+        chip8.hard_reset();
+        assert_eq!(chip8.ram().read_long(i).unwrap(), 0x11);
+    }
+
+    #[test]
+    fn font_binaries_of_the_wrong_size_are_rejected() {
+        let mut chip8 = Chip8::new();
+
+        match chip8.load_font(&[0; 81]) {
+            Err(LoadError::InvalidFont { size }) => assert_eq!(size, 81),
+            other => panic!("expected InvalidFont, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn xo_chip_uses_octo_big_hex_digits() {
+        let mut chip8 = Chip8::with_platform(Platform::xo_chip());
+        // V0 = A, I = big sprite of V0
+        chip8.load_rom(&rom(&[0x600A, 0xF030])).unwrap();
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+
+        let i = chip8.cpu().read_i();
+        assert_eq!(chip8.ram().read_long(i).unwrap(), 0x7E);
+    }
+
+    #[test]
+    fn random_bytes_come_from_the_injected_source() {
+        let program = rom(&[0xC0FF, 0xC1FF]);
+        let mut first = Chip8::new();
+        first.set_random_source(Box::new(SeededRandom::with_seed(3)));
+        first.load_rom(&program).unwrap();
+        let mut second = Chip8::new();
+        second.set_random_source(Box::new(SeededRandom::with_seed(3)));
+        second.load_rom(&program).unwrap();
+
+        first.run_frame(2).unwrap();
+        second.run_frame(2).unwrap();
+        assert_eq!(first.cpu().read_vx(0), second.cpu().read_vx(0));
+        assert_eq!(first.cpu().read_vx(1), second.cpu().read_vx(1));
+    }
+
+    #[test]
+    fn restoring_a_snapshot_replays_random_bytes() {
+        let mut chip8 = Chip8::new();
+        chip8.set_random_source(Box::new(SeededRandom::with_seed(9)));
+        // V0 = random, then jump back
+        chip8.load_rom(&rom(&[0xC0FF, 0x1200])).unwrap();
+        let snapshot = chip8.snapshot();
+
+        chip8.step().unwrap();
+        let first = chip8.cpu().read_vx(0);
+        chip8.step().unwrap();
+        chip8.step().unwrap();
+        assert_ne!(chip8.snapshot().random_state(), snapshot.random_state());
+
+        chip8.restore(&snapshot);
+        assert_eq!(chip8.cpu().read_pc(), START);
+        chip8.step().unwrap();
+        assert_eq!(chip8.cpu().read_vx(0), first);
+    }
+
+    #[test]
+    fn cycle_budget_decides_how_many_instructions_run() {
+        let mut chip8 = Chip8::new();
+        // 6XNN takes 6 cycles
+        chip8.load_rom(&rom(&[0x6001; 20])).unwrap();
+
+        chip8.run_frame_cycles(60).unwrap();
+        assert_eq!(chip8.cpu().read_pc(), START + 20);
+    }
+
+    #[test]
+    fn instructions_running_over_a_frame_borrow_from_the_next() {
+        let mut chip8 = Chip8::new();
+        // 7XNN takes 10 cycles
+        chip8.load_rom(&rom(&[0x7001; 10])).unwrap();
+
+        chip8.run_frame_cycles(25).unwrap();
+        assert_eq!(chip8.cpu().read_vx(0), 3);
+        chip8.run_frame_cycles(25).unwrap();
+        assert_eq!(chip8.cpu().read_vx(0), 5);
+    }
+
+    #[test]
+    fn restoring_a_snapshot_brings_back_borrowed_cycles() {
+        let mut chip8 = Chip8::new();
+        // 7XNN takes 10 cycles
+        chip8.load_rom(&rom(&[0x7001; 10])).unwrap();
+        chip8.run_frame_cycles(25).unwrap();
+        let snapshot = chip8.snapshot();
+
+        chip8.run_frame_cycles(25).unwrap();
+        let first = (chip8.cpu().read_pc(), chip8.cpu().read_vx(0));
+        chip8.restore(&snapshot);
+        chip8.run_frame_cycles(25).unwrap();
+        assert_eq!((chip8.cpu().read_pc(), chip8.cpu().read_vx(0)), first);
+    }
+
+    #[test]
+    fn display_wait_ends_the_cycle_budget_at_a_draw() {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom(&rom(&[0xD001, 0xD001, 0x1204])).unwrap();
+
+        chip8.run_frame_cycles(10000).unwrap();
+        assert_eq!(chip8.cpu().read_pc(), START + 2);
+        chip8.run_frame_cycles(10000).unwrap();
+        assert_eq!(chip8.cpu().read_pc(), START + 4);
+    }
+
+    #[test]
+    fn taken_skips_cost_extra_cycles() {
+        let mut chip8 = Chip8::new();
+        // V0 == 0 skips, V0 == 1 does not
+        chip8.load_rom(&rom(&[0x3000, 0x0000, 0x3001])).unwrap();
+
+        chip8.step().unwrap();
+        let taken = chip8.cpu().last_cycles();
+        chip8.step().unwrap();
+        assert_eq!(taken, chip8.cpu().last_cycles() + 4);
+    }
+}
@@ -0,0 +1,72 @@
+// RCA VP-590 colour board used by CHIP-8X. The monochrome framebuffer is
+// left as it is; the board only decides which colour lit pixels and the
+// background are shown in. Colours are 3 bit values: bit 0 red, bit 1
+// blue, bit 2 green.
+pub const BLACK: u8 = 0x0;
+pub const RED: u8 = 0x1;
+pub const BLUE: u8 = 0x2;
+pub const GREEN: u8 = 0x4;
+pub const WHITE: u8 = 0x7;
+
+// Lit pixels are coloured in zones of 8x4 pixels, which split the 64x32
+// screen into 8 columns and 8 rows.
+pub const ZONE_WIDTH: usize = 8;
+pub const ZONE_HEIGHT: usize = 4;
+pub const ZONE_COLUMNS: usize = 8;
+pub const ZONE_ROWS: usize = 8;
+
+// 02A0 steps the background through these, in this order.
+const BACKGROUNDS: [u8; 4] = [BLUE, BLACK, GREEN, RED];
+
+#[derive(Clone)]
+pub struct ColourBoard {
+    background: usize,
+    zones: [u8; ZONE_COLUMNS * ZONE_ROWS],
+}
+
+impl Default for ColourBoard {
+    fn default() -> ColourBoard {
+        ColourBoard::new()
+    }
+}
+
+impl ColourBoard {
+    // Blue background and red foreground, as after power on.
+    pub fn new() -> ColourBoard {
+        ColourBoard {
+            background: 0,
+            zones: [RED; ZONE_COLUMNS * ZONE_ROWS],
+        }
+    }
+
+    pub fn background(&self) -> u8 {
+        BACKGROUNDS[self.background]
+    }
+
+    pub fn cycle_background(&mut self) {
+        self.background = (self.background + 1) % BACKGROUNDS.len();
+    }
+
+    pub fn zone(&self, column: usize, row: usize) -> u8 {
+        self.zones[row * ZONE_COLUMNS + column]
+    }
+
+    // Colours the zones from `columns.0` to `columns.1` and `rows.0` to
+    // `rows.1`, all inclusive. Zones off the screen are ignored.
+    pub fn fill(&mut self, columns: (usize, usize), rows: (usize, usize), colour: u8) {
+        for row in rows.0..=rows.1.min(ZONE_ROWS - 1) {
+            for column in columns.0..=columns.1.min(ZONE_COLUMNS - 1) {
+                self.zones[row * ZONE_COLUMNS + column] = colour & WHITE;
+            }
+        }
+    }
+
+    // Colour a pixel is shown in, lit or not.
+    pub fn colour(&self, x: usize, y: usize, lit: bool) -> u8 {
+        if lit {
+            self.zone((x / ZONE_WIDTH) % ZONE_COLUMNS, (y / ZONE_HEIGHT) % ZONE_ROWS)
+        } else {
+            self.background()
+        }
+    }
+}
@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod colour_test {
+
+    use colour::{ ColourBoard, BLACK, BLUE, GREEN, RED, WHITE };
+
+    #[test]
+    fn starts_red_on_blue() {
+        let board = ColourBoard::new();
+
+        assert_eq!(board.background(), BLUE);
+        assert_eq!(board.colour(0, 0, true), RED);
+        assert_eq!(board.colour(63, 31, false), BLUE);
+    }
+
+    #[test]
+    fn background_cycles_through_four_colours() {
+        let mut board = ColourBoard::new();
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            board.cycle_background();
+            seen.push(board.background());
+        }
+
+        assert_eq!(seen, vec![BLACK, GREEN, RED, BLUE]);
+    }
+
+    #[test]
+    fn fill_colours_zones_of_8x4_pixels() {
+        let mut board = ColourBoard::new();
+        board.fill((1, 2), (0, 0), WHITE);
+
+        assert_eq!(board.colour(7, 0, true), RED);
+        assert_eq!(board.colour(8, 3, true), WHITE);
+        assert_eq!(board.colour(23, 0, true), WHITE);
+        assert_eq!(board.colour(8, 4, true), RED);
+        assert_eq!(board.colour(24, 0, true), RED);
+    }
+}
@@ -0,0 +1,171 @@
+#[cfg(test)]
+mod conformance_test {
+
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use chip8::Chip8;
+    use platform::Platform;
+
+    // What must hold once a case has run.
+    enum Check {
+        V(usize, u8),
+        I(u32),
+        Pc(u16),
+    }
+
+    struct Case {
+        name: &'static str,
+        platform: fn() -> Platform,
+        program: &'static [u16],
+        checks: &'static [Check],
+    }
+
+    // Every case runs its whole program, one step per instruction.
+    const CASES: &[Case] = &[
+        Case { name: "7XNN wraps and leaves VF alone", platform: Platform::chip8,
+               program: &[0x6F05, 0x60FF, 0x7002], checks: &[Check::V(0, 0x01), Check::V(0xF, 0x05)] },
+        Case { name: "8XY4 carries", platform: Platform::chip8,
+               program: &[0x60FF, 0x6102, 0x8014], checks: &[Check::V(0, 0x01), Check::V(0xF, 1)] },
+        Case { name: "8XY4 without carry", platform: Platform::chip8,
+               program: &[0x6001, 0x6102, 0x8014], checks: &[Check::V(0, 0x03), Check::V(0xF, 0)] },
+        Case { name: "8XY4 into VF keeps the flag", platform: Platform::chip8,
+               program: &[0x6FFF, 0x6102, 0x8F14], checks: &[Check::V(0xF, 1)] },
+        Case { name: "8XY5 without borrow", platform: Platform::chip8,
+               program: &[0x6005, 0x6103, 0x8015], checks: &[Check::V(0, 0x02), Check::V(0xF, 1)] },
+        Case { name: "8XY5 borrows", platform: Platform::chip8,
+               program: &[0x6003, 0x6105, 0x8015], checks: &[Check::V(0, 0xFE), Check::V(0xF, 0)] },
+        Case { name: "8XY5 into VF keeps the flag", platform: Platform::chip8,
+               program: &[0x6F05, 0x6103, 0x8F15], checks: &[Check::V(0xF, 1)] },
+        Case { name: "8XY7 without borrow", platform: Platform::chip8,
+               program: &[0x6003, 0x6105, 0x8017], checks: &[Check::V(0, 0x02), Check::V(0xF, 1)] },
+        Case { name: "8XY7 borrows", platform: Platform::chip8,
+               program: &[0x6005, 0x6103, 0x8017], checks: &[Check::V(0, 0xFE), Check::V(0xF, 0)] },
+        Case { name: "8XY6 shifts VY out into VF", platform: Platform::chip8,
+               program: &[0x6105, 0x8016], checks: &[Check::V(0, 0x02), Check::V(0xF, 1)] },
+        Case { name: "8XYE shifts the MSB into VF", platform: Platform::chip8,
+               program: &[0x6181, 0x801E], checks: &[Check::V(0, 0x02), Check::V(0xF, 1)] },
+        Case { name: "8XYE into VF keeps the flag", platform: Platform::chip8,
+               program: &[0x6101, 0x8F1E], checks: &[Check::V(0xF, 0)] },
+        Case { name: "8XYE shifts VX on SUPER-CHIP", platform: Platform::super_chip,
+               program: &[0x6081, 0x6100, 0x801E], checks: &[Check::V(0, 0x02), Check::V(0xF, 1)] },
+        Case { name: "8XY1 resets VF on the VIP", platform: Platform::chip8,
+               program: &[0x6F05, 0x8011], checks: &[Check::V(0xF, 0)] },
+        Case { name: "8XY1 leaves VF on SUPER-CHIP", platform: Platform::super_chip,
+               program: &[0x6F05, 0x8011], checks: &[Check::V(0xF, 5)] },
+        Case { name: "FX1E moves on to the next instruction", platform: Platform::chip8,
+               program: &[0x6005, 0xF01E, 0x6107], checks: &[Check::I(0x05), Check::V(1, 0x07)] },
+        Case { name: "BNNN adds V0 on the VIP", platform: Platform::chip8,
+               program: &[0x6004, 0xB300], checks: &[Check::Pc(0x304)] },
+        Case { name: "BXNN adds VX on SUPER-CHIP", platform: Platform::super_chip,
+               program: &[0x6004, 0x6306, 0xB300], checks: &[Check::Pc(0x306)] },
+        Case { name: "FX55 moves I past the registers on the VIP", platform: Platform::chip8,
+               program: &[0xA300, 0xF155], checks: &[Check::I(0x302)] },
+        Case { name: "FX55 moves I to the last register on the HP48", platform: Platform::hp48,
+               program: &[0xA300, 0xF155], checks: &[Check::I(0x301)] },
+        Case { name: "FX55 leaves I on SUPER-CHIP", platform: Platform::super_chip,
+               program: &[0xA300, 0xF155], checks: &[Check::I(0x300)] },
+    ];
+
+    fn rom(operations: &[u16]) -> Vec<u8> {
+        operations.iter()
+            .flat_map(|operation| vec![(operation >> 8) as u8, (operation & 0x00FF) as u8])
+            .collect()
+    }
+
+    #[test]
+    fn arithmetic_flags_and_quirks_match_the_spec() {
+        let mut failures = Vec::new();
+        for case in CASES {
+            let mut chip8 = Chip8::with_platform((case.platform)());
+            chip8.load_rom(&rom(case.program)).unwrap();
+            for _ in case.program {
+                chip8.step().unwrap();
+            }
+
+            let cpu = chip8.cpu();
+            for check in case.checks {
+                let (expected, actual) = match *check {
+                    Check::V(x, value) => (value as u32, cpu.read_vx(x) as u32),
+                    Check::I(value) => (value, cpu.read_i()),
+                    Check::Pc(value) => (value as u32, cpu.read_pc() as u32),
+                };
+                if expected != actual {
+                    failures.push(format!("{}: expected {:#X}, got {:#X}", case.name, expected, actual));
+                }
+            }
+        }
+        assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+    }
+
+    // Community test ROMs, which report by drawing on the screen. They
+    // are not shipped here: put them in `tests/roms`, or the directory in
+    // CHIP8_TEST_ROMS, each next to a `<rom>.<variant>.expected` file
+    // (say `4-flags.ch8.Chip8.expected`) holding the final screen as rows
+    // of `#` and `.`, and run `cargo test -- --ignored`.
+    struct RomCase {
+        file: &'static str,
+        platform: fn() -> Platform,
+        frames: usize,
+        // Pressed for the first frames and then released, to answer
+        // menus such as the quirks test's platform choice.
+        key: Option<u8>,
+    }
+
+    const ROMS: &[RomCase] = &[
+        RomCase { file: "1-chip8-logo.ch8", platform: Platform::chip8, frames: 60, key: None },
+        RomCase { file: "2-ibm-logo.ch8", platform: Platform::chip8, frames: 60, key: None },
+        RomCase { file: "3-corax+.ch8", platform: Platform::chip8, frames: 60, key: None },
+        RomCase { file: "4-flags.ch8", platform: Platform::chip8, frames: 120, key: None },
+        RomCase { file: "5-quirks.ch8", platform: Platform::chip8, frames: 600, key: Some(0x1) },
+        RomCase { file: "5-quirks.ch8", platform: Platform::super_chip, frames: 600, key: Some(0x2) },
+        RomCase { file: "5-quirks.ch8", platform: Platform::xo_chip, frames: 600, key: Some(0x3) },
+    ];
+
+    const KEY_FRAMES: usize = 10;
+    const INSTRUCTIONS_PER_FRAME: usize = 1000;
+
+    fn roms_directory() -> PathBuf {
+        match env::var_os("CHIP8_TEST_ROMS") {
+            Some(directory) => PathBuf::from(directory),
+            None => PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("roms"),
+        }
+    }
+
+    fn screen(chip8: &Chip8) -> String {
+        let display = chip8.display();
+        display.pixels()
+            .chunks(display.width())
+            .map(|row| row.iter().map(|&pixel| if pixel { '#' } else { '.' }).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    #[test]
+    #[ignore = "needs the community test ROMs, see RomCase"]
+    fn community_test_roms_draw_their_expected_screens() {
+        let directory = roms_directory();
+        for case in ROMS {
+            let path = directory.join(case.file);
+            let rom = fs::read(&path).unwrap_or_else(|error| panic!("{}: {}", path.display(), error));
+            let expected_path = PathBuf::from(format!("{}.{:?}.expected", path.display(), (case.platform)().variant));
+            let mut chip8 = Chip8::with_platform((case.platform)());
+            chip8.load_rom(&rom).unwrap();
+            for frame in 0..case.frames {
+                match case.key {
+                    Some(key) if frame < KEY_FRAMES => chip8.press_key(key),
+                    Some(key) => chip8.release_key(key),
+                    None => {}
+                }
+                chip8.run_frame(INSTRUCTIONS_PER_FRAME)
+                    .unwrap_or_else(|error| panic!("{}: {}", case.file, error));
+            }
+
+            let actual = screen(&chip8);
+            let expected = fs::read_to_string(&expected_path)
+                .unwrap_or_else(|_| panic!("{} is missing, the screen was:\n{}", expected_path.display(), actual));
+            assert_eq!(actual, expected.trim_end(), "{}", case.file);
+        }
+    }
+}
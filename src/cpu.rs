@@ -1,245 +1,957 @@
-use ram::Ram;
-use instruction::Instruction;
-use rand;
-use rand::Rng;
-
+use ram::{Ram, FONT_ADDRESS, FONT_SPRITE_SIZE, BIG_FONT_SPRITE_SIZE};
+use display::{Display, WIDTH, TWO_PAGE_HEIGHT};
+use bus::Bus;
+use stack::{Stack, StackError};
+use error::EmulatorError;
+use timer::Timers;
+use keypad::Keypad;
+use audio::{Audio, PATTERN_SIZE};
+use colour::{ColourBoard, ZONE_WIDTH, ZONE_HEIGHT};
+use port::Port;
+use megachip::{MegaDisplay, SamplePlayer, BlendMode, SAMPLE_HEADER_SIZE};
+use quirks::{Quirks, LoadStoreIncrement};
+use instruction::{Instruction, Opcode};
+use platform::{Platform, Variant};
+use random::RandomSource;
+use timing;
+use timing::SKIP_TAKEN_CYCLES;
+
+// Where programs are loaded and start on most platforms.
 pub const START: u16 = 0x200;
+
+// Hi-Res CHIP-8 programs open with a jump to 0x260, where the VIP
+// interpreter patch lived; the program proper starts at 0x2C0.
+pub const HIRES_JUMP: u16 = 0x260;
+pub const HIRES_START: u16 = 0x2C0;
 pub const CARRY_FLAG: usize = 0xF;
 
+// SUPER-CHIP user flags saved by FX75 and restored by FX85.
+pub const RPL_FLAG_COUNT: usize = 16;
+
+// Progress of an FX0A instruction. Like on the COSMAC VIP, a key only
+// counts once it has been pressed and then released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyWait {
+    Press { register: usize },
+    Release { register: usize, key: u8 },
+}
+
+// What a successful call to `execute` did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    Executed,
+    WaitingForKey,
+    WaitingForVblank,
+    // FXFB is waiting for the host to send a byte to the I/O port.
+    WaitingForInput,
+    // 00FD ended the program. Further calls do nothing.
+    Exited,
+}
+
+#[derive(Clone)]
 pub struct Cpu {
     pc: u16,
-    i: u16,
+    i: u32,
     reg_vx: [u8; 16],
-    pub memory: Ram
+    stack: Stack,
+    timers: Timers,
+    key_wait: Option<KeyWait>,
+    vblank_wait: bool,
+    drawn_this_frame: bool,
+    input_wait: bool,
+    exited: bool,
+    rpl_flags: [u8; RPL_FLAG_COUNT],
+    quirks: Quirks,
+    variant: Variant,
+    start: u16,
+    font_address: u16,
+    // What the last instruction executed cost, in VIP machine cycles.
+    last_cycles: u32
+}
+
+impl Default for Cpu {
+    fn default() -> Cpu {
+        Cpu::new()
+    }
 }
 
 impl Cpu {
     pub fn new() -> Cpu {
+        Cpu::with_stack(Stack::new())
+    }
+
+    pub fn with_stack_depth(depth: usize) -> Cpu {
+        Cpu::with_stack(Stack::with_depth(depth))
+    }
+
+    // A CPU with the instruction set and quirks of `platform`.
+    pub fn for_platform(platform: &Platform) -> Cpu {
+        let mut cpu = Cpu::with_stack_depth(platform.stack_depth);
+        cpu.quirks = platform.quirks;
+        cpu.variant = platform.variant;
+        cpu.start = platform.load_address;
+        cpu.pc = platform.load_address;
+        cpu.font_address = platform.font_address;
+        cpu
+    }
+
+    fn with_stack(stack: Stack) -> Cpu {
         Cpu {
             pc: START,
             i: 0,
             reg_vx: [0; 16],
-            memory: Ram::new()
+            stack,
+            timers: Timers::new(),
+            key_wait: None,
+            vblank_wait: false,
+            drawn_this_frame: false,
+            input_wait: false,
+            exited: false,
+            rpl_flags: [0; RPL_FLAG_COUNT],
+            quirks: Quirks::default(),
+            variant: Variant::Chip8,
+            start: START,
+            font_address: FONT_ADDRESS,
+            last_cycles: 0
+        }
+    }
+
+    // Back to the power-on state: registers, stack and timers cleared
+    // and the program counter on the first instruction of the program.
+    // RPL flags survive, as they would on the calculator.
+    pub fn reset(&mut self) {
+        self.pc = self.start;
+        self.i = 0;
+        self.reg_vx = [0; 16];
+        self.stack.clear();
+        self.timers = Timers::new();
+        self.key_wait = None;
+        self.vblank_wait = false;
+        self.drawn_this_frame = false;
+        self.input_wait = false;
+        self.exited = false;
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    // Address of the first instruction after a reset.
+    pub fn start_address(&self) -> u16 {
+        self.start
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    // Quirks can be changed at any time, even halfway through a program.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    fn clear_screen(&mut self, display: &mut Display) {
+        display.clear();
+        self.advance(2);
+    }
+
+    // Draws the N byte sprite stored at I on (VX, VY). On SUPER-CHIP, N = 0
+    // draws a 16x16 sprite of 32 bytes instead. On XO-CHIP the sprite is
+    // repeated for each selected plane, one after the other.
+    // VF is set to 1 if any pixel was turned off, 0 otherwise.
+    fn draw_sprite(&mut self, x: u8, y: u8, n: u8, ram: &Ram, display: &mut Display) -> Result<(), EmulatorError> {
+        if self.quirks.display_wait && self.drawn_this_frame {
+            self.vblank_wait = true;
+            return Ok(());
         }
+
+        let x = self.reg_vx[x as usize];
+        let y = self.reg_vx[y as usize];
+        let large = n == 0 && self.variant.extends_super_chip();
+        let planes = display.selected_planes().count_ones() as u16;
+        let length = if large { 32 } else { n as u16 } * planes.max(1);
+        let sprite = (0..length)
+            .map(|row| ram.read_long(self.i.wrapping_add(row as u32)))
+            .collect::<Result<Vec<u8>, EmulatorError>>()?;
+
+        let collision = match (large, self.quirks.clip_sprites) {
+            (false, true) => display.draw_sprite(x, y, &sprite),
+            (false, false) => display.draw_sprite_wrapped(x, y, &sprite),
+            (true, true) => display.draw_large_sprite(x, y, &sprite),
+            (true, false) => display.draw_large_sprite_wrapped(x, y, &sprite),
+        };
+        self.drawn_this_frame = true;
+        self.reg_vx[CARRY_FLAG] = if collision { 0x1 } else { 0x0 };
+        self.advance(2);
+        Ok(())
     }
 
-    fn write_on_vx(&mut self, instruction: &Instruction) {
-        let x = instruction.x() as usize;
-        self.reg_vx[x] = instruction.nn();
-        self.pc += 2;
+    // Calls subroutine at NNN. The address of the next instruction is
+    // pushed so 00EE can come back to it.
+    fn call_subroutine(&mut self, nnn: u16) -> Result<(), StackError> {
+        self.stack.push(self.pc.wrapping_add(2))?;
+        self.pc = nnn;
+        Ok(())
     }
 
-    fn skip_on_vx_equal_vy(&mut self, instruction: &Instruction) {
-        let x = instruction.x() as usize;
-        let y = instruction.y() as usize;
+    fn return_from_subroutine(&mut self) -> Result<(), StackError> {
+        self.pc = self.stack.pop()?;
+        Ok(())
+    }
+
+    fn write_on_vx(&mut self, x: u8, nn: u8) {
+        let x = x as usize;
+        self.reg_vx[x] = nn;
+        self.advance(2);
+    }
+
+    fn skip_on_vx_equal_vy(&mut self, x: u8, y: u8) {
+        let x = x as usize;
+        let y = y as usize;
         if self.reg_vx[x] == self.reg_vx[y] {
-            self.pc += 2;
+            self.advance(2);
         }
-        self.pc += 2;
+        self.advance(2);
     }
 
-    fn skip_on_vx_not_equal_vy(&mut self, instruction: &Instruction) {
-        let x = instruction.x() as usize;
-        let y = instruction.y() as usize;
+    fn skip_on_vx_not_equal_vy(&mut self, x: u8, y: u8) {
+        let x = x as usize;
+        let y = y as usize;
         if self.reg_vx[x] != self.reg_vx[y] {
-            self.pc += 2;
+            self.advance(2);
         }
-        self.pc += 2;
+        self.advance(2);
     }
 
-    fn skip_on_vx_not_equal_nn(&mut self, instruction: &Instruction) {
-        let x = instruction.x() as usize;
-        if self.reg_vx[x] != instruction.nn() {
-            self.pc += 2;
+    fn skip_on_vx_not_equal_nn(&mut self, x: u8, nn: u8) {
+        let x = x as usize;
+        if self.reg_vx[x] != nn {
+            self.advance(2);
         }
-        self.pc += 2;
+        self.advance(2);
     }
 
-    fn skip_on_vx_equal_nn(&mut self, instruction: &Instruction) {
-        let x = instruction.x() as usize;
-        if self.reg_vx[x] == instruction.nn() {
-            self.pc += 2;
+    fn skip_on_vx_equal_nn(&mut self, x: u8, nn: u8) {
+        let x = x as usize;
+        if self.reg_vx[x] == nn {
+            self.advance(2);
         }
-        self.pc += 2;
+        self.advance(2);
     }
 
-    fn add_on_vx(&mut self, instruction: &Instruction) {
-        let x = instruction.x() as usize;
-        self.reg_vx[x] += instruction.nn();
-        self.pc += 2;
+    // Adds NN to VX, wrapping around. VF is left alone.
+    fn add_on_vx(&mut self, x: u8, nn: u8) {
+        let x = x as usize;
+        self.reg_vx[x] = self.reg_vx[x].wrapping_add(nn);
+        self.advance(2);
     }
 
-    fn assign_vx_to_vy(&mut self, instruction: &Instruction) {
-        let x = instruction.x() as usize;
-        let y = instruction.y() as usize;
+    fn assign_vx_to_vy(&mut self, x: u8, y: u8) {
+        let x = x as usize;
+        let y = y as usize;
         self.reg_vx[x] = self.reg_vx[y];
-        self.pc += 2;
+        self.advance(2);
     }
 
-    fn bitwise_or(&mut self, instruction: &Instruction) {
-        let x = instruction.x() as usize;
-        let y = instruction.y() as usize;
+    fn bitwise_or(&mut self, x: u8, y: u8) {
+        let x = x as usize;
+        let y = y as usize;
         self.reg_vx[x] |= self.reg_vx[y];
-        self.pc += 2;
+        self.reset_vf_after_logic();
+        self.advance(2);
     }
 
-    fn bitwise_and(&mut self, instruction: &Instruction) {
-        let x = instruction.x() as usize;
-        let y = instruction.y() as usize;
+    fn bitwise_and(&mut self, x: u8, y: u8) {
+        let x = x as usize;
+        let y = y as usize;
         self.reg_vx[x] &= self.reg_vx[y];
-        self.pc += 2;
+        self.reset_vf_after_logic();
+        self.advance(2);
     }
 
-    fn bitwise_xor(&mut self, instruction: &Instruction) {
-        let x = instruction.x() as usize;
-        let y = instruction.y() as usize;
+    fn bitwise_xor(&mut self, x: u8, y: u8) {
+        let x = x as usize;
+        let y = y as usize;
         self.reg_vx[x] ^= self.reg_vx[y];
-        self.pc += 2;
+        self.reset_vf_after_logic();
+        self.advance(2);
     }
 
-    fn adds_vy_to_vx(&mut self, instruction: &Instruction) {
-        let x = instruction.x() as usize;
-        let y = instruction.y() as usize;
-        let sum : u16 = self.reg_vx[x] as u16 + self.reg_vx[y] as u16;
-        self.reg_vx[CARRY_FLAG] = if sum > 0xFF { 0x1 } else { 0x0 };
-        self.reg_vx[x] = sum as u8;
-        self.pc += 2;
+    fn reset_vf_after_logic(&mut self) {
+        if self.quirks.logic_resets_vf {
+            self.reg_vx[CARRY_FLAG] = 0x0;
+        }
     }
 
-    fn subtracts_vy_to_vx(&mut self, instruction: &Instruction) {
-        let x = instruction.x() as usize;
-        let y = instruction.y() as usize;
+    // VX += VY. VF is set to 1 on carry, 0 otherwise.
+    // Like every 8XYN flag, VF is written after VX, so it wins when X is F.
+    fn adds_vy_to_vx(&mut self, x: u8, y: u8) {
+        let (sum, carry) = self.reg_vx[x as usize].overflowing_add(self.reg_vx[y as usize]);
+        self.write_with_flag(x, sum, carry);
+    }
 
-        let subtract = self.reg_vx[x] as i8 - self.reg_vx[y] as i8;
-        self.reg_vx[x] = subtract as u8;
-        self.reg_vx[CARRY_FLAG] = if subtract < 0 { 0x1 } else { 0x0 };
-        self.pc += 2;
+    // VX -= VY. VF is set to 0 on borrow, 1 otherwise.
+    fn subtracts_vy_to_vx(&mut self, x: u8, y: u8) {
+        let (difference, borrow) = self.reg_vx[x as usize].overflowing_sub(self.reg_vx[y as usize]);
+        self.write_with_flag(x, difference, !borrow);
     }
 
-    fn subtracts_vx_to_vy(&mut self, instruction: &Instruction) {
-        let x = instruction.x() as usize;
-        let y = instruction.y() as usize;
+    // VX = VY - VX. VF is set to 0 on borrow, 1 otherwise.
+    fn subtracts_vx_to_vy(&mut self, x: u8, y: u8) {
+        let (difference, borrow) = self.reg_vx[y as usize].overflowing_sub(self.reg_vx[x as usize]);
+        self.write_with_flag(x, difference, !borrow);
+    }
 
-        let subtract = self.reg_vx[y] as i8 - self.reg_vx[x] as i8;
-        self.reg_vx[x] = subtract as u8;
-        self.reg_vx[CARRY_FLAG] = if subtract < 0 { 0x0 } else { 0x1 };
-        self.pc += 2;
+    fn write_with_flag(&mut self, x: u8, value: u8, flag: bool) {
+        self.reg_vx[x as usize] = value;
+        self.reg_vx[CARRY_FLAG] = flag as u8;
+        self.advance(2);
     }
 
-    fn jump_to_address_nnn_plus_v0(&mut self, instruction: &Instruction) {
-        self.pc = START + instruction.nnn() + self.reg_vx[0] as u16;
+    // Jumps to NNN + V0, or to XNN + VX when `jump_uses_vx` is set.
+    fn jump_to_address_nnn_plus_v0(&mut self, nnn: u16) {
+        let offset = if self.quirks.jump_uses_vx { (nnn >> 8) as usize } else { 0 };
+        self.pc = nnn + self.reg_vx[offset] as u16;
     }
 
-    fn jump_to_address_nnn(&mut self, instruction: &Instruction) {
-        self.pc = START + instruction.nnn();
+    fn jump_to_address_nnn(&mut self, nnn: u16) {
+        self.pc = nnn;
     }
 
     // Adds VX to I. 
     // I +=Vx
-    fn adds_vx_to_i(&mut self, instruction: &Instruction) {
-        self.i = self.i + self.reg_vx[instruction.x() as usize] as u16;
+    fn adds_vx_to_i(&mut self, x: u8) {
+        self.i = self.i.wrapping_add(self.reg_vx[x as usize] as u32);
+        self.advance(2);
     }
 
     // Stores V0 to VX (including VX) in memory starting at address I. 
-    // I is increased by 1 for each value written.
-    fn load_from_vx_to_mem(&mut self, instruction: &Instruction) {
-        let x_usize = instruction.x() as usize;
+    // I then moves according to `load_store_increment`.
+    fn load_from_vx_to_mem(&mut self, x: u8, ram: &mut Ram) -> Result<(), EmulatorError> {
+        let x_usize = x as usize;
         for j in 0..( x_usize + 1) {
-            self.memory.write_bytes(self.i, self.reg_vx[j]);
-            self.i += 1;
+            ram.write_long(self.i.wrapping_add(j as u32), self.reg_vx[j])?;
         }
-        self.pc += 2;
+        self.increment_i_after_load_store(x);
+        self.advance(2);
+        Ok(())
     }
 
     // Fills V0 to VX (including VX) with values from memory starting at address I. 
-    // I is increased by 1 for each value written.
-    fn load_to_mem_from_vx(&mut self, instruction: &Instruction) {
-        let x_usize = instruction.x() as usize;
+    // I then moves according to `load_store_increment`.
+    fn load_to_mem_from_vx(&mut self, x: u8, ram: &Ram) -> Result<(), EmulatorError> {
+        let x_usize = x as usize;
         for j in 0..( x_usize + 1) {
-            self.reg_vx[j] = self.memory.read_bytes(self.i);
-            self.i += 1;
+            self.reg_vx[j] = ram.read_long(self.i.wrapping_add(j as u32))?;
         }
-        self.pc += 2;
+        self.increment_i_after_load_store(x);
+        self.advance(2);
+        Ok(())
+    }
+
+    fn increment_i_after_load_store(&mut self, x: u8) {
+        self.i += match self.quirks.load_store_increment {
+            LoadStoreIncrement::XPlusOne => x as u32 + 1,
+            LoadStoreIncrement::X => x as u32,
+            LoadStoreIncrement::None => 0,
+        };
+    }
+
+    // Points I at the font sprite for the hex digit in VX.
+    fn font_sprite_to_i(&mut self, x: u8) {
+        let digit = (self.reg_vx[x as usize] & 0x0F) as u16;
+        self.i = (self.font_address + digit * FONT_SPRITE_SIZE) as u32;
+        self.advance(2);
+    }
+
+    // Points I at the 8x10 SUPER-CHIP sprite for the decimal digit in VX.
+    fn big_font_sprite_to_i(&mut self, x: u8) {
+        let digit = (self.reg_vx[x as usize] & 0x0F) as u16;
+        self.i = (self.font_address + 16 * FONT_SPRITE_SIZE + digit * BIG_FONT_SPRITE_SIZE) as u32;
+        self.advance(2);
+    }
+
+    fn scroll_down(&mut self, n: u8, display: &mut Display) {
+        display.scroll_down(n as usize);
+        self.advance(2);
+    }
+
+    fn scroll_right(&mut self, display: &mut Display) {
+        display.scroll_right(4);
+        self.advance(2);
+    }
+
+    fn scroll_left(&mut self, display: &mut Display) {
+        display.scroll_left(4);
+        self.advance(2);
+    }
+
+    fn set_high_resolution(&mut self, high: bool, display: &mut Display) {
+        display.set_high_resolution(high);
+        self.advance(2);
+    }
+
+    // The opening 1260 of a Hi-Res CHIP-8 program switches the screen to
+    // 64x64 and skips the interpreter patch.
+    fn enter_two_page_mode(&mut self, display: &mut Display) {
+        display.set_resolution(WIDTH, TWO_PAGE_HEIGHT);
+        self.pc = HIRES_START;
+    }
+
+    fn scroll_up(&mut self, n: u8, display: &mut Display) {
+        display.scroll_up(n as usize);
+        self.advance(2);
+    }
+
+    fn select_planes(&mut self, n: u8, display: &mut Display) {
+        display.select_planes(n);
+        self.advance(2);
+    }
+
+    // Points I at the 16 bit address in the word after the instruction.
+    fn load_long_i(&mut self, ram: &Ram) -> Result<(), EmulatorError> {
+        self.i = fetch(ram, self.pc.wrapping_add(2))? as u32;
+        self.advance(4);
+        Ok(())
+    }
+
+    // The registers from VX to VY, in that order even when X > Y.
+    fn register_range(x: u8, y: u8) -> Vec<usize> {
+        if x <= y {
+            (x as usize..=y as usize).collect()
+        } else {
+            (y as usize..=x as usize).rev().collect()
+        }
+    }
+
+    // Stores VX to VY at I onwards. I is left unchanged.
+    fn save_range(&mut self, x: u8, y: u8, ram: &mut Ram) -> Result<(), EmulatorError> {
+        for (offset, register) in Cpu::register_range(x, y).into_iter().enumerate() {
+            ram.write_long(self.i.wrapping_add(offset as u32), self.reg_vx[register])?;
+        }
+        self.advance(2);
+        Ok(())
+    }
+
+    // Loads VX to VY from I onwards. I is left unchanged.
+    fn load_range(&mut self, x: u8, y: u8, ram: &Ram) -> Result<(), EmulatorError> {
+        for (offset, register) in Cpu::register_range(x, y).into_iter().enumerate() {
+            self.reg_vx[register] = ram.read_long(self.i.wrapping_add(offset as u32))?;
+        }
+        self.advance(2);
+        Ok(())
+    }
+
+    // Loads the 16 byte audio pattern at I.
+    fn load_audio(&mut self, ram: &Ram, audio: &mut Audio) -> Result<(), EmulatorError> {
+        let mut pattern = [0; PATTERN_SIZE];
+        for (offset, byte) in pattern.iter_mut().enumerate() {
+            *byte = ram.read_long(self.i.wrapping_add(offset as u32))?;
+        }
+        audio.set_pattern(pattern);
+        self.advance(2);
+        Ok(())
+    }
+
+    fn set_pitch(&mut self, x: u8, audio: &mut Audio) {
+        audio.set_pitch(self.reg_vx[x as usize]);
+        self.advance(2);
+    }
+
+    // XO-CHIP skips treat F000 NNNN as a single instruction.
+    fn skip_long_instruction(&mut self, address: u16, ram: &Ram) -> Result<(), EmulatorError> {
+        let skipped = address.wrapping_add(2);
+        if self.pc == address.wrapping_add(4) && fetch(ram, skipped)? == Opcode::LoadLongI.encode() {
+            self.advance(2);
+        }
+        Ok(())
+    }
+
+    fn cycle_background(&mut self, colour: &mut ColourBoard) {
+        colour.cycle_background();
+        self.advance(2);
+    }
+
+    // Adds VY to VX nibble by nibble, keeping the three low bits of each:
+    // VX and VY hold a pair of zone coordinates.
+    fn add_nibbles(&mut self, x: u8, y: u8) {
+        let x = x as usize;
+        let sum = (self.reg_vx[x] & 0x77) + (self.reg_vx[y as usize] & 0x77);
+        self.reg_vx[x] = sum & 0x77;
+        self.advance(2);
+    }
+
+    // Gives the colour in VY to a block of zones. VX holds the first
+    // column in its low nibble and the last in its high nibble; VX+1
+    // holds the rows the same way.
+    fn colour_zones(&mut self, x: u8, y: u8, colour: &mut ColourBoard) {
+        let columns = self.reg_vx[x as usize];
+        let rows = self.reg_vx[(x as usize + 1) & 0xF];
+        colour.fill(
+            ((columns & 0x0F) as usize, (columns >> 4) as usize),
+            ((rows & 0x0F) as usize, (rows >> 4) as usize),
+            self.reg_vx[y as usize]);
+        self.advance(2);
+    }
+
+    // Gives the colour in VY to the zones under an 8xN sprite drawn at
+    // (VX, VX+1).
+    fn colour_rows(&mut self, x: u8, y: u8, n: u8, colour: &mut ColourBoard) {
+        let column = self.reg_vx[x as usize] as usize / ZONE_WIDTH;
+        let top = self.reg_vx[(x as usize + 1) & 0xF] as usize;
+        let bottom = top + n as usize - 1;
+        colour.fill((column, column), (top / ZONE_HEIGHT, bottom / ZONE_HEIGHT), self.reg_vx[y as usize]);
+        self.advance(2);
+    }
+
+    fn output_port(&mut self, x: u8, port: &mut Port) {
+        port.write(self.reg_vx[x as usize]);
+        self.advance(2);
+    }
+
+    // Reads a byte from the I/O port into VX. Until the host sends one
+    // the CPU stays on this instruction.
+    fn input_port(&mut self, x: u8, port: &mut Port) {
+        match port.read() {
+            Some(value) => {
+                self.reg_vx[x as usize] = value;
+                self.advance(2);
+            }
+            None => self.input_wait = true,
+        }
+    }
+
+    fn set_mega_mode(&mut self, enabled: bool, mega_display: &mut MegaDisplay) {
+        mega_display.set_enabled(enabled);
+        self.advance(2);
+    }
+
+    // In MegaChip8 mode 00E0 shows the frame drawn so far, then clears
+    // the back buffer for the next one.
+    fn update_mega_screen(&mut self, mega_display: &mut MegaDisplay) {
+        mega_display.update();
+        mega_display.clear();
+        self.advance(2);
+    }
+
+    // Points I at the 24 bit address made of NN and the following word.
+    fn load_wide_i(&mut self, nn: u8, ram: &Ram) -> Result<(), EmulatorError> {
+        let low = fetch(ram, self.pc.wrapping_add(2))? as u32;
+        self.i = (nn as u32) << 16 | low;
+        self.advance(4);
+        Ok(())
+    }
+
+    // Loads NN colours from I into palette entries 1 to NN, four bytes
+    // each: alpha, red, green and blue.
+    fn load_palette(&mut self, nn: u8, ram: &Ram, mega_display: &mut MegaDisplay) -> Result<(), EmulatorError> {
+        for entry in 0..nn as u32 {
+            let mut colour = 0;
+            for offset in 0..4 {
+                colour = colour << 8 | ram.read_long(self.i.wrapping_add(entry * 4 + offset))? as u32;
+            }
+            mega_display.set_palette_entry(entry as u8 + 1, colour);
+        }
+        self.advance(2);
+        Ok(())
+    }
+
+    fn set_sprite_width(&mut self, nn: u8, mega_display: &mut MegaDisplay) {
+        mega_display.set_sprite_width(nn);
+        self.advance(2);
+    }
+
+    fn set_sprite_height(&mut self, nn: u8, mega_display: &mut MegaDisplay) {
+        mega_display.set_sprite_height(nn);
+        self.advance(2);
+    }
+
+    fn set_screen_alpha(&mut self, nn: u8, mega_display: &mut MegaDisplay) {
+        mega_display.set_screen_alpha(nn);
+        self.advance(2);
+    }
+
+    fn set_blend_mode(&mut self, n: u8, mega_display: &mut MegaDisplay) {
+        mega_display.set_blend_mode(BlendMode::from_code(n));
+        self.advance(2);
+    }
+
+    fn set_collision_colour(&mut self, nn: u8, mega_display: &mut MegaDisplay) {
+        mega_display.set_collision_colour(nn);
+        self.advance(2);
+    }
+
+    // Plays the sample at I: a 16 bit rate and a 24 bit length, both big
+    // endian, a reserved byte, then the samples. N = 0 loops it.
+    fn play_sample(&mut self, n: u8, ram: &Ram, sampler: &mut SamplePlayer) -> Result<(), EmulatorError> {
+        let byte = |offset: u32| ram.read_long(self.i.wrapping_add(offset)).map(|value| value as u32);
+        let rate = (byte(0)? << 8 | byte(1)?) as u16;
+        let length = byte(2)? << 16 | byte(3)? << 8 | byte(4)?;
+        let samples = (0..length)
+            .map(|offset| ram.read_long(self.i.wrapping_add(SAMPLE_HEADER_SIZE + offset)))
+            .collect::<Result<Vec<u8>, EmulatorError>>()?;
+        sampler.play(samples, rate, n == 0);
+        self.advance(2);
+        Ok(())
+    }
+
+    fn stop_sample(&mut self, sampler: &mut SamplePlayer) {
+        sampler.stop();
+        self.advance(2);
+    }
+
+    // Draws the sprite of palette indexes at I, as large as set by 03NN
+    // and 04NN, on (VX, VY). VF is set when it covers the collision colour.
+    fn draw_mega_sprite(&mut self, x: u8, y: u8, ram: &Ram, mega_display: &mut MegaDisplay) -> Result<(), EmulatorError> {
+        let (width, height) = mega_display.sprite_size();
+        let sprite = (0..(width * height) as u32)
+            .map(|offset| ram.read_long(self.i.wrapping_add(offset)))
+            .collect::<Result<Vec<u8>, EmulatorError>>()?;
+        let collision = mega_display.draw(self.reg_vx[x as usize], self.reg_vx[y as usize], &sprite);
+        self.reg_vx[CARRY_FLAG] = if collision { 0x1 } else { 0x0 };
+        self.advance(2);
+        Ok(())
+    }
+
+    // 00FD: the program is over. PC stays on the exit instruction.
+    fn exit(&mut self) {
+        self.exited = true;
+    }
+
+    // Copies V0 to VX into the RPL user flags.
+    fn save_flags(&mut self, x: u8) {
+        let count = x as usize + 1;
+        self.rpl_flags[..count].copy_from_slice(&self.reg_vx[..count]);
+        self.advance(2);
+    }
+
+    // Copies the RPL user flags back into V0 to VX.
+    fn load_flags(&mut self, x: u8) {
+        let count = x as usize + 1;
+        self.reg_vx[..count].copy_from_slice(&self.rpl_flags[..count]);
+        self.advance(2);
+    }
+
+    // Stores the binary-coded decimal representation of VX at I, I+1 and I+2:
+    // hundreds first, then tens, then units.
+    fn store_bcd(&mut self, x: u8, ram: &mut Ram) -> Result<(), EmulatorError> {
+        let value = self.reg_vx[x as usize];
+        ram.write_long(self.i, value / 100)?;
+        ram.write_long(self.i.wrapping_add(1), (value / 10) % 10)?;
+        ram.write_long(self.i.wrapping_add(2), value % 10)?;
+        self.advance(2);
+        Ok(())
     }
 
     // Shifts VY right by one and copies the result to VX. 
     // VF is set to the value of the least significant bit of VY 
     // before the shift.[2]
-    fn shift_vy_right_and_assign_to_vx(&mut self, instruction: &Instruction){
-        self.reg_vx[CARRY_FLAG] = self.reg_vx[instruction.y() as usize] & 0x01;
-        self.reg_vx[instruction.y() as usize] = self.reg_vx[instruction.y() as usize] >> 1;
-        self.reg_vx[instruction.x() as usize] = self.reg_vx[instruction.y() as usize];
-        self.pc += 2;
+    // Without `shift_uses_vy`, VX is shifted instead of VY.
+    fn shift_vy_right_and_assign_to_vx(&mut self, x: u8, y: u8){
+        let source = self.shift_source(x, y);
+        self.write_with_flag(x, source >> 1, source & 0x01 != 0);
     }
 
     // Shifts VY left by one and copies the result to VX. 
     // VF is set to the value of the most significant bit of VY before the shift.
-    fn shift_vy_left_and_assign_to_vx(&mut self, instruction: &Instruction){
-        self.reg_vx[CARRY_FLAG] = self.reg_vx[instruction.y() as usize] & 0x01;
-        self.reg_vx[instruction.y() as usize] = self.reg_vx[instruction.y() as usize] << 1;
-        self.reg_vx[instruction.x() as usize] = self.reg_vx[instruction.y() as usize];
-        self.pc += 2;
-    }
-
-    // Sets VX to the result of a bitwise and operation on a random number 
-    // (Typically: 0 to 255) and NN.
-    fn bitwise_random(&mut self, instruction: &Instruction){
-        let rnd = rand::thread_rng().gen_range(0, 255);
-        self.reg_vx[instruction.x() as usize] = rnd & instruction.nn();
-        self.pc += 2;
-    }
-
-    pub fn read_vx(&mut self, x: usize) -> u8 {
-        return self.reg_vx[x];
-    }
-
-    pub fn read_i(&mut self) -> u16 {
-        return self.i;
-    }
-
-    pub fn write_i(&mut self, instruction: &Instruction) {
-        self.i = instruction.nnn();
-    }
-
-    pub fn execute(&mut self, ram: &mut Ram) {
-        let lo = ram.read_bytes(self.pc) as u16;
-        let hi = ram.read_bytes(self.pc + 1) as u16;
-        let raw: u16 = (hi << 8) | lo;
-        let instruction = &mut Instruction::new(raw);
-        let op = instruction.op();
-
-        // println!("op: {}", op);
-        // println!("raw: {}", raw);
-        // println!("pc: {}", self.pc);
-
-        // println!("y: {}", instruction.y());
-        // println!("n: {}", instruction.n());
-
-        match (op, instruction.x(), instruction.y(), instruction.n()) {
-            (0x1, _, _, _) => self.jump_to_address_nnn(instruction),
-            (0x3, _, _, _) => self.skip_on_vx_equal_nn(instruction),
-            (0x4, _, _, _) => self.skip_on_vx_not_equal_nn(instruction),
-            (0x5, _, _, _) => self.skip_on_vx_equal_vy(instruction),
-            (0x6, _, _, _) => self.write_on_vx(instruction),
-            (0x7, _, _, _) => self.add_on_vx(instruction),
-            (0x8, _, _, 0x0) => self.assign_vx_to_vy(instruction),
-            (0x8, _, _, 0x1) => self.bitwise_or(instruction),
-            (0x8, _, _, 0x2) => self.bitwise_and(instruction),
-            (0x8, _, _, 0x3) => self.bitwise_xor(instruction),
-            (0x8, _, _, 0x4) => self.adds_vy_to_vx(instruction),
-            (0x8, _, _, 0x5) => self.subtracts_vy_to_vx(instruction),
-            (0x8, _, _, 0x6) => self.shift_vy_right_and_assign_to_vx(instruction),
-            (0x8, _, _, 0x7) => self.subtracts_vx_to_vy(instruction),
-            (0x8, _, _, 0xE) => self.shift_vy_left_and_assign_to_vx(instruction),
-            (0x9, _, _, 0x0) => self.skip_on_vx_not_equal_vy(instruction),
-            (0xA, _, _, _) => self.write_i(instruction),
-            (0xB, _, _, _) => self.jump_to_address_nnn_plus_v0(instruction),
-            (0xC, _, _, _) => self.bitwise_random(instruction),
-            (0xF, _, 0x1, 0xE) => self.adds_vx_to_i(instruction),
-            (0xF, _, 0x5, 0x5) => self.load_from_vx_to_mem(instruction),
-            (0xF, _, 0x6, 0x5) => self.load_to_mem_from_vx(instruction),
-            _ => panic!("Unknown instruction {}", raw)
-        }
-
-    }
-}
\ No newline at end of file
+    // Without `shift_uses_vy`, VX is shifted instead of VY.
+    fn shift_vy_left_and_assign_to_vx(&mut self, x: u8, y: u8){
+        let source = self.shift_source(x, y);
+        self.write_with_flag(x, source << 1, source & 0x80 != 0);
+    }
+
+    fn shift_source(&self, x: u8, y: u8) -> u8 {
+        if self.quirks.shift_uses_vy {
+            self.reg_vx[y as usize]
+        } else {
+            self.reg_vx[x as usize]
+        }
+    }
+
+    // Sets VX to the result of a bitwise and operation on a random number
+    // (0 to 255) and NN.
+    fn bitwise_random(&mut self, x: u8, nn: u8, random: &mut dyn RandomSource){
+        let rnd = random.next_byte();
+        self.reg_vx[x as usize] = rnd & nn;
+        self.advance(2);
+    }
+
+    // Sets VX to the value of the delay timer.
+    fn read_delay_timer(&mut self, x: u8) {
+        self.reg_vx[x as usize] = self.timers.delay();
+        self.advance(2);
+    }
+
+    fn write_delay_timer(&mut self, x: u8) {
+        self.timers.set_delay(self.reg_vx[x as usize]);
+        self.advance(2);
+    }
+
+    fn write_sound_timer(&mut self, x: u8) {
+        self.timers.set_sound(self.reg_vx[x as usize]);
+        self.advance(2);
+    }
+
+    // Skips the next instruction if the key stored in VX is pressed.
+    fn skip_on_key_pressed(&mut self, x: u8, keypad: &Keypad) {
+        let key = self.reg_vx[x as usize] & 0x0F;
+        if keypad.is_pressed(key) {
+            self.advance(2);
+        }
+        self.advance(2);
+    }
+
+    // Skips the next instruction if the key stored in VX is not pressed.
+    fn skip_on_key_not_pressed(&mut self, x: u8, keypad: &Keypad) {
+        let key = self.reg_vx[x as usize] & 0x0F;
+        if !keypad.is_pressed(key) {
+            self.advance(2);
+        }
+        self.advance(2);
+    }
+
+    // Halts until a key is pressed and released, then stores it in VX.
+    // The CPU stays on this instruction; `execute` polls the keypad.
+    fn wait_for_key(&mut self, x: u8) {
+        self.key_wait = Some(KeyWait::Press { register: x as usize });
+    }
+
+    fn poll_key_wait(&mut self, wait: KeyWait, keypad: &Keypad) {
+        self.key_wait = match wait {
+            KeyWait::Press { register } => match keypad.first_pressed() {
+                Some(key) => Some(KeyWait::Release { register, key }),
+                None => Some(wait),
+            },
+            KeyWait::Release { register, key } => {
+                if keypad.is_pressed(key) {
+                    Some(wait)
+                } else {
+                    self.reg_vx[register] = key;
+                    self.advance(2);
+                    None
+                }
+            }
+        };
+    }
+
+    pub fn key_wait(&self) -> Option<KeyWait> {
+        self.key_wait
+    }
+
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.key_wait.is_some()
+    }
+
+    // Counts the timers down by one. Hosts call this 60 times per second,
+    // separately from `execute`, so the timers keep their pace whatever
+    // the number of instructions run per frame.
+    pub fn tick_timers(&mut self) {
+        self.timers.tick();
+    }
+
+    // Signals the vertical blank at the end of a frame. A sprite draw held
+    // back by `display_wait` goes ahead on the next `execute`.
+    pub fn vblank(&mut self) {
+        self.vblank_wait = false;
+        self.drawn_this_frame = false;
+    }
+
+    pub fn is_waiting_for_vblank(&self) -> bool {
+        self.vblank_wait
+    }
+
+    pub fn timers(&self) -> &Timers {
+        &self.timers
+    }
+
+    // True while the buzzer should be heard.
+    pub fn sound_active(&self) -> bool {
+        self.timers.sound_active()
+    }
+
+    pub fn read_vx(&self, x: usize) -> u8 {
+        self.reg_vx[x]
+    }
+
+    pub fn read_i(&self) -> u32 {
+        self.i
+    }
+
+    // Machine cycles the last executed instruction took on the VIP.
+    pub fn last_cycles(&self) -> u32 {
+        self.last_cycles
+    }
+
+    pub fn read_pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn stack(&self) -> &Stack {
+        &self.stack
+    }
+
+    pub fn rpl_flags(&self) -> &[u8; RPL_FLAG_COUNT] {
+        &self.rpl_flags
+    }
+
+    // Hosts restore flags saved by an earlier run of the same program.
+    pub fn set_rpl_flags(&mut self, flags: [u8; RPL_FLAG_COUNT]) {
+        self.rpl_flags = flags;
+    }
+
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    // Moves on `bytes`, wrapping at the top of the 64K PC can reach.
+    // `execute` masks the result to the address space.
+    fn advance(&mut self, bytes: u16) {
+        self.pc = self.pc.wrapping_add(bytes);
+    }
+
+    fn write_i(&mut self, nnn: u16) {
+        self.i = nnn as u32;
+        self.advance(2);
+    }
+
+    // Fetches, decodes and executes the instruction at PC. On error the
+    // CPU is left on the faulting instruction.
+    pub fn execute(&mut self, bus: &mut Bus) -> Result<StepOutcome, EmulatorError> {
+        if self.exited {
+            return Ok(StepOutcome::Exited);
+        }
+        if let Some(wait) = self.key_wait {
+            self.poll_key_wait(wait, &bus.keypad);
+            return Ok(self.outcome());
+        }
+
+        self.input_wait = false;
+        self.pc = (self.pc as u32 & bus.ram.address_mask()) as u16;
+        let address = self.pc;
+        let raw = fetch(&bus.ram, address)?;
+        let opcode = match Instruction::new(raw).decode_for(self.variant) {
+            Some(opcode) => opcode,
+            None => return Err(EmulatorError::UnknownOpcode { opcode: raw, address })
+        };
+        if !self.variant.supports(&opcode) {
+            return Err(EmulatorError::UnsupportedOpcode { opcode: raw, address });
+        }
+        let vx = self.reg_vx[Instruction::new(raw).x() as usize];
+        let stack_fault = |error| match error {
+            StackError::Overflow => EmulatorError::StackOverflow { address },
+            StackError::Underflow => EmulatorError::StackUnderflow { address },
+        };
+
+        match opcode {
+            Opcode::ClearScreen if bus.mega_display.is_enabled() => self.update_mega_screen(&mut bus.mega_display),
+            Opcode::ClearScreen => self.clear_screen(&mut bus.display),
+            Opcode::Sys { nnn: 0x230 } if self.variant == Variant::HiRes => self.clear_screen(&mut bus.display),
+            Opcode::Jump { nnn: HIRES_JUMP } if self.variant == Variant::HiRes && address == self.start =>
+                self.enter_two_page_mode(&mut bus.display),
+            Opcode::Return => self.return_from_subroutine().map_err(stack_fault)?,
+            Opcode::Jump { nnn } => self.jump_to_address_nnn(nnn),
+            Opcode::Call { nnn } => self.call_subroutine(nnn).map_err(stack_fault)?,
+            Opcode::SkipIfEqual { x, nn } => self.skip_on_vx_equal_nn(x, nn),
+            Opcode::SkipIfNotEqual { x, nn } => self.skip_on_vx_not_equal_nn(x, nn),
+            Opcode::SkipIfRegistersEqual { x, y } => self.skip_on_vx_equal_vy(x, y),
+            Opcode::Load { x, nn } => self.write_on_vx(x, nn),
+            Opcode::Add { x, nn } => self.add_on_vx(x, nn),
+            Opcode::Move { x, y } => self.assign_vx_to_vy(x, y),
+            Opcode::Or { x, y } => self.bitwise_or(x, y),
+            Opcode::And { x, y } => self.bitwise_and(x, y),
+            Opcode::Xor { x, y } => self.bitwise_xor(x, y),
+            Opcode::AddRegisters { x, y } => self.adds_vy_to_vx(x, y),
+            Opcode::Subtract { x, y } => self.subtracts_vy_to_vx(x, y),
+            Opcode::ShiftRight { x, y } => self.shift_vy_right_and_assign_to_vx(x, y),
+            Opcode::SubtractReversed { x, y } => self.subtracts_vx_to_vy(x, y),
+            Opcode::ShiftLeft { x, y } => self.shift_vy_left_and_assign_to_vx(x, y),
+            Opcode::SkipIfRegistersNotEqual { x, y } => self.skip_on_vx_not_equal_vy(x, y),
+            Opcode::LoadI { nnn } => self.write_i(nnn),
+            Opcode::JumpWithOffset { nnn } => self.jump_to_address_nnn_plus_v0(nnn),
+            Opcode::Random { x, nn } => self.bitwise_random(x, nn, bus.random.as_mut()),
+            Opcode::Draw { x, y, .. } if bus.mega_display.is_enabled() =>
+                self.draw_mega_sprite(x, y, &bus.ram, &mut bus.mega_display)?,
+            Opcode::Draw { x, y, n } => self.draw_sprite(x, y, n, &bus.ram, &mut bus.display)?,
+            Opcode::SkipIfKeyPressed { x } => self.skip_on_key_pressed(x, &bus.keypad),
+            Opcode::SkipIfKeyNotPressed { x } => self.skip_on_key_not_pressed(x, &bus.keypad),
+            Opcode::ReadDelayTimer { x } => self.read_delay_timer(x),
+            Opcode::WaitForKey { x } => self.wait_for_key(x),
+            Opcode::SetDelayTimer { x } => self.write_delay_timer(x),
+            Opcode::SetSoundTimer { x } => self.write_sound_timer(x),
+            Opcode::AddToI { x } => self.adds_vx_to_i(x),
+            Opcode::LoadFont { x } => self.font_sprite_to_i(x),
+            Opcode::StoreBcd { x } => self.store_bcd(x, &mut bus.ram)?,
+            Opcode::StoreRegisters { x } => self.load_from_vx_to_mem(x, &mut bus.ram)?,
+            Opcode::LoadRegisters { x } => self.load_to_mem_from_vx(x, &bus.ram)?,
+            Opcode::ScrollDown { n } => self.scroll_down(n, &mut bus.display),
+            Opcode::ScrollRight => self.scroll_right(&mut bus.display),
+            Opcode::ScrollLeft => self.scroll_left(&mut bus.display),
+            Opcode::Exit => self.exit(),
+            Opcode::LowResolution => self.set_high_resolution(false, &mut bus.display),
+            Opcode::HighResolution => self.set_high_resolution(true, &mut bus.display),
+            Opcode::LoadBigFont { x } => self.big_font_sprite_to_i(x),
+            Opcode::SaveFlags { x } => self.save_flags(x),
+            Opcode::LoadFlags { x } => self.load_flags(x),
+            Opcode::ScrollUp { n } => self.scroll_up(n, &mut bus.display),
+            Opcode::SaveRange { x, y } => self.save_range(x, y, &mut bus.ram)?,
+            Opcode::LoadRange { x, y } => self.load_range(x, y, &bus.ram)?,
+            Opcode::LoadLongI => self.load_long_i(&bus.ram)?,
+            Opcode::SelectPlanes { n } => self.select_planes(n, &mut bus.display),
+            Opcode::LoadAudio => self.load_audio(&bus.ram, &mut bus.audio)?,
+            Opcode::SetPitch { x } => self.set_pitch(x, &mut bus.audio),
+            Opcode::CycleBackground => self.cycle_background(&mut bus.colour),
+            Opcode::AddNibbles { x, y } => self.add_nibbles(x, y),
+            Opcode::ColourZones { x, y } => self.colour_zones(x, y, &mut bus.colour),
+            Opcode::ColourRows { x, y, n } => self.colour_rows(x, y, n, &mut bus.colour),
+            Opcode::SkipIfSecondKeyPressed { x } => self.skip_on_key_pressed(x, &bus.second_keypad),
+            Opcode::SkipIfSecondKeyNotPressed { x } => self.skip_on_key_not_pressed(x, &bus.second_keypad),
+            Opcode::OutputPort { x } => self.output_port(x, &mut bus.port),
+            Opcode::InputPort { x } => self.input_port(x, &mut bus.port),
+            Opcode::MegaOff => self.set_mega_mode(false, &mut bus.mega_display),
+            Opcode::MegaOn => self.set_mega_mode(true, &mut bus.mega_display),
+            Opcode::LoadWideI { nn } => self.load_wide_i(nn, &bus.ram)?,
+            Opcode::LoadPalette { nn } => self.load_palette(nn, &bus.ram, &mut bus.mega_display)?,
+            Opcode::SpriteWidth { nn } => self.set_sprite_width(nn, &mut bus.mega_display),
+            Opcode::SpriteHeight { nn } => self.set_sprite_height(nn, &mut bus.mega_display),
+            Opcode::ScreenAlpha { nn } => self.set_screen_alpha(nn, &mut bus.mega_display),
+            Opcode::PlaySample { n } => self.play_sample(n, &bus.ram, &mut bus.sampler)?,
+            Opcode::StopSample => self.stop_sample(&mut bus.sampler),
+            Opcode::SetBlendMode { n } => self.set_blend_mode(n, &mut bus.mega_display),
+            Opcode::CollisionColour { nn } => self.set_collision_colour(nn, &mut bus.mega_display),
+            Opcode::Sys { .. } => return Err(EmulatorError::UnsupportedOpcode { opcode: raw, address })
+        }
+
+        self.last_cycles = timing::cycles(&opcode, vx);
+        if opcode.is_skip() && self.pc != address.wrapping_add(2) {
+            self.last_cycles += SKIP_TAKEN_CYCLES;
+        }
+        if opcode.is_skip() && self.variant == Variant::XoChip {
+            self.skip_long_instruction(address, &bus.ram)?;
+        }
+        self.pc = (self.pc as u32 & bus.ram.address_mask()) as u16;
+
+        Ok(self.outcome())
+    }
+
+    fn outcome(&self) -> StepOutcome {
+        if self.exited {
+            StepOutcome::Exited
+        } else if self.is_waiting_for_key() {
+            StepOutcome::WaitingForKey
+        } else if self.vblank_wait {
+            StepOutcome::WaitingForVblank
+        } else if self.input_wait {
+            StepOutcome::WaitingForInput
+        } else {
+            StepOutcome::Executed
+        }
+    }
+}
+
+// Reads the instruction word at `address`.
+// Words are big endian: the high byte comes first.
+fn fetch(ram: &Ram, address: u16) -> Result<u16, EmulatorError> {
+    let hi = ram.read(address)? as u16;
+    let lo = ram.read(address.wrapping_add(1))? as u16;
+    Ok((hi << 8) | lo)
+}
@@ -1,24 +1,32 @@
 #[cfg(test)]
 mod cpu_test {
     use ram::Ram;
-    use cpu::{Cpu, START};
+    use bus::Bus;
+    use cpu::{Cpu, KeyWait, StepOutcome, START, HIRES_START};
+    use platform::{Platform, CHIP8X_START};
+    use colour::{BLACK, GREEN, RED};
+    use ram::{BIG_FONT_ADDRESS, XO_MEMORY_SIZE, MEGA_MEMORY_SIZE};
+    use megachip::BlendMode;
+    use ram::FONT_ADDRESS;
+    use error::EmulatorError;
+    use quirks::{Quirks, LoadStoreIncrement};
 
     fn write_operation_on_ram(ram: &mut Ram, address: u16, value: u16) {
         let hi = (value >> 8) as u8;
         let lo = (value & 0x00FF) as u8;
 
-        ram.write_bytes(address, lo);
-        ram.write_bytes(address + 1, hi);
+        ram.write_bytes(address, hi);
+        ram.write_bytes(address + 1, lo);
     }
 
     #[test]
     fn op_6xnn_assigns_nn_to_vx() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6511);
+        write_operation_on_ram(&mut bus.ram, START, 0x6511);
 
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(5), 0x11);
     }
@@ -26,13 +34,13 @@ mod cpu_test {
     #[test]
     fn op_7xnn_adds_nn_to_vx() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6511);
-        write_operation_on_ram(ram, START + 2, 0x7511);
+        write_operation_on_ram(&mut bus.ram, START, 0x6511);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x7511);
 
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(5), 0x22);
     }
@@ -40,17 +48,17 @@ mod cpu_test {
     #[test]
     fn op_5xy0_skip_instruction_if_vx_equals_vy() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6511);
-        write_operation_on_ram(ram, START + 2, 0x6611);
-        write_operation_on_ram(ram, START + 4, 0x5560);
-        write_operation_on_ram(ram, START + 8, 0x6766);
+        write_operation_on_ram(&mut bus.ram, START, 0x6511);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6611);
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x5560);
+        write_operation_on_ram(&mut bus.ram, START + 8, 0x6766);
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(7), 0x66);
     }
@@ -58,16 +66,16 @@ mod cpu_test {
     #[test]
     fn op_4xnn_skip_instruction_if_vx_not_equals_nn() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6511);
-        write_operation_on_ram(ram, START + 2, 0x4560);
-        write_operation_on_ram(ram, START + 4, 0x6512);
-        write_operation_on_ram(ram, START + 6, 0x6766);
+        write_operation_on_ram(&mut bus.ram, START, 0x6511);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x4560);
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x6512);
+        write_operation_on_ram(&mut bus.ram, START + 6, 0x6766);
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(5), 0x11);
         assert_eq!(cpu.read_vx(7), 0x66);
@@ -76,15 +84,15 @@ mod cpu_test {
     #[test]
     fn op_3xnn_skip_instruction_if_vx_equals_nn() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6511);
-        write_operation_on_ram(ram, START + 2, 0x3511);
-        write_operation_on_ram(ram, START + 6, 0x6766);
+        write_operation_on_ram(&mut bus.ram, START, 0x6511);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x3511);
+        write_operation_on_ram(&mut bus.ram, START + 6, 0x6766);
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(7), 0x66);
     }
@@ -92,16 +100,16 @@ mod cpu_test {
     #[test]
     fn op_9xy0_skip_instruction_if_vx_not_equals_vy() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6111);
-        write_operation_on_ram(ram, START, 0x6222);
-        write_operation_on_ram(ram, START + 2, 0x9120);
-        write_operation_on_ram(ram, START + 6, 0x6766);
+        write_operation_on_ram(&mut bus.ram, START, 0x6111);
+        write_operation_on_ram(&mut bus.ram, START, 0x6222);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x9120);
+        write_operation_on_ram(&mut bus.ram, START + 6, 0x6766);
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(7), 0x66);
     }
@@ -109,11 +117,11 @@ mod cpu_test {
     #[test]
     fn op_annn_sets_i_to_nnn() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0xA123);
+        write_operation_on_ram(&mut bus.ram, START, 0xA123);
 
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_i(), 0x123);
     }
@@ -121,15 +129,15 @@ mod cpu_test {
     #[test]
     fn op_bnnn_jumps_to_nnn_plus_v0() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6005); //v0 = 0x05
-        write_operation_on_ram(ram, START + 2, 0xB001); //pc = v0 + 0x01 = 0x06
-        write_operation_on_ram(ram, START + 6, 0x6110); //v1=0x010
+        write_operation_on_ram(&mut bus.ram, START, 0x6005); //v0 = 0x05
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xB201); //pc = 0x201 + v0 = 0x206
+        write_operation_on_ram(&mut bus.ram, START + 6, 0x6110); //v1=0x010
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(1), 0x010);
     }
@@ -137,16 +145,16 @@ mod cpu_test {
     #[test]
     fn op_8xy0_assigns_vx_to_vy() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6105); //v1 = 0x05
-        write_operation_on_ram(ram, START + 2, 0x8010); //v0 = v1
+        write_operation_on_ram(&mut bus.ram, START, 0x6105); //v1 = 0x05
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x8010); //v0 = v1
 
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(0), 0x0);
 
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(0), 0x05);
     }
@@ -154,20 +162,20 @@ mod cpu_test {
     #[test]
     fn op_8xy1_bitwise_or_operation() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x60E5); //v0 = 0xe5
-        write_operation_on_ram(ram, START + 2, 0x6116); //v1 = 0x16
-        write_operation_on_ram(ram, START + 4, 0x8011);
+        write_operation_on_ram(&mut bus.ram, START, 0x60E5); //v0 = 0xe5
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6116); //v1 = 0x16
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x8011);
         // V0=V0|V1
         // 11100101 E5
         // 00010110 16
         // --------
         // 11110111 F7
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(0), 0xF7);
     }
@@ -175,20 +183,20 @@ mod cpu_test {
     #[test]
     fn op_8xy2_bitwise_and_operation() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x60E5); //v0 = 0xe5
-        write_operation_on_ram(ram, START + 2, 0x6116); //v1 = 0x16
-        write_operation_on_ram(ram, START + 4, 0x8012);
+        write_operation_on_ram(&mut bus.ram, START, 0x60E5); //v0 = 0xe5
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6116); //v1 = 0x16
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x8012);
         // V0=V0|V1
         // 11100101 E5
         // 00010110 16
         // --------
         // 00000100 F7
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(0), 0x04);
     }
@@ -196,21 +204,21 @@ mod cpu_test {
     #[test]
     fn op_8xy3_bitwise_xor_operation() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x60E5); //v0 = 0xe5
-        write_operation_on_ram(ram, START + 2, 0x6116); //v1 = 0x16
+        write_operation_on_ram(&mut bus.ram, START, 0x60E5); //v0 = 0xe5
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6116); //v1 = 0x16
 
-        write_operation_on_ram(ram, START + 4, 0x8013);
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x8013);
         // V0=V0|V1
         // 11100101 E5
         // 00010110 16
         // --------
         // 11110011 F7
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(0), 0xF3);
     }
@@ -218,15 +226,15 @@ mod cpu_test {
     #[test]
     fn op_8xy4_adds_vy_to_vx_without_carry() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6001); //v0 = 0x01
-        write_operation_on_ram(ram, START + 2, 0x6102); //v1 = 0x02
-        write_operation_on_ram(ram, START + 4, 0x8014);
+        write_operation_on_ram(&mut bus.ram, START, 0x6001); //v0 = 0x01
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6102); //v1 = 0x02
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x8014);
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(0), 0x03);
         assert_eq!(cpu.read_vx(0xF), 0x0);
@@ -235,15 +243,15 @@ mod cpu_test {
     #[test]
     fn op_8xy4_adds_vy_to_vx_with_carry() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x60FF); //v0 = 0xFF
-        write_operation_on_ram(ram, START + 2, 0x61FF); //v1 = 0xFF
-        write_operation_on_ram(ram, START + 4, 0x8014);
+        write_operation_on_ram(&mut bus.ram, START, 0x60FF); //v0 = 0xFF
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x61FF); //v1 = 0xFF
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x8014);
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(0), 0xFE);
         assert_eq!(cpu.read_vx(0xF), 0x1);
@@ -252,68 +260,68 @@ mod cpu_test {
     #[test]
     fn op_8xy5_subtracts_vy_from_vx_without_borrow() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6002); //v0 = 0x02
-        write_operation_on_ram(ram, START + 2, 0x6101); //v1 = 0x01
-        write_operation_on_ram(ram, START + 4, 0x8015);
+        write_operation_on_ram(&mut bus.ram, START, 0x6002); //v0 = 0x02
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6101); //v1 = 0x01
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x8015);
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(0), 0x01);
-        assert_eq!(cpu.read_vx(0xF), 0x0);
+        assert_eq!(cpu.read_vx(0xF), 0x1);
     }
 
     #[test]
     fn op_8xy5_subtracts_vy_from_vx_with_borrow() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6002); //v0 = 0x02
-        write_operation_on_ram(ram, START + 2, 0x6103); //v1 = 0x03
-        write_operation_on_ram(ram, START + 4, 0x8015);
+        write_operation_on_ram(&mut bus.ram, START, 0x6002); //v0 = 0x02
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6103); //v1 = 0x03
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x8015);
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(0), 0xFF);
-        assert_eq!(cpu.read_vx(0xF), 0x1);
+        assert_eq!(cpu.read_vx(0xF), 0x0);
     }
 
     #[test]
     fn op_fx1e_adds_vx_to_i() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6001); //v0 = 0x01
-        cpu.execute(ram);
+        write_operation_on_ram(&mut bus.ram, START, 0x6001); //v0 = 0x01
+        cpu.execute(bus).unwrap();
 
-        write_operation_on_ram(ram, START + 2, 0xF01E); //i = 0x01
-        cpu.execute(ram);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xF01E); //i = 0x01
+        cpu.execute(bus).unwrap();
         assert_eq!(cpu.read_i(), 0x01);
 
-        write_operation_on_ram(ram, START + 4, 0x6102); //v1 = 0x02
-        cpu.execute(ram);
-        write_operation_on_ram(ram, START + 6, 0xF11E); //i = 0x03
-        cpu.execute(ram);
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x6102); //v1 = 0x02
+        cpu.execute(bus).unwrap();
+        write_operation_on_ram(&mut bus.ram, START + 6, 0xF11E); //i = 0x03
+        cpu.execute(bus).unwrap();
         assert_eq!(cpu.read_i(), 0x03);
     }
 
     #[test]
     fn op_8xy7_without_borrow() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6002); //v0 = 0x02
-        write_operation_on_ram(ram, START + 2, 0x6103); //v1 = 0x03
-        write_operation_on_ram(ram, START + 4, 0x8017);
+        write_operation_on_ram(&mut bus.ram, START, 0x6002); //v0 = 0x02
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6103); //v1 = 0x03
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x8017);
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(0), 0x1);
         assert_eq!(cpu.read_vx(0xF), 0x1);
@@ -322,15 +330,15 @@ mod cpu_test {
     #[test]
     fn op_8xy7_with_borrow() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6005); //v0 = 0x05
-        write_operation_on_ram(ram, START + 2, 0x6101); //v1 = 0x01
-        write_operation_on_ram(ram, START + 4, 0x8017);
+        write_operation_on_ram(&mut bus.ram, START, 0x6005); //v0 = 0x05
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6101); //v1 = 0x01
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x8017);
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(0), 0xFC);
         assert_eq!(cpu.read_vx(0xF), 0x0);
@@ -339,15 +347,15 @@ mod cpu_test {
     #[test]
     fn op_1nnn_go_to_nnn() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
 
-        write_operation_on_ram(ram, START, 0x6005); //v0 = 0x05
-        write_operation_on_ram(ram, START + 2, 0x1022);
-        write_operation_on_ram(ram, START + 0x22, 0x6015);
+        write_operation_on_ram(&mut bus.ram, START, 0x6005); //v0 = 0x05
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x1222);
+        write_operation_on_ram(&mut bus.ram, START + 0x22, 0x6015);
 
-        cpu.execute(ram);
-        cpu.execute(ram);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
 
         assert_eq!(cpu.read_vx(0x0), 0x15);
     }
@@ -355,34 +363,34 @@ mod cpu_test {
     #[test]
     fn op_fx55_reg_dump() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
     
         for x in 0..6  {
             let address  = 0x6000 + (x * 256);
-            write_operation_on_ram(ram, START + (2 * x), address + x); //vx = 0x0x
-            cpu.execute(ram);
+            write_operation_on_ram(&mut bus.ram, START + (2 * x), address + x); //vx = 0x0x
+            cpu.execute(bus).unwrap();
             assert_eq!(cpu.read_vx(x as usize), x as u8);
         }
 
-        write_operation_on_ram(ram, START + 12, 0xf555); 
-        cpu.execute(ram);
+        write_operation_on_ram(&mut bus.ram, START + 12, 0xf555); 
+        cpu.execute(bus).unwrap();
 
         for x in 0..4  {
-            assert_eq!(cpu.memory.read_bytes(x as u16), x);
+            assert_eq!(bus.ram.read_bytes(x as u16), x);
         }
     }
 
     #[test]
     fn op_fx65_dump_from_mem_to_vx() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
     
         for x in 0..6  {
-            cpu.memory.write_bytes(x, x as u8);
+            bus.ram.write_bytes(x, x as u8);
         }
 
-        write_operation_on_ram(ram, START, 0xf565); 
-        cpu.execute(ram);
+        write_operation_on_ram(&mut bus.ram, START, 0xf565); 
+        cpu.execute(bus).unwrap();
 
         for x in 0..6  {
             assert_eq!(cpu.read_vx(x as usize), x as u8);
@@ -393,41 +401,917 @@ mod cpu_test {
     #[test]
     fn op_8xy6_shift_vy_and_assigns_to_vx() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
     
-        write_operation_on_ram(ram, START, 0x6010);
-        cpu.execute(ram);
+        write_operation_on_ram(&mut bus.ram, START, 0x6010);
+        cpu.execute(bus).unwrap();
 
-        write_operation_on_ram(ram, START + 2, 0x8506);
-        cpu.execute(ram);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x8506);
+        cpu.execute(bus).unwrap();
 
-        assert_eq!(cpu.read_vx(0), 8);
+        assert_eq!(cpu.read_vx(0), 0x10);
         assert_eq!(cpu.read_vx(5), 8);
     }
 
     #[test]
     fn op_8xye_shift_vy_left_by_one_and_assigns_to_vx() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
     
         //v0=1
-        write_operation_on_ram(ram, START, 0x6010);
-        cpu.execute(ram);
+        write_operation_on_ram(&mut bus.ram, START, 0x6010);
+        cpu.execute(bus).unwrap();
 
-        write_operation_on_ram(ram, START + 2, 0x850e);
-        cpu.execute(ram);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x850e);
+        cpu.execute(bus).unwrap();
 
-        assert_eq!(cpu.read_vx(0), 32);
+        assert_eq!(cpu.read_vx(0), 0x10);
         assert_eq!(cpu.read_vx(5), 32);
     }
 
     #[test]
-    #[should_panic]
+    fn op_dxyn_draws_sprite_from_i_at_vx_vy() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        bus.ram.write_bytes(0x300, 0xC0);
+        bus.ram.write_bytes(0x301, 0x40);
+        write_operation_on_ram(&mut bus.ram, START, 0xA300); //i = 0x300
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6003); //v0 = 0x03
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x6104); //v1 = 0x04
+        write_operation_on_ram(&mut bus.ram, START + 6, 0xD012);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+
+        assert!(bus.display.pixel(3, 4));
+        assert!(bus.display.pixel(4, 4));
+        assert!(!bus.display.pixel(3, 5));
+        assert!(bus.display.pixel(4, 5));
+        assert_eq!(cpu.read_vx(0xF), 0x0);
+    }
+
+    #[test]
+    fn op_dxyn_sets_vf_on_collision() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        bus.ram.write_bytes(0x300, 0x80);
+        write_operation_on_ram(&mut bus.ram, START, 0xA300); //i = 0x300
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xD001);
+        write_operation_on_ram(&mut bus.ram, START + 4, 0xD001);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_vx(0xF), 0x0);
+
+        cpu.vblank();
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_vx(0xF), 0x1);
+        assert!(!bus.display.pixel(0, 0));
+    }
+
+    #[test]
+    fn op_00e0_clears_the_screen() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        bus.ram.write_bytes(0x300, 0xFF);
+        write_operation_on_ram(&mut bus.ram, START, 0xA300); //i = 0x300
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xD001);
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x00E0);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        assert!(bus.display.pixel(0, 0));
+
+        cpu.execute(bus).unwrap();
+        assert!(bus.display.pixels().iter().all(|pixel| !pixel));
+    }
+
+    #[test]
+    fn op_2nnn_calls_subroutine_and_00ee_returns() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x2300);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6122); //v1 = 0x22
+        write_operation_on_ram(&mut bus.ram, 0x300, 0x6011); //v0 = 0x11
+        write_operation_on_ram(&mut bus.ram, 0x302, 0x00EE);
+
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_pc(), 0x300);
+        assert_eq!(cpu.stack().frames(), &[START + 2]);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_pc(), START + 2);
+        assert!(cpu.stack().is_empty());
+
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_vx(0), 0x11);
+        assert_eq!(cpu.read_vx(1), 0x22);
+    }
+
+    #[test]
+    fn op_2nnn_reports_stack_overflow() {
+        let mut cpu = Cpu::with_stack_depth(2);
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x2200); //calls itself forever
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+
+        assert_eq!(cpu.execute(bus), Err(EmulatorError::StackOverflow { address: START }));
+        assert_eq!(cpu.stack().len(), 2);
+    }
+
+    #[test]
+    fn op_00ee_reports_stack_underflow() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x00EE);
+
+        assert_eq!(cpu.execute(bus), Err(EmulatorError::StackUnderflow { address: START }));
+    }
+
+    #[test]
+    fn op_fx15_sets_delay_timer_and_fx07_reads_it() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x6005); //v0 = 0x05
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xF015); //delay = v0
+        write_operation_on_ram(&mut bus.ram, START + 4, 0xF107); //v1 = delay
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.timers().delay(), 0x05);
+
+        cpu.tick_timers();
+        cpu.tick_timers();
+        cpu.execute(bus).unwrap();
+
+        assert_eq!(cpu.read_vx(1), 0x03);
+    }
+
+    #[test]
+    fn op_fx18_sets_sound_timer() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x6002); //v0 = 0x02
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xF018); //sound = v0
+
+        cpu.execute(bus).unwrap();
+        assert!(!cpu.sound_active());
+
+        cpu.execute(bus).unwrap();
+        assert!(cpu.sound_active());
+
+        cpu.tick_timers();
+        cpu.tick_timers();
+        assert!(!cpu.sound_active());
+    }
+
+    #[test]
+    fn timers_do_not_move_while_executing_instructions() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x6009); //v0 = 0x09
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xF015); //delay = v0
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x1204); //loop forever
+
+        for _ in 0..100 {
+            cpu.execute(bus).unwrap();
+        }
+
+        assert_eq!(cpu.timers().delay(), 0x09);
+    }
+
+    #[test]
+    fn op_ex9e_skips_if_key_in_vx_is_pressed() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x6007); //v0 = 0x07
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xE09E);
+        write_operation_on_ram(&mut bus.ram, START + 4, 0xE09E);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_pc(), START + 4);
+
+        bus.keypad.press(0x7);
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_pc(), START + 8);
+    }
+
+    #[test]
+    fn op_exa1_skips_if_key_in_vx_is_not_pressed() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x6007); //v0 = 0x07
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xE0A1);
+        write_operation_on_ram(&mut bus.ram, START + 6, 0xE0A1);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_pc(), START + 6);
+
+        bus.keypad.press(0x7);
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_pc(), START + 8);
+    }
+
+    #[test]
+    fn op_fx0a_waits_for_key_press_and_release() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0xF30A);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.key_wait(), Some(KeyWait::Press { register: 3 }));
+        assert_eq!(cpu.read_pc(), START);
+
+        bus.keypad.press(0xB);
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.key_wait(), Some(KeyWait::Release { register: 3, key: 0xB }));
+        assert_eq!(cpu.read_pc(), START);
+
+        bus.keypad.release(0xB);
+        cpu.execute(bus).unwrap();
+        assert!(!cpu.is_waiting_for_key());
+        assert_eq!(cpu.read_vx(3), 0xB);
+        assert_eq!(cpu.read_pc(), START + 2);
+    }
+
+    #[test]
+    fn timers_keep_running_while_waiting_for_key() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x6002); //v0 = 0x02
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xF015); //delay = v0
+        write_operation_on_ram(&mut bus.ram, START + 4, 0xF10A);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.tick_timers();
+        cpu.execute(bus).unwrap();
+
+        assert!(cpu.is_waiting_for_key());
+        assert_eq!(cpu.timers().delay(), 0x01);
+    }
+
+    #[test]
+    fn op_fx29_points_i_to_font_sprite_of_vx() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x600A); //v0 = 0x0A
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xF029);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+
+        assert_eq!(cpu.read_i(), (FONT_ADDRESS + 0xA * 5) as u32);
+        assert_eq!(bus.ram.read_long(cpu.read_i()).unwrap(), 0xF0);
+    }
+
+    #[test]
+    fn op_fx33_stores_bcd_of_vx_at_i() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x60FE); //v0 = 254
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xA300); //i = 0x300
+        write_operation_on_ram(&mut bus.ram, START + 4, 0xF033);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+
+        assert_eq!(bus.ram.read_bytes(0x300), 2);
+        assert_eq!(bus.ram.read_bytes(0x301), 5);
+        assert_eq!(bus.ram.read_bytes(0x302), 4);
+        assert_eq!(cpu.read_i(), 0x300);
+    }
+
+    #[test]
+    fn fetch_wraps_around_the_end_of_memory() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x1FFF);
+        write_operation_on_ram(&mut bus.ram, 0xFFF, 0x6042); //v0 = 0x42, second byte at 0x000
+        write_operation_on_ram(&mut bus.ram, 0x001, 0x6143); //v1 = 0x43
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_vx(0), 0x42);
+
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_vx(1), 0x43);
+    }
+
+    #[test]
+    fn op_fx0a_reports_waiting_for_key() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0xF30A);
+
+        assert_eq!(cpu.execute(bus), Ok(StepOutcome::WaitingForKey));
+        assert_eq!(cpu.execute(bus), Ok(StepOutcome::WaitingForKey));
+
+        bus.keypad.press(0x1);
+        assert_eq!(cpu.execute(bus), Ok(StepOutcome::WaitingForKey));
+
+        bus.keypad.release(0x1);
+        assert_eq!(cpu.execute(bus), Ok(StepOutcome::Executed));
+    }
+
+    fn cpu_with(quirks: Quirks) -> Cpu {
+        let mut cpu = Cpu::new();
+        cpu.set_quirks(quirks);
+        cpu
+    }
+
+    #[test]
+    fn shift_quirk_selects_vx_as_source() {
+        let mut cpu = cpu_with(Quirks { shift_uses_vy: false, ..Quirks::cosmac_vip() });
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x6010); //v0 = 0x10
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6503); //v5 = 0x03
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x8506); //v5 >>= 1
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+
+        assert_eq!(cpu.read_vx(5), 0x01);
+        assert_eq!(cpu.read_vx(0xF), 0x1);
+        assert_eq!(cpu.read_vx(0), 0x10);
+    }
+
+    #[test]
+    fn load_store_quirk_controls_i_increment() {
+        let increments = [
+            (LoadStoreIncrement::XPlusOne, 0x303),
+            (LoadStoreIncrement::X, 0x302),
+            (LoadStoreIncrement::None, 0x300),
+        ];
+
+        for &(increment, expected) in increments.iter() {
+            let mut cpu = cpu_with(Quirks { load_store_increment: increment, ..Quirks::cosmac_vip() });
+            let bus = &mut Bus::new();
+
+            write_operation_on_ram(&mut bus.ram, START, 0xA300); //i = 0x300
+            write_operation_on_ram(&mut bus.ram, START + 2, 0xF255);
+            cpu.execute(bus).unwrap();
+            cpu.execute(bus).unwrap();
+
+            assert_eq!(cpu.read_i(), expected, "{:?}", increment);
+        }
+    }
+
+    #[test]
+    fn jump_quirk_adds_vx_instead_of_v0() {
+        let mut cpu = cpu_with(Quirks { jump_uses_vx: true, ..Quirks::cosmac_vip() });
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x6004); //v0 = 0x04
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6302); //v3 = 0x02
+        write_operation_on_ram(&mut bus.ram, START + 4, 0xB300); //pc = 0x300 + v3
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+
+        assert_eq!(cpu.read_pc(), 0x302);
+    }
+
+    #[test]
+    fn logic_quirk_resets_vf() {
+        for &(resets, expected) in [(true, 0x0), (false, 0x1)].iter() {
+            let mut cpu = cpu_with(Quirks { logic_resets_vf: resets, ..Quirks::cosmac_vip() });
+            let bus = &mut Bus::new();
+
+            write_operation_on_ram(&mut bus.ram, START, 0x6F01); //vf = 0x01
+            write_operation_on_ram(&mut bus.ram, START + 2, 0x8011); //v0 |= v1
+            cpu.execute(bus).unwrap();
+            cpu.execute(bus).unwrap();
+
+            assert_eq!(cpu.read_vx(0xF), expected);
+        }
+    }
+
+    #[test]
+    fn clipping_quirk_off_wraps_sprites() {
+        let mut cpu = cpu_with(Quirks { clip_sprites: false, ..Quirks::cosmac_vip() });
+        let bus = &mut Bus::new();
+
+        bus.ram.write_bytes(0x300, 0xFF);
+        write_operation_on_ram(&mut bus.ram, START, 0xA300); //i = 0x300
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x603C); //v0 = 60
+        write_operation_on_ram(&mut bus.ram, START + 4, 0xD011);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+
+        assert!(bus.display.pixel(63, 0));
+        assert!(bus.display.pixel(0, 0));
+        assert!(bus.display.pixel(3, 0));
+    }
+
+    #[test]
+    fn display_wait_quirk_holds_second_draw_until_vblank() {
+        let mut cpu = cpu_with(Quirks::cosmac_vip());
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0xD001);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xD001);
+
+        assert_eq!(cpu.execute(bus), Ok(StepOutcome::Executed));
+        assert_eq!(cpu.execute(bus), Ok(StepOutcome::WaitingForVblank));
+        assert_eq!(cpu.read_pc(), START + 2);
+
+        cpu.vblank();
+        assert_eq!(cpu.execute(bus), Ok(StepOutcome::Executed));
+        assert_eq!(cpu.read_pc(), START + 4);
+    }
+
+    #[test]
+    fn without_display_wait_draws_never_stall() {
+        let mut cpu = cpu_with(Quirks::super_chip());
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0xD001);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xD001);
+
+        assert_eq!(cpu.execute(bus), Ok(StepOutcome::Executed));
+        assert_eq!(cpu.execute(bus), Ok(StepOutcome::Executed));
+    }
+
+    #[test]
     fn unknown_operation_should_fail() {
         let mut cpu = Cpu::new();
-        let ram = &mut Ram::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x8AAA);
+
+        assert_eq!(cpu.execute(bus), Err(EmulatorError::UnknownOpcode { opcode: 0x8AAA, address: START }));
+        assert_eq!(cpu.read_pc(), START);
+    }
+
+    #[test]
+    fn machine_code_routines_are_unsupported() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+
+        write_operation_on_ram(&mut bus.ram, START, 0x0123);
+
+        assert_eq!(cpu.execute(bus), Err(EmulatorError::UnsupportedOpcode { opcode: 0x0123, address: START }));
+    }
+
+    #[test]
+    fn memory_access_outside_ram_fails() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+        bus.ram = Ram::with_size(0x300);
+
+        write_operation_on_ram(&mut bus.ram, START, 0xA2FF); //i = 0x2FF
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xF155); //writes 0x2FF and 0x300
+
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.execute(bus), Err(EmulatorError::MemoryOutOfBounds { address: 0x300 }));
+    }
+
+    #[test]
+    fn fetch_outside_ram_fails() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+        bus.ram = Ram::with_size(0x300);
+
+        write_operation_on_ram(&mut bus.ram, START, 0x1300);
+
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.execute(bus), Err(EmulatorError::MemoryOutOfBounds { address: 0x300 }));
+        assert_eq!(cpu.read_pc(), 0x300);
+    }
+
+    #[test]
+    fn super_chip_opcodes_are_unsupported_on_chip8() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+        write_operation_on_ram(&mut bus.ram, START, 0x00FF);
+
+        assert_eq!(cpu.execute(bus), Err(EmulatorError::UnsupportedOpcode { opcode: 0x00FF, address: START }));
+    }
+
+    #[test]
+    fn op_00ff_00fe_switch_resolution() {
+        let mut cpu = Cpu::for_platform(&Platform::super_chip());
+        let bus = &mut Bus::new();
+        write_operation_on_ram(&mut bus.ram, START, 0x00FF);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x00FE);
+
+        cpu.execute(bus).unwrap();
+        assert!(bus.display.is_high_resolution());
+        cpu.execute(bus).unwrap();
+        assert!(!bus.display.is_high_resolution());
+        assert_eq!(cpu.read_pc(), START + 4);
+    }
+
+    #[test]
+    fn op_00cn_00fb_00fc_scroll_the_screen() {
+        let mut cpu = Cpu::for_platform(&Platform::super_chip());
+        let bus = &mut Bus::new();
+        bus.display.draw_sprite(8, 0, &[0x80]);
+        write_operation_on_ram(&mut bus.ram, START, 0x00C2);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x00FB);
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x00FC);
+        write_operation_on_ram(&mut bus.ram, START + 6, 0x00FC);
+
+        cpu.execute(bus).unwrap();
+        assert!(bus.display.pixel(8, 2));
+        cpu.execute(bus).unwrap();
+        assert!(bus.display.pixel(12, 2));
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        assert!(bus.display.pixel(4, 2));
+    }
+
+    #[test]
+    fn op_dxy0_draws_16x16_sprite_on_super_chip() {
+        let mut cpu = Cpu::for_platform(&Platform::super_chip());
+        let bus = &mut Bus::new();
+        bus.ram.write_bytes(0x300, 0xFF);
+        bus.ram.write_bytes(0x301, 0xFF);
+        bus.ram.write_bytes(0x31F, 0x01);
+        write_operation_on_ram(&mut bus.ram, START, 0xA300); //i = 0x300
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xD000);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        assert!(bus.display.pixel(15, 0));
+        assert!(bus.display.pixel(15, 15));
+        assert!(!bus.display.pixel(0, 15));
+        assert_eq!(cpu.read_vx(0xF), 0x0);
+    }
+
+    #[test]
+    fn op_fx30_points_i_to_big_digit() {
+        let mut cpu = Cpu::for_platform(&Platform::super_chip());
+        let bus = &mut Bus::new();
+        write_operation_on_ram(&mut bus.ram, START, 0x6507); //v5 = 0x07
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xF530);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_i(), (BIG_FONT_ADDRESS + 70) as u32);
+        assert_eq!(bus.ram.read_long(cpu.read_i()).unwrap(), 0xFF);
+    }
+
+    #[test]
+    fn op_fx75_fx85_save_and_restore_rpl_flags() {
+        let mut cpu = Cpu::for_platform(&Platform::super_chip());
+        let bus = &mut Bus::new();
+        write_operation_on_ram(&mut bus.ram, START, 0x6011); //v0 = 0x11
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6122); //v1 = 0x22
+        write_operation_on_ram(&mut bus.ram, START + 4, 0xF175);
+        write_operation_on_ram(&mut bus.ram, START + 6, 0x6000); //v0 = 0x00
+        write_operation_on_ram(&mut bus.ram, START + 8, 0xF085);
+
+        for _ in 0..4 {
+            cpu.execute(bus).unwrap();
+        }
+        assert_eq!(cpu.rpl_flags()[..3], [0x11, 0x22, 0x00]);
+
+        cpu.reset();
+        for _ in 0..5 {
+            cpu.execute(bus).unwrap();
+        }
+        assert_eq!(cpu.read_vx(0), 0x11);
+    }
+
+    #[test]
+    fn op_00fd_exits_and_stops_execution() {
+        let mut cpu = Cpu::for_platform(&Platform::super_chip());
+        let bus = &mut Bus::new();
+        write_operation_on_ram(&mut bus.ram, START, 0x00FD);
+
+        assert_eq!(cpu.execute(bus), Ok(StepOutcome::Exited));
+        assert_eq!(cpu.execute(bus), Ok(StepOutcome::Exited));
+        assert!(cpu.has_exited());
+        assert_eq!(cpu.read_pc(), START);
+
+        cpu.reset();
+        assert!(!cpu.has_exited());
+    }
+
+    // An XO-CHIP CPU and a bus with the full 64 KB of memory.
+    fn xo_chip() -> (Cpu, Bus) {
+        let mut bus = Bus::new();
+        bus.ram = Ram::with_address_space(XO_MEMORY_SIZE);
+        (Cpu::for_platform(&Platform::xo_chip()), bus)
+    }
+
+    #[test]
+    fn op_f000_loads_16_bit_address_into_i() {
+        let (mut cpu, ref mut bus) = xo_chip();
+        write_operation_on_ram(&mut bus.ram, START, 0xF000);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xE123);
+
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_i(), 0xE123);
+        assert_eq!(cpu.read_pc(), START + 4);
+    }
+
+    #[test]
+    fn skips_step_over_a_whole_f000_nnnn() {
+        let (mut cpu, ref mut bus) = xo_chip();
+        write_operation_on_ram(&mut bus.ram, START, 0x3000); //skip if v0 == 0x00
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xF000);
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x1234);
+
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_pc(), START + 6);
+    }
+
+    #[test]
+    fn op_5xy2_5xy3_save_and_load_register_ranges() {
+        let (mut cpu, ref mut bus) = xo_chip();
+        write_operation_on_ram(&mut bus.ram, START, 0x6211); //v2 = 0x11
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x6322); //v3 = 0x22
+        write_operation_on_ram(&mut bus.ram, START + 4, 0xA300); //i = 0x300
+        write_operation_on_ram(&mut bus.ram, START + 6, 0x5232);
+        write_operation_on_ram(&mut bus.ram, START + 8, 0x5543); //v5 = 0x11, v4 = 0x22
+
+        for _ in 0..5 {
+            cpu.execute(bus).unwrap();
+        }
+        assert_eq!(bus.ram.read_bytes(0x300), 0x11);
+        assert_eq!(bus.ram.read_bytes(0x301), 0x22);
+        assert_eq!(cpu.read_i(), 0x300);
+        assert_eq!(cpu.read_vx(5), 0x11);
+        assert_eq!(cpu.read_vx(4), 0x22);
+    }
+
+    #[test]
+    fn op_fn01_selects_planes_for_drawing() {
+        let (mut cpu, ref mut bus) = xo_chip();
+        bus.ram.write_bytes(0x300, 0x80);
+        bus.ram.write_bytes(0x301, 0x40);
+        write_operation_on_ram(&mut bus.ram, START, 0xF301); //both planes
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xA300); //i = 0x300
+        write_operation_on_ram(&mut bus.ram, START + 4, 0xD001);
+
+        for _ in 0..3 {
+            cpu.execute(bus).unwrap();
+        }
+        assert_eq!(bus.display.selected_planes(), 0x3);
+        assert_eq!(bus.display.colour(0, 0), 1);
+        assert_eq!(bus.display.colour(1, 0), 2);
+    }
+
+    #[test]
+    fn op_f002_fx3a_set_audio_pattern_and_pitch() {
+        let (mut cpu, ref mut bus) = xo_chip();
+        for offset in 0..16 {
+            bus.ram.write_bytes(0x300 + offset, offset as u8);
+        }
+        write_operation_on_ram(&mut bus.ram, START, 0xA300); //i = 0x300
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xF002);
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x6470); //v4 = 0x70
+        write_operation_on_ram(&mut bus.ram, START + 6, 0xF43A);
+
+        for _ in 0..4 {
+            cpu.execute(bus).unwrap();
+        }
+        assert_eq!(bus.audio.pattern()[15], 15);
+        assert_eq!(bus.audio.pitch(), 0x70);
+    }
+
+    #[test]
+    fn xo_chip_opcodes_are_unsupported_on_super_chip() {
+        let mut cpu = Cpu::for_platform(&Platform::super_chip());
+        let bus = &mut Bus::new();
+        write_operation_on_ram(&mut bus.ram, START, 0xF000);
+
+        assert_eq!(cpu.execute(bus), Err(EmulatorError::UnsupportedOpcode { opcode: 0xF000, address: START }));
+    }
+
+    #[test]
+    fn hires_program_enters_two_page_mode_through_1260() {
+        let mut cpu = Cpu::for_platform(&Platform::chip8_hires());
+        let bus = &mut Bus::new();
+        write_operation_on_ram(&mut bus.ram, START, 0x1260);
+        write_operation_on_ram(&mut bus.ram, HIRES_START, 0x0230);
+
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_pc(), HIRES_START);
+        assert_eq!(bus.display.width(), 64);
+        assert_eq!(bus.display.height(), 64);
+
+        bus.display.draw_sprite(0, 63, &[0x80]);
+        cpu.execute(bus).unwrap();
+        assert!(!bus.display.pixel(0, 63));
+        assert_eq!(cpu.read_pc(), HIRES_START + 2);
+    }
+
+    #[test]
+    fn jump_to_0x260_is_plain_elsewhere() {
+        let mut cpu = Cpu::new();
+        let bus = &mut Bus::new();
+        write_operation_on_ram(&mut bus.ram, START, 0x1260);
 
-        write_operation_on_ram(ram, START, 0x8AAA);
-        cpu.execute(ram);
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_pc(), 0x260);
+        assert_eq!(bus.display.height(), 32);
+    }
+
+    #[test]
+    fn chip8x_bxy0_colours_a_block_of_zones() {
+        let mut cpu = Cpu::for_platform(&Platform::chip8x());
+        let bus = &mut Bus::new();
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START, 0x6021); //columns 1 to 2
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START + 2, 0x6130); //rows 0 to 3
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START + 4, 0x6204); //green
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START + 6, 0xB020);
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START + 8, 0x02A0);
+
+        for _ in 0..5 {
+            cpu.execute(bus).unwrap();
+        }
+        assert_eq!(bus.colour.zone(1, 0), GREEN);
+        assert_eq!(bus.colour.zone(2, 3), GREEN);
+        assert_eq!(bus.colour.zone(3, 0), RED);
+        assert_eq!(bus.colour.zone(1, 4), RED);
+        assert_eq!(bus.colour.background(), BLACK);
+        assert_eq!(cpu.read_pc(), CHIP8X_START + 10);
+    }
+
+    #[test]
+    fn chip8x_bxyn_colours_the_zones_under_a_sprite() {
+        let mut cpu = Cpu::for_platform(&Platform::chip8x());
+        let bus = &mut Bus::new();
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START, 0x6010); //x = 16
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START + 2, 0x6106); //y = 6
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START + 4, 0x6207); //white
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START + 6, 0xB023); //rows 6 to 8
+
+        for _ in 0..4 {
+            cpu.execute(bus).unwrap();
+        }
+        assert_eq!(bus.colour.zone(2, 1), 0x7);
+        assert_eq!(bus.colour.zone(2, 2), 0x7);
+        assert_eq!(bus.colour.zone(2, 3), RED);
+    }
+
+    #[test]
+    fn chip8x_5xy1_adds_nibbles_separately() {
+        let mut cpu = Cpu::for_platform(&Platform::chip8x());
+        let bus = &mut Bus::new();
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START, 0x6036);
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START + 2, 0x6125);
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START + 4, 0x5011);
+
+        for _ in 0..3 {
+            cpu.execute(bus).unwrap();
+        }
+        assert_eq!(cpu.read_vx(0), 0x53);
+    }
+
+    #[test]
+    fn chip8x_exf2_exf5_read_the_second_keypad() {
+        let mut cpu = Cpu::for_platform(&Platform::chip8x());
+        let bus = &mut Bus::new();
+        bus.second_keypad.press(0x0);
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START, 0xE0F2);
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START + 4, 0xE0F5);
+
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_pc(), CHIP8X_START + 4);
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_pc(), CHIP8X_START + 6);
+    }
+
+    #[test]
+    fn chip8x_fxf8_fxfb_use_the_io_port() {
+        let mut cpu = Cpu::for_platform(&Platform::chip8x());
+        let bus = &mut Bus::new();
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START, 0x6042);
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START + 2, 0xF0F8);
+        write_operation_on_ram(&mut bus.ram, CHIP8X_START + 4, 0xF1FB);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        assert_eq!(bus.port.output(), 0x42);
+
+        assert_eq!(cpu.execute(bus), Ok(StepOutcome::WaitingForInput));
+        assert_eq!(cpu.read_pc(), CHIP8X_START + 4);
+
+        bus.port.send(0x99);
+        assert_eq!(cpu.execute(bus), Ok(StepOutcome::Executed));
+        assert_eq!(cpu.read_vx(1), 0x99);
+    }
+
+    // A MegaChip8 CPU and a bus with the full 16 MB of memory.
+    fn megachip() -> (Cpu, Bus) {
+        let mut bus = Bus::new();
+        bus.ram = Ram::with_address_space(MEGA_MEMORY_SIZE);
+        (Cpu::for_platform(&Platform::megachip()), bus)
+    }
+
+    #[test]
+    fn megachip_01nn_loads_24_bit_address_into_i() {
+        let (mut cpu, ref mut bus) = megachip();
+        write_operation_on_ram(&mut bus.ram, START, 0x0112);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x3456);
+        bus.ram.write_long(0x123456, 0x77).unwrap();
+        write_operation_on_ram(&mut bus.ram, START + 4, 0xF065);
+
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_i(), 0x123456);
+        assert_eq!(cpu.read_pc(), START + 4);
+        cpu.execute(bus).unwrap();
+        assert_eq!(cpu.read_vx(0), 0x77);
+    }
+
+    #[test]
+    fn megachip_draws_colour_sprites_and_shows_them_on_00e0() {
+        let (mut cpu, ref mut bus) = megachip();
+        for (offset, byte) in [0xFF, 0x11, 0x22, 0x33, 0x01, 0x01].iter().enumerate() {
+            bus.ram.write_bytes(0x300 + offset as u16, *byte);
+        }
+        write_operation_on_ram(&mut bus.ram, START, 0x0011); //mega mode on
+        write_operation_on_ram(&mut bus.ram, START + 2, 0xA300); //i = 0x300
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x0201); //one palette entry
+        write_operation_on_ram(&mut bus.ram, START + 6, 0x0302); //width 2
+        write_operation_on_ram(&mut bus.ram, START + 8, 0x0401); //height 1
+        write_operation_on_ram(&mut bus.ram, START + 10, 0xA304); //i = 0x304
+        write_operation_on_ram(&mut bus.ram, START + 12, 0xD001);
+        write_operation_on_ram(&mut bus.ram, START + 14, 0x00E0);
+
+        for _ in 0..7 {
+            cpu.execute(bus).unwrap();
+        }
+        assert_eq!(bus.mega_display.palette()[1], 0xFF112233);
+        assert_eq!(bus.mega_display.frame()[0], 0);
+        assert!(bus.display.pixels().iter().all(|pixel| !pixel));
+
+        cpu.execute(bus).unwrap();
+        assert_eq!(bus.mega_display.frame()[0], 0xFF112233);
+        assert_eq!(bus.mega_display.frame()[1], 0xFF112233);
+        assert_eq!(bus.mega_display.frame()[2], 0);
+    }
+
+    #[test]
+    fn megachip_060n_plays_the_sample_at_i() {
+        let (mut cpu, ref mut bus) = megachip();
+        for (offset, byte) in [0x1F, 0x40, 0x00, 0x00, 0x02, 0x00, 0x80, 0x90].iter().enumerate() {
+            bus.ram.write_bytes(0x300 + offset as u16, *byte);
+        }
+        write_operation_on_ram(&mut bus.ram, START, 0xA300); //i = 0x300
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x0601); //play once
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x0700);
+
+        cpu.execute(bus).unwrap();
+        cpu.execute(bus).unwrap();
+        assert!(bus.sampler.is_playing());
+        assert!(!bus.sampler.is_looping());
+        assert_eq!(bus.sampler.rate(), 8000);
+        assert_eq!(bus.sampler.samples(), &[0x80, 0x90]);
+
+        cpu.execute(bus).unwrap();
+        assert!(!bus.sampler.is_playing());
+    }
+
+    #[test]
+    fn megachip_080n_09nn_05nn_set_drawing_state() {
+        let (mut cpu, ref mut bus) = megachip();
+        write_operation_on_ram(&mut bus.ram, START, 0x0803);
+        write_operation_on_ram(&mut bus.ram, START + 2, 0x0905);
+        write_operation_on_ram(&mut bus.ram, START + 4, 0x0580);
+
+        for _ in 0..3 {
+            cpu.execute(bus).unwrap();
+        }
+        assert_eq!(bus.mega_display.blend_mode(), BlendMode::Additive);
+        assert_eq!(bus.mega_display.collision_colour(), 0x05);
+        assert_eq!(bus.mega_display.screen_alpha(), 0x80);
     }
 }
@@ -0,0 +1,434 @@
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet};
+
+use disassembler::{mnemonic, Decoded, Disassembly};
+use instruction::Opcode;
+
+// Turns a disassembled ROM into C-like pseudo-code. Every 2NNN target
+// becomes a function and the load address becomes main. Inside them, a
+// jump back to an earlier instruction makes a loop, a skip over a jump
+// forward an if, and a then block ending in a jump past the else block
+// an if/else. Anything that fits no pattern is left as a goto.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Statement {
+    Simple(u32, String),
+    If(u32, String, Vec<Statement>, Vec<Statement>),
+    // `while (true)`, or `do ... while` with a condition.
+    Loop(u32, Vec<Statement>, Option<String>),
+    Goto(u32, u32),
+}
+
+impl Statement {
+    fn address(&self) -> u32 {
+        match *self {
+            Statement::Simple(address, _) |
+            Statement::If(address, ..) |
+            Statement::Loop(address, ..) |
+            Statement::Goto(address, _) => address,
+        }
+    }
+}
+
+// Where `break` and `continue` go in the innermost loop.
+#[derive(Clone, Copy)]
+struct Enclosing {
+    header: u32,
+    exit: u32,
+}
+
+// Sprites are drawn from at most this many bytes, 16x16 ones.
+const LARGEST_SPRITE: u32 = 32;
+
+struct Decompiler<'a> {
+    disassembly: &'a Disassembly,
+    functions: BTreeSet<u32>,
+    // Address and size of every block of data loaded into I.
+    sprites: BTreeMap<u32, u32>,
+}
+
+pub fn decompile(disassembly: &Disassembly) -> String {
+    let mut functions = BTreeSet::new();
+    functions.insert(disassembly.load_address());
+    for decoded in disassembly.instructions().values() {
+        if let Opcode::Call { nnn } = decoded.opcode {
+            if disassembly.instruction(nnn as u32).is_some() {
+                functions.insert(nnn as u32);
+            }
+        }
+    }
+    let mut decompiler = Decompiler { disassembly, functions, sprites: BTreeMap::new() };
+    decompiler.find_sprites();
+    decompiler.render()
+}
+
+impl<'a> Decompiler<'a> {
+    fn instruction(&self, address: u32) -> Decoded {
+        self.disassembly.instructions()[&address]
+    }
+
+    // Sprites are as tall as the tallest draw made from them before I
+    // changes, and never run into code or the next sprite.
+    fn find_sprites(&mut self) {
+        let instructions = self.disassembly.instructions();
+        for (&address, decoded) in instructions {
+            let target = match decoded.opcode {
+                Opcode::LoadI { nnn } => nnn as u32,
+                Opcode::LoadLongI => decoded.long_address,
+                _ => continue,
+            };
+            if target < self.disassembly.load_address() || target >= self.disassembly.end_address() ||
+                self.disassembly.is_code(target) {
+                continue;
+            }
+            let mut rows = 0;
+            for later in instructions.range(address + decoded.size..).map(|(_, later)| later.opcode) {
+                match later {
+                    Opcode::Draw { n: 0, .. } => rows = cmp::max(rows, LARGEST_SPRITE),
+                    Opcode::Draw { n, .. } => rows = cmp::max(rows, n as u32),
+                    Opcode::LoadI { .. } | Opcode::LoadLongI | Opcode::AddToI { .. } |
+                    Opcode::LoadFont { .. } | Opcode::LoadBigFont { .. } | Opcode::Jump { .. } |
+                    Opcode::JumpWithOffset { .. } | Opcode::Call { .. } | Opcode::Return | Opcode::Exit => break,
+                    _ => {}
+                }
+            }
+            let size = self.sprites.entry(target).or_insert(0);
+            *size = cmp::max(*size, rows);
+        }
+
+        // Undrawn data runs up to whatever comes next.
+        let starts: Vec<u32> = self.sprites.keys().cloned().collect();
+        for (index, &start) in starts.iter().enumerate() {
+            let mut limit = starts.get(index + 1).cloned().unwrap_or_else(|| self.disassembly.end_address());
+            if let Some((&code, _)) = self.disassembly.instructions().range(start..).next() {
+                limit = cmp::min(limit, code);
+            }
+            let size = self.sprites.get_mut(&start).unwrap();
+            *size = if *size == 0 { limit - start } else { cmp::min(*size, limit - start) };
+        }
+    }
+
+    // The instructions of the function at `entry`: everything reached from
+    // it without following calls or jumping into another function.
+    fn body(&self, entry: u32) -> BTreeSet<u32> {
+        let mut body = BTreeSet::new();
+        let mut pending = vec![entry];
+        while let Some(address) = pending.pop() {
+            let decoded = match self.disassembly.instruction(address) {
+                Some(decoded) if body.insert(address) => decoded,
+                _ => continue,
+            };
+            let next = address + decoded.size;
+            match decoded.opcode {
+                Opcode::Jump { nnn } | Opcode::JumpWithOffset { nnn } => {
+                    if !self.functions.contains(&(nnn as u32)) || nnn as u32 == entry {
+                        pending.push(nnn as u32);
+                    }
+                }
+                Opcode::Return | Opcode::Exit => {}
+                _ if decoded.opcode.is_skip() => {
+                    pending.push(next);
+                    if let Some(skipped) = self.disassembly.instruction(next) {
+                        pending.push(next + skipped.size);
+                    }
+                }
+                _ => pending.push(next),
+            }
+        }
+        body
+    }
+
+    // The last jump back to `header` before `to`, which closes its loop.
+    fn loop_end(&self, body: &BTreeSet<u32>, header: u32, to: u32) -> Option<u32> {
+        body.range(header..to).rev()
+            .find(|&&address| self.instruction(address).opcode == Opcode::Jump { nnn: header as u16 })
+            .cloned()
+    }
+
+    // Structures the instructions of `body` from `from` up to `to`.
+    // `in_header` is set when `from` starts the loop being structured.
+    fn block(&self, body: &BTreeSet<u32>, from: u32, to: u32, enclosing: Option<Enclosing>, in_header: bool)
+             -> Vec<Statement> {
+        let mut statements = Vec::new();
+        let mut next = body.range(from..to).next().cloned();
+        let mut first = true;
+        while let Some(address) = next {
+            let at_header = first && in_header;
+            first = false;
+            let decoded = self.instruction(address);
+            let after = address + decoded.size;
+
+            let resume = match self.loop_end(body, address, to) {
+                Some(end) if !at_header => {
+                    let exit = end + 2;
+                    let inner = Some(Enclosing { header: address, exit });
+                    // A skip over the jump back leaves the loop, making it a do ... while.
+                    let test = body.range(address..end).next_back().cloned()
+                        .filter(|&test| test + self.instruction(test).size == end && self.instruction(test).opcode.is_skip());
+                    statements.push(match test {
+                        Some(test) => Statement::Loop(address, self.block(body, address, test, inner, true),
+                                                      Some(condition(&self.instruction(test).opcode, true))),
+                        None => Statement::Loop(address, self.block(body, address, end, inner, true), None),
+                    });
+                    exit
+                }
+                _ if decoded.opcode.is_skip() => self.skip(body, address, decoded, to, enclosing, &mut statements),
+                _ => {
+                    statements.push(self.statement(address, &decoded, enclosing));
+                    after
+                }
+            };
+            next = body.range(cmp::min(resume, to)..to).next().cloned();
+        }
+        statements
+    }
+
+    // Structures the skip at `address` with what it skips, returning where
+    // to carry on.
+    fn skip(&self, body: &BTreeSet<u32>, address: u32, decoded: Decoded, to: u32, enclosing: Option<Enclosing>,
+            statements: &mut Vec<Statement>) -> u32 {
+        let skipped_address = address + decoded.size;
+        let skipped = match self.disassembly.instruction(skipped_address) {
+            // What is skipped has to lie within this block, or the skip
+            // would take the block past its own end.
+            Some(skipped) if skipped_address < to && body.contains(&skipped_address) && !skipped.opcode.is_skip() => {
+                skipped
+            }
+            _ => {
+                statements.push(Statement::Simple(address, format!("asm(\"{}\");", mnemonic(&decoded))));
+                return skipped_address;
+            }
+        };
+        let after = skipped_address + skipped.size;
+
+        // Skipping a jump forward runs the code in between only when the
+        // skip is taken. If that code ends by jumping further still, what
+        // it jumps over is the else.
+        if let Opcode::Jump { nnn } = skipped.opcode {
+            let target = nnn as u32;
+            let is_exit = enclosing.is_some_and(|enclosing| enclosing.exit == target || enclosing.header == target);
+            if !is_exit && target > after && target <= to {
+                let last = body.range(after..target).next_back().cloned();
+                if let Some(last) = last.filter(|&last| last + 2 == target) {
+                    if let Opcode::Jump { nnn: end } = self.instruction(last).opcode {
+                        let end = end as u32;
+                        if end > target && end <= to {
+                            statements.push(Statement::If(address, condition(&decoded.opcode, false),
+                                                          self.block(body, after, last, enclosing, false),
+                                                          self.block(body, target, end, enclosing, false)));
+                            return end;
+                        }
+                    }
+                }
+                statements.push(Statement::If(address, condition(&decoded.opcode, false),
+                                              self.block(body, after, target, enclosing, false), Vec::new()));
+                return target;
+            }
+        }
+
+        let then = self.statement(skipped_address, &skipped, enclosing);
+        statements.push(Statement::If(address, condition(&decoded.opcode, true), vec![then], Vec::new()));
+        after
+    }
+
+    // A single instruction as a statement.
+    fn statement(&self, address: u32, decoded: &Decoded, enclosing: Option<Enclosing>) -> Statement {
+        if let Opcode::Jump { nnn } = decoded.opcode {
+            let target = nnn as u32;
+            return match enclosing {
+                Some(enclosing) if enclosing.exit == target => Statement::Simple(address, "break;".to_string()),
+                Some(enclosing) if enclosing.header == target => Statement::Simple(address, "continue;".to_string()),
+                _ => Statement::Goto(address, target),
+            };
+        }
+        Statement::Simple(address, self.text(decoded))
+    }
+
+    fn name(&self, address: u32) -> String {
+        if address == self.disassembly.load_address() {
+            return "main".to_string();
+        }
+        if self.functions.contains(&address) {
+            return format!("sub_{:03X}", address);
+        }
+        if self.sprites.contains_key(&address) {
+            return format!("sprite_{:03X}", address);
+        }
+        format!("0x{:03X}", address)
+    }
+
+    fn text(&self, decoded: &Decoded) -> String {
+        let v = |x: u8| format!("v{:x}", x);
+        match decoded.opcode {
+            Opcode::ClearScreen => "clear();".to_string(),
+            Opcode::Return => "return;".to_string(),
+            Opcode::Call { nnn } => format!("{}();", self.name(nnn as u32)),
+            Opcode::Load { x, nn } => format!("{} = 0x{:02X};", v(x), nn),
+            Opcode::Add { x, nn } => format!("{} += 0x{:02X};", v(x), nn),
+            Opcode::Move { x, y } => format!("{} = {};", v(x), v(y)),
+            Opcode::Or { x, y } => format!("{} |= {};", v(x), v(y)),
+            Opcode::And { x, y } => format!("{} &= {};", v(x), v(y)),
+            Opcode::Xor { x, y } => format!("{} ^= {};", v(x), v(y)),
+            Opcode::AddRegisters { x, y } => format!("{} += {};  // vf = carry", v(x), v(y)),
+            Opcode::Subtract { x, y } => format!("{} -= {};  // vf = no borrow", v(x), v(y)),
+            Opcode::SubtractReversed { x, y } => format!("{} = {} - {};  // vf = no borrow", v(x), v(y), v(x)),
+            Opcode::ShiftRight { x, y } if x == y => format!("{} >>= 1;  // vf = bit out", v(x)),
+            Opcode::ShiftRight { x, y } => format!("{} = {} >> 1;  // vf = bit out", v(x), v(y)),
+            Opcode::ShiftLeft { x, y } if x == y => format!("{} <<= 1;  // vf = bit out", v(x)),
+            Opcode::ShiftLeft { x, y } => format!("{} = {} << 1;  // vf = bit out", v(x), v(y)),
+            Opcode::LoadI { nnn } => format!("i = {};", self.name(nnn as u32)),
+            Opcode::LoadLongI => format!("i = {};", self.name(decoded.long_address)),
+            Opcode::JumpWithOffset { nnn } => format!("goto *(0x{:03X} + v0);", nnn),
+            Opcode::Random { x, nn } => format!("{} = rand() & 0x{:02X};", v(x), nn),
+            Opcode::Draw { x, y, n } => format!("vf = draw({}, {}, {});", v(x), v(y), n),
+            Opcode::ReadDelayTimer { x } => format!("{} = delay;", v(x)),
+            Opcode::WaitForKey { x } => format!("{} = wait_key();", v(x)),
+            Opcode::SetDelayTimer { x } => format!("delay = {};", v(x)),
+            Opcode::SetSoundTimer { x } => format!("sound = {};", v(x)),
+            Opcode::AddToI { x } => format!("i += {};", v(x)),
+            Opcode::LoadFont { x } => format!("i = font({});", v(x)),
+            Opcode::LoadBigFont { x } => format!("i = big_font({});", v(x)),
+            Opcode::StoreBcd { x } => format!("bcd({});", v(x)),
+            Opcode::StoreRegisters { x } => format!("save(v0..{});", v(x)),
+            Opcode::LoadRegisters { x } => format!("load(v0..{});", v(x)),
+            Opcode::SaveRange { x, y } => format!("save({}..{});", v(x), v(y)),
+            Opcode::LoadRange { x, y } => format!("load({}..{});", v(x), v(y)),
+            Opcode::SaveFlags { x } => format!("save_flags(v0..{});", v(x)),
+            Opcode::LoadFlags { x } => format!("load_flags(v0..{});", v(x)),
+            Opcode::ScrollDown { n } => format!("scroll_down({});", n),
+            Opcode::ScrollUp { n } => format!("scroll_up({});", n),
+            Opcode::ScrollLeft => "scroll_left();".to_string(),
+            Opcode::ScrollRight => "scroll_right();".to_string(),
+            Opcode::Exit => "exit();".to_string(),
+            Opcode::LowResolution => "lores();".to_string(),
+            Opcode::HighResolution => "hires();".to_string(),
+            Opcode::SelectPlanes { n } => format!("plane({});", n),
+            Opcode::LoadAudio => "audio();".to_string(),
+            Opcode::SetPitch { x } => format!("pitch = {};", v(x)),
+            _ => format!("asm(\"{}\");", mnemonic(decoded)),
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut bodies = Vec::new();
+        for &entry in &self.functions {
+            let body = self.body(entry);
+            let end = body.iter().next_back().map_or(entry, |&last| last + self.instruction(last).size);
+            bodies.push((entry, self.block(&body, entry, end, None, false)));
+        }
+
+        // Gotos get labels where they land on a statement.
+        let mut starts = BTreeSet::new();
+        let mut targets = BTreeSet::new();
+        for (_, statements) in &bodies {
+            collect(statements, &mut starts, &mut targets);
+        }
+        let labels: BTreeSet<u32> = targets.intersection(&starts).cloned().collect();
+
+        let mut output = format!("// Decompiled from a ROM loaded at 0x{:03X}.\n", self.disassembly.load_address());
+        for (entry, statements) in &bodies {
+            output.push_str(&format!("\nvoid {}() {{\n", self.name(*entry)));
+            self.render_block(statements, 1, &labels, &mut output);
+            output.push_str("}\n");
+        }
+        for (&address, &size) in &self.sprites {
+            let start = (address - self.disassembly.load_address()) as usize;
+            output.push_str(&format!("\nuint8_t {}[{}] = {{\n", self.name(address), size));
+            for &row in &self.disassembly.rom()[start..start + size as usize] {
+                let pixels: String = (0..8).map(|bit| if row & (0x80 >> bit) != 0 { '#' } else { '.' }).collect();
+                output.push_str(&format!("    0x{:02X},  // {}\n", row, pixels));
+            }
+            output.push_str("};\n");
+        }
+        output
+    }
+
+    fn render_block(&self, statements: &[Statement], depth: usize, labels: &BTreeSet<u32>, output: &mut String) {
+        let indent = "    ".repeat(depth);
+        for statement in statements {
+            if labels.contains(&statement.address()) {
+                output.push_str(&format!("{}label_{:03X}:\n", "    ".repeat(depth - 1), statement.address()));
+            }
+            match *statement {
+                Statement::Simple(_, ref text) => output.push_str(&format!("{}{}\n", indent, text)),
+                Statement::Goto(_, target) => output.push_str(&format!("{}{}\n", indent, self.goto(target, labels))),
+                Statement::If(_, ref condition, ref then, ref otherwise) => {
+                    match then.as_slice() {
+                        [Statement::Simple(_, ref text)] if otherwise.is_empty() && !labels.contains(&then[0].address()) =>
+                            output.push_str(&format!("{}if ({}) {}\n", indent, condition, text)),
+                        [Statement::Goto(_, target)] if otherwise.is_empty() =>
+                            output.push_str(&format!("{}if ({}) {}\n", indent, condition, self.goto(*target, labels))),
+                        _ => {
+                            output.push_str(&format!("{}if ({}) {{\n", indent, condition));
+                            self.render_block(then, depth + 1, labels, output);
+                            if !otherwise.is_empty() {
+                                output.push_str(&format!("{}}} else {{\n", indent));
+                                self.render_block(otherwise, depth + 1, labels, output);
+                            }
+                            output.push_str(&format!("{}}}\n", indent));
+                        }
+                    }
+                }
+                Statement::Loop(_, ref body, ref condition) if body.is_empty() => {
+                    let condition = condition.as_ref().map_or("true", String::as_str);
+                    output.push_str(&format!("{}while ({}) {{}}\n", indent, condition));
+                }
+                Statement::Loop(_, ref body, ref condition) => {
+                    match *condition {
+                        Some(_) => output.push_str(&format!("{}do {{\n", indent)),
+                        None => output.push_str(&format!("{}while (true) {{\n", indent)),
+                    }
+                    self.render_block(body, depth + 1, labels, output);
+                    match *condition {
+                        Some(ref condition) => output.push_str(&format!("{}}} while ({});\n", indent, condition)),
+                        None => output.push_str(&format!("{}}}\n", indent)),
+                    }
+                }
+            }
+        }
+    }
+
+    fn goto(&self, target: u32, labels: &BTreeSet<u32>) -> String {
+        if labels.contains(&target) {
+            format!("goto label_{:03X};", target)
+        } else {
+            format!("goto {};", self.name(target))
+        }
+    }
+}
+
+fn collect(statements: &[Statement], starts: &mut BTreeSet<u32>, targets: &mut BTreeSet<u32>) {
+    for statement in statements {
+        starts.insert(statement.address());
+        match *statement {
+            Statement::Goto(_, target) => {
+                targets.insert(target);
+            }
+            Statement::If(_, _, ref then, ref otherwise) => {
+                collect(then, starts, targets);
+                collect(otherwise, starts, targets);
+            }
+            Statement::Loop(_, ref body, _) => collect(body, starts, targets),
+            Statement::Simple(..) => {}
+        }
+    }
+}
+
+// When the skip `opcode` is taken, or with `negate` when it is not.
+fn condition(opcode: &Opcode, negate: bool) -> String {
+    let v = |x: u8| format!("v{:x}", x);
+    let (equal, different) = if negate { ("!=", "==") } else { ("==", "!=") };
+    let not = if negate { "" } else { "!" };
+    let is = if negate { "!" } else { "" };
+    match *opcode {
+        Opcode::SkipIfEqual { x, nn } => format!("{} {} 0x{:02X}", v(x), equal, nn),
+        Opcode::SkipIfNotEqual { x, nn } => format!("{} {} 0x{:02X}", v(x), different, nn),
+        Opcode::SkipIfRegistersEqual { x, y } => format!("{} {} {}", v(x), equal, v(y)),
+        Opcode::SkipIfRegistersNotEqual { x, y } => format!("{} {} {}", v(x), different, v(y)),
+        Opcode::SkipIfKeyPressed { x } => format!("{}key_down({})", is, v(x)),
+        Opcode::SkipIfKeyNotPressed { x } => format!("{}key_down({})", not, v(x)),
+        Opcode::SkipIfSecondKeyPressed { x } => format!("{}key2_down({})", is, v(x)),
+        Opcode::SkipIfSecondKeyNotPressed { x } => format!("{}key2_down({})", not, v(x)),
+        _ => unreachable!("not a skip"),
+    }
+}
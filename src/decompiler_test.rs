@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod decompiler_test {
+
+    use assembler::assemble;
+    use decompiler::decompile;
+    use disassembler::disassemble;
+    use platform::Variant;
+
+    fn decompiled(source: &str) -> String {
+        let assembly = assemble(source).unwrap_or_else(|error| panic!("{}", error));
+        decompile(&disassemble(&assembly.rom, 0x200, Variant::Chip8))
+    }
+
+    #[test]
+    fn skips_over_jumps_become_if_and_else() {
+        let source = "    SE V3, 1\n    JP other\n    LD V4, 1\n    JP end\nother:\n    LD V4, 2\nend:\n    SE V1, 2\n    LD V2, 3\n    RET";
+
+        assert_eq!(decompiled(source), "// Decompiled from a ROM loaded at 0x200.
+
+void main() {
+    if (v3 == 0x01) {
+        v4 = 0x01;
+    } else {
+        v4 = 0x02;
+    }
+    if (v1 != 0x02) v2 = 0x03;
+    return;
+}
+");
+    }
+
+    #[test]
+    fn jumps_back_become_loops_with_breaks() {
+        let source = "loop:\n    SNE V0, 5\n    JP done\n    ADD V0, 1\n    JP loop\ndone:\n    ADD V1, 1\n    SE V1, 10\n    JP done\nwait:\n    SKNP V5\n    JP wait\nhalt:\n    JP halt";
+
+        assert_eq!(decompiled(source), "// Decompiled from a ROM loaded at 0x200.
+
+void main() {
+    while (true) {
+        if (v0 == 0x05) break;
+        v0 += 0x01;
+    }
+    do {
+        v1 += 0x01;
+    } while (v1 != 0x0A);
+    while (key_down(v5)) {}
+    while (true) {}
+}
+");
+    }
+
+    #[test]
+    fn call_targets_become_functions() {
+        let output = decompiled("    CALL draw\n    CALL draw\nhalt:\n    JP halt\ndraw:\n    LD V0, DT\n    RET");
+
+        assert!(output.contains("void main() {\n    sub_206();\n    sub_206();\n    while (true) {}\n}\n"), "{}", output);
+        assert!(output.contains("void sub_206() {\n    v0 = delay;\n    return;\n}\n"), "{}", output);
+    }
+
+    #[test]
+    fn unstructured_jumps_are_left_as_gotos_to_labels() {
+        let source = "    SE V0, 1\n    JP skip\n    LD V1, 1\nback:\n    LD V2, 2\n    CALL sub\nskip:\n    RET\nsub:\n    SE V0, 2\n    JP back\n    RET";
+        let output = decompiled(source);
+
+        assert!(output.contains("    label_206:\n        v2 = 0x02;\n"), "{}", output);
+        assert!(output.contains("    if (v0 != 0x02) goto label_206;\n"), "{}", output);
+    }
+
+    #[test]
+    fn skips_past_the_end_of_an_if_are_left_as_asm() {
+        // The second SE sits at the end of the if and skips the first
+        // instruction after it.
+        let rom = [0x30, 0x00, 0x12, 0x08, 0x60, 0x00, 0x30, 0x00, 0x61, 0x01, 0x12, 0x0A];
+        let output = decompile(&disassemble(&rom, 0x200, Variant::Chip8));
+
+        assert!(output.contains("    if (v0 == 0x00) {\n        v0 = 0x00;\n        asm(\"SE V0, #00\");\n    }\n    v1 = 0x01;\n"),
+                "{}", output);
+    }
+
+    #[test]
+    fn sprites_are_as_tall_as_their_draws() {
+        let output = decompiled("    LD I, ball\n    DRW V0, V1, 2\n    LD I, rest\n    RET\nball:\n    DB #3C, #FF, #81\nrest:\n    DB #01, #02");
+
+        assert!(output.contains("uint8_t sprite_208[2] = {\n    0x3C,  // ..####..\n    0xFF,  // ########\n};\n"), "{}", output);
+        // Data that is never drawn runs up to the end of the ROM.
+        assert!(output.contains("uint8_t sprite_20B[2] = {\n"), "{}", output);
+    }
+}
@@ -0,0 +1,354 @@
+use std::cmp;
+use std::collections::BTreeMap;
+
+use instruction::{Instruction, Opcode};
+use platform::Variant;
+
+// Turns ROMs back into assembly the assembler accepts. Code is told from
+// data by following every path execution can take from the load address;
+// whatever no path reaches is shown as bytes.
+
+// An instruction as found in a ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decoded {
+    pub opcode: Opcode,
+    // In bytes: 4 for F000 NNNN and 01NN NNNN, 2 for the rest.
+    pub size: u32,
+    // The address in the second word of F000 NNNN and 01NN NNNN.
+    pub long_address: u32,
+}
+
+// Why an address has a label. When there is more than one reason, the
+// last one here wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LabelKind {
+    // Loaded into I, which is mostly done for sprites.
+    Sprite,
+    Jump,
+    Subroutine,
+    // The load address.
+    Start,
+}
+
+// Decodes the instruction at `offset` in `rom`, or None when there is no
+// whole instruction there or `variant` would refuse to run it.
+pub fn decode(rom: &[u8], offset: usize, variant: Variant) -> Option<Decoded> {
+    let word = |offset: usize| -> Option<u16> {
+        match (rom.get(offset), rom.get(offset + 1)) {
+            (Some(&high), Some(&low)) => Some((high as u16) << 8 | low as u16),
+            _ => None,
+        }
+    };
+    let opcode = Instruction::new(word(offset)?).decode_for(variant)?;
+    if !variant.supports(&opcode) {
+        return None;
+    }
+
+    match opcode {
+        // Machine code calls only run on the Hi-Res interpreter.
+        Opcode::Sys { nnn: 0x230 } if variant == Variant::HiRes => {}
+        Opcode::Sys { .. } => return None,
+        Opcode::LoadLongI => {
+            let long_address = word(offset + 2)? as u32;
+            return Some(Decoded { opcode, size: 4, long_address });
+        }
+        Opcode::LoadWideI { nn } => {
+            let long_address = (nn as u32) << 16 | word(offset + 2)? as u32;
+            return Some(Decoded { opcode, size: 4, long_address });
+        }
+        _ => {}
+    }
+    Some(Decoded { opcode, size: 2, long_address: 0 })
+}
+
+// The assembly for `decoded`, with addresses in hex.
+pub fn mnemonic(decoded: &Decoded) -> String {
+    render(decoded, &|address| hex_address(address))
+}
+
+fn hex_address(address: u32) -> String {
+    format!("#{:03X}", address)
+}
+
+// The assembly for `decoded`, naming addresses with `address`.
+fn render(decoded: &Decoded, address: &dyn Fn(u32) -> String) -> String {
+    let v = |x: u8| format!("V{:X}", x);
+    match decoded.opcode {
+        Opcode::Sys { nnn } => format!("SYS {}", address(nnn as u32)),
+        Opcode::ClearScreen => "CLS".to_string(),
+        Opcode::Return => "RET".to_string(),
+        Opcode::Jump { nnn } => format!("JP {}", address(nnn as u32)),
+        Opcode::Call { nnn } => format!("CALL {}", address(nnn as u32)),
+        Opcode::SkipIfEqual { x, nn } => format!("SE {}, #{:02X}", v(x), nn),
+        Opcode::SkipIfNotEqual { x, nn } => format!("SNE {}, #{:02X}", v(x), nn),
+        Opcode::SkipIfRegistersEqual { x, y } => format!("SE {}, {}", v(x), v(y)),
+        Opcode::Load { x, nn } => format!("LD {}, #{:02X}", v(x), nn),
+        Opcode::Add { x, nn } => format!("ADD {}, #{:02X}", v(x), nn),
+        Opcode::Move { x, y } => format!("LD {}, {}", v(x), v(y)),
+        Opcode::Or { x, y } => format!("OR {}, {}", v(x), v(y)),
+        Opcode::And { x, y } => format!("AND {}, {}", v(x), v(y)),
+        Opcode::Xor { x, y } => format!("XOR {}, {}", v(x), v(y)),
+        Opcode::AddRegisters { x, y } => format!("ADD {}, {}", v(x), v(y)),
+        Opcode::Subtract { x, y } => format!("SUB {}, {}", v(x), v(y)),
+        Opcode::ShiftRight { x, y } => format!("SHR {}, {}", v(x), v(y)),
+        Opcode::SubtractReversed { x, y } => format!("SUBN {}, {}", v(x), v(y)),
+        Opcode::ShiftLeft { x, y } => format!("SHL {}, {}", v(x), v(y)),
+        Opcode::SkipIfRegistersNotEqual { x, y } => format!("SNE {}, {}", v(x), v(y)),
+        Opcode::LoadI { nnn } => format!("LD I, {}", address(nnn as u32)),
+        Opcode::JumpWithOffset { nnn } => format!("JP V0, {}", address(nnn as u32)),
+        Opcode::Random { x, nn } => format!("RND {}, #{:02X}", v(x), nn),
+        Opcode::Draw { x, y, n } => format!("DRW {}, {}, {}", v(x), v(y), n),
+        Opcode::SkipIfKeyPressed { x } => format!("SKP {}", v(x)),
+        Opcode::SkipIfKeyNotPressed { x } => format!("SKNP {}", v(x)),
+        Opcode::ReadDelayTimer { x } => format!("LD {}, DT", v(x)),
+        Opcode::WaitForKey { x } => format!("LD {}, K", v(x)),
+        Opcode::SetDelayTimer { x } => format!("LD DT, {}", v(x)),
+        Opcode::SetSoundTimer { x } => format!("LD ST, {}", v(x)),
+        Opcode::AddToI { x } => format!("ADD I, {}", v(x)),
+        Opcode::LoadFont { x } => format!("LD F, {}", v(x)),
+        Opcode::StoreBcd { x } => format!("LD B, {}", v(x)),
+        Opcode::StoreRegisters { x } => format!("LD [I], {}", v(x)),
+        Opcode::LoadRegisters { x } => format!("LD {}, [I]", v(x)),
+        Opcode::ScrollDown { n } => format!("SCD {}", n),
+        Opcode::ScrollRight => "SCR".to_string(),
+        Opcode::ScrollLeft => "SCL".to_string(),
+        Opcode::Exit => "EXIT".to_string(),
+        Opcode::LowResolution => "LOW".to_string(),
+        Opcode::HighResolution => "HIGH".to_string(),
+        Opcode::LoadBigFont { x } => format!("LD HF, {}", v(x)),
+        Opcode::SaveFlags { x } => format!("LD R, {}", v(x)),
+        Opcode::LoadFlags { x } => format!("LD {}, R", v(x)),
+        Opcode::ScrollUp { n } => format!("SCU {}", n),
+        Opcode::SaveRange { x, y } => format!("SAVE {}, {}", v(x), v(y)),
+        Opcode::LoadRange { x, y } => format!("LOAD {}, {}", v(x), v(y)),
+        Opcode::LoadLongI => format!("LD I, LONG {}", address(decoded.long_address)),
+        Opcode::SelectPlanes { n } => format!("PLANE {}", n),
+        Opcode::LoadAudio => "AUDIO".to_string(),
+        Opcode::SetPitch { x } => format!("PITCH {}", v(x)),
+        Opcode::CycleBackground => "CBG".to_string(),
+        Opcode::AddNibbles { x, y } => format!("ADDN {}, {}", v(x), v(y)),
+        Opcode::ColourZones { x, y } => format!("COL {}, {}", v(x), v(y)),
+        Opcode::ColourRows { x, y, n } => format!("COL {}, {}, {}", v(x), v(y), n),
+        Opcode::SkipIfSecondKeyPressed { x } => format!("SKP2 {}", v(x)),
+        Opcode::SkipIfSecondKeyNotPressed { x } => format!("SKNP2 {}", v(x)),
+        Opcode::OutputPort { x } => format!("OUT {}", v(x)),
+        Opcode::InputPort { x } => format!("IN {}", v(x)),
+        Opcode::MegaOff => "MEGAOFF".to_string(),
+        Opcode::MegaOn => "MEGAON".to_string(),
+        Opcode::LoadWideI { .. } => format!("LDHI {}", address(decoded.long_address)),
+        Opcode::LoadPalette { nn } => format!("LDPAL #{:02X}", nn),
+        Opcode::SpriteWidth { nn } => format!("SPRW #{:02X}", nn),
+        Opcode::SpriteHeight { nn } => format!("SPRH #{:02X}", nn),
+        Opcode::ScreenAlpha { nn } => format!("ALPHA #{:02X}", nn),
+        Opcode::PlaySample { n } => format!("DIGISND {}", n),
+        Opcode::StopSample => "STOPSND".to_string(),
+        Opcode::SetBlendMode { n } => format!("BMODE {}", n),
+        Opcode::CollisionColour { nn } => format!("CCOL #{:02X}", nn),
+    }
+}
+
+// A ROM split into code and data.
+pub struct Disassembly {
+    rom: Vec<u8>,
+    load_address: u32,
+    variant: Variant,
+    // Keyed by address, like everything else here.
+    instructions: BTreeMap<u32, Decoded>,
+    labels: BTreeMap<u32, LabelKind>,
+}
+
+// Data is listed this many bytes to a line, sprites one row to a line.
+const DATA_PER_LINE: usize = 8;
+
+// Where listing comments start.
+const COMMENT_COLUMN: usize = 28;
+
+// Finds the code in `rom`, loaded at `load_address`, by walking every
+// path from there. Computed jumps are followed to their base address,
+// which is where jump tables usually are.
+pub fn disassemble(rom: &[u8], load_address: u16, variant: Variant) -> Disassembly {
+    let load_address = load_address as u32;
+    let mut disassembly = Disassembly {
+        rom: rom.to_vec(),
+        load_address,
+        variant,
+        instructions: BTreeMap::new(),
+        labels: BTreeMap::new(),
+    };
+    disassembly.add_label(load_address, LabelKind::Start);
+
+    let mut covered = vec![false; rom.len()];
+    let mut pending = vec![load_address];
+    while let Some(mut address) = pending.pop() {
+        while let Some(offset) = disassembly.offset(address) {
+            if disassembly.instructions.contains_key(&address) {
+                break;
+            }
+            let decoded = match decode(rom, offset, variant) {
+                Some(decoded) => decoded,
+                None => break,
+            };
+            // Never let an instruction overlap another.
+            let end = offset + decoded.size as usize;
+            if covered[offset..end].iter().any(|&byte| byte) {
+                break;
+            }
+            for byte in &mut covered[offset..end] {
+                *byte = true;
+            }
+            disassembly.instructions.insert(address, decoded);
+
+            let next = address + decoded.size;
+            match decoded.opcode {
+                Opcode::Jump { nnn } | Opcode::JumpWithOffset { nnn } => {
+                    disassembly.add_label(nnn as u32, LabelKind::Jump);
+                    pending.push(nnn as u32);
+                    break;
+                }
+                Opcode::Call { nnn } => {
+                    disassembly.add_label(nnn as u32, LabelKind::Subroutine);
+                    pending.push(nnn as u32);
+                }
+                Opcode::Return | Opcode::Exit => break,
+                Opcode::LoadI { nnn } => disassembly.add_label(nnn as u32, LabelKind::Sprite),
+                Opcode::LoadLongI | Opcode::LoadWideI { .. } =>
+                    disassembly.add_label(decoded.long_address, LabelKind::Sprite),
+                _ if decoded.opcode.is_skip() => {
+                    let skipped = match disassembly.offset(next).and_then(|offset| decode(rom, offset, variant)) {
+                        Some(Decoded { opcode: Opcode::LoadLongI, size, .. }) if variant == Variant::XoChip => size,
+                        _ => 2,
+                    };
+                    pending.push(next + skipped);
+                }
+                _ => {}
+            }
+            address = next;
+        }
+    }
+    disassembly
+}
+
+impl Disassembly {
+    fn add_label(&mut self, address: u32, kind: LabelKind) {
+        let label = self.labels.entry(address).or_insert(kind);
+        *label = cmp::max(*label, kind);
+    }
+
+    fn offset(&self, address: u32) -> Option<usize> {
+        let offset = address.checked_sub(self.load_address)? as usize;
+        if offset < self.rom.len() { Some(offset) } else { None }
+    }
+
+    pub fn load_address(&self) -> u32 {
+        self.load_address
+    }
+
+    // One past the last byte of the ROM.
+    pub fn end_address(&self) -> u32 {
+        self.load_address + self.rom.len() as u32
+    }
+
+    // The instruction set the ROM was decoded with.
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    // The code found, by address.
+    pub fn instructions(&self) -> &BTreeMap<u32, Decoded> {
+        &self.instructions
+    }
+
+    pub fn instruction(&self, address: u32) -> Option<Decoded> {
+        self.instructions.get(&address).cloned()
+    }
+
+    // Whether the byte at `address` is part of an instruction.
+    pub fn is_code(&self, address: u32) -> bool {
+        self.instructions.range(..=address).next_back()
+            .is_some_and(|(start, decoded)| address < start + decoded.size)
+    }
+
+    // Every address something refers to, including those outside the ROM.
+    pub fn labels(&self) -> &BTreeMap<u32, LabelKind> {
+        &self.labels
+    }
+
+    // The name of the label at `address`. Only addresses the listing can
+    // put a label on have one: in the ROM or just past it, and not inside
+    // an instruction.
+    pub fn label(&self, address: u32) -> Option<String> {
+        let kind = *self.labels.get(&address)?;
+        let placeable = address == self.end_address() ||
+            (self.offset(address).is_some() && (self.instructions.contains_key(&address) || !self.is_code(address)));
+        if !placeable {
+            return None;
+        }
+        Some(match kind {
+            LabelKind::Start => "start".to_string(),
+            LabelKind::Subroutine => format!("sub_{:03X}", address),
+            LabelKind::Jump => format!("label_{:03X}", address),
+            LabelKind::Sprite => format!("sprite_{:03X}", address),
+        })
+    }
+
+    // The assembly for `decoded`, naming addresses by their labels.
+    pub fn render(&self, decoded: &Decoded) -> String {
+        render(decoded, &|address| self.label(address).unwrap_or_else(|| hex_address(address)))
+    }
+
+    // The whole ROM as assembly which assembles back into it. Every line
+    // ends in a comment with its address and bytes, and sprite rows are
+    // drawn next to theirs.
+    pub fn listing(&self) -> String {
+        let mut listing = format!("    ORG {}\n", hex_address(self.load_address));
+        let mut in_sprite = false;
+        let mut address = self.load_address;
+        while address < self.end_address() {
+            if let Some(name) = self.label(address) {
+                listing.push_str(&format!("{}:\n", name));
+                in_sprite = self.labels[&address] == LabelKind::Sprite;
+            }
+
+            let offset = (address - self.load_address) as usize;
+            if let Some(decoded) = self.instruction(address) {
+                let bytes = &self.rom[offset..offset + decoded.size as usize];
+                listing.push_str(&line(&self.render(&decoded), address, bytes, ""));
+                address += decoded.size;
+                in_sprite = false;
+                continue;
+            }
+
+            let limit = if in_sprite { 1 } else { DATA_PER_LINE };
+            let mut end = address + 1;
+            while end < self.end_address() && ((end - address) as usize) < limit &&
+                !self.instructions.contains_key(&end) && self.label(end).is_none() {
+                end += 1;
+            }
+            let bytes = &self.rom[offset..offset + (end - address) as usize];
+            let values: Vec<String> = bytes.iter().map(|byte| format!("#{:02X}", byte)).collect();
+            let pixels = if in_sprite { pixels(bytes[0]) } else { String::new() };
+            listing.push_str(&line(&format!("DB {}", values.join(", ")), address, bytes, &pixels));
+            address = end;
+        }
+        if let Some(name) = self.label(address) {
+            listing.push_str(&format!("{}:\n", name));
+        }
+        listing
+    }
+}
+
+fn line(text: &str, address: u32, bytes: &[u8], note: &str) -> String {
+    let bytes: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    let comment = format!("; {:03X}  {}", address, bytes.join(" "));
+    let comment = if note.is_empty() { comment } else { format!("{}  {}", comment, note) };
+    format!("    {:<width$} {}\n", text, comment, width = COMMENT_COLUMN - 5)
+}
+
+// A sprite row, lit pixels as `#`.
+fn pixels(row: u8) -> String {
+    (0..8).map(|bit| if row & (0x80 >> bit) != 0 { '#' } else { '.' }).collect()
+}
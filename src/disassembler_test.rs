@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod disassembler_test {
+
+    use disassembler::{ decode, disassemble, mnemonic, LabelKind };
+    use platform::Variant;
+
+    fn rom(operations: &[u16]) -> Vec<u8> {
+        operations.iter()
+            .flat_map(|operation| vec![(operation >> 8) as u8, (operation & 0x00FF) as u8])
+            .collect()
+    }
+
+    fn text(words: &[u16], variant: Variant) -> String {
+        mnemonic(&decode(&rom(words), 0, variant).unwrap())
+    }
+
+    #[test]
+    fn renders_base_instructions() {
+        assert_eq!(text(&[0x00E0], Variant::Chip8), "CLS");
+        assert_eq!(text(&[0x1234], Variant::Chip8), "JP #234");
+        assert_eq!(text(&[0x3A0F], Variant::Chip8), "SE VA, #0F");
+        assert_eq!(text(&[0x8AB6], Variant::Chip8), "SHR VA, VB");
+        assert_eq!(text(&[0xB300], Variant::Chip8), "JP V0, #300");
+        assert_eq!(text(&[0xD125], Variant::Chip8), "DRW V1, V2, 5");
+        assert_eq!(text(&[0xF355], Variant::Chip8), "LD [I], V3");
+        assert_eq!(text(&[0xF365], Variant::Chip8), "LD V3, [I]");
+    }
+
+    #[test]
+    fn renders_super_chip_and_xo_chip_instructions() {
+        assert_eq!(text(&[0x00C4], Variant::SuperChip), "SCD 4");
+        assert_eq!(text(&[0xF230], Variant::SuperChip), "LD HF, V2");
+        assert_eq!(text(&[0xF585], Variant::SuperChip), "LD V5, R");
+        assert_eq!(text(&[0x5122], Variant::XoChip), "SAVE V1, V2");
+        assert_eq!(text(&[0xF000, 0x1234], Variant::XoChip), "LD I, LONG #1234");
+        assert_eq!(text(&[0xF201], Variant::XoChip), "PLANE 2");
+    }
+
+    #[test]
+    fn words_the_variant_does_not_run_are_not_instructions() {
+        assert_eq!(decode(&rom(&[0x00FF]), 0, Variant::Chip8), None);
+        assert_eq!(decode(&rom(&[0x5122]), 0, Variant::SuperChip), None);
+        assert_eq!(decode(&rom(&[0x0000]), 0, Variant::Chip8), None);
+        assert_eq!(decode(&rom(&[0xF000]), 0, Variant::XoChip), None);
+    }
+
+    #[test]
+    fn follows_jumps_and_calls_past_data() {
+        // 200: JP 206, 202: data, 206: CALL 20C, 208: JP 208, 20A: data, 20C: RET
+        let disassembly = disassemble(&rom(&[0x1206, 0xFFFF, 0xFFFF, 0x220C, 0x1208, 0xFFFF, 0x00EE]), 0x200, Variant::Chip8);
+
+        assert!(disassembly.is_code(0x200));
+        assert!(!disassembly.is_code(0x202));
+        assert!(disassembly.is_code(0x207));
+        assert!(!disassembly.is_code(0x20A));
+        assert!(disassembly.is_code(0x20C));
+        assert_eq!(disassembly.labels()[&0x206], LabelKind::Jump);
+        assert_eq!(disassembly.labels()[&0x20C], LabelKind::Subroutine);
+        assert_eq!(disassembly.label(0x200), Some("start".to_string()));
+    }
+
+    #[test]
+    fn skips_may_land_on_either_instruction() {
+        // 200: SE V0, 0, 202: JP 202, 204: CLS
+        let disassembly = disassemble(&rom(&[0x3000, 0x1202, 0x00E0]), 0x200, Variant::Chip8);
+
+        assert!(disassembly.is_code(0x204));
+    }
+
+    #[test]
+    fn xo_chip_skips_step_over_long_loads() {
+        // 200: SE V0, 0, 202: LD I, LONG 0, 206: JP 206
+        let disassembly = disassemble(&rom(&[0x3000, 0xF000, 0x0000, 0x1206]), 0x200, Variant::XoChip);
+
+        assert_eq!(disassembly.instructions().len(), 3);
+        assert!(disassembly.is_code(0x204));
+    }
+
+    #[test]
+    fn lists_code_labels_and_sprites() {
+        let program = rom(&[0xA206, 0xD015, 0x1202, 0x3C42, 0xFF00]);
+        let listing = disassemble(&program, 0x200, Variant::Chip8).listing();
+
+        assert_eq!(listing, "    ORG #200\n\
+                             start:\n\
+                            \x20   LD I, sprite_206        ; 200  A2 06\n\
+                             label_202:\n\
+                            \x20   DRW V0, V1, 5           ; 202  D0 15\n\
+                            \x20   JP label_202            ; 204  12 02\n\
+                             sprite_206:\n\
+                            \x20   DB #3C                  ; 206  3C  ..####..\n\
+                            \x20   DB #42                  ; 207  42  .#....#.\n\
+                            \x20   DB #FF                  ; 208  FF  ########\n\
+                            \x20   DB #00                  ; 209  00  ........\n");
+    }
+
+    #[test]
+    fn addresses_inside_instructions_stay_numbers() {
+        // 200: JP 201
+        let disassembly = disassemble(&rom(&[0x1201]), 0x200, Variant::Chip8);
+
+        assert_eq!(disassembly.label(0x201), None);
+        assert!(disassembly.listing().contains("JP #201"));
+    }
+}
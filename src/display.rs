@@ -0,0 +1,247 @@
+pub const WIDTH: usize = 64;
+pub const HEIGHT: usize = 32;
+
+// SUPER-CHIP high resolution mode.
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+
+// Hi-Res CHIP-8 shows two 64x32 pages, one above the other.
+pub const TWO_PAGE_HEIGHT: usize = 64;
+
+// XO-CHIP draws on two bitplanes, giving four colours.
+pub const PLANE_COUNT: usize = 2;
+
+// Monochrome framebuffer, stored row by row. A pixel is either on or off.
+// It starts in the 64x32 low resolution mode.
+//
+// There are really two such framebuffers, the bitplanes. Drawing, clearing
+// and scrolling only touch the selected planes. Only the first plane is
+// selected until an XO-CHIP program picks others, so other programs never
+// see the second one.
+#[derive(Clone)]
+pub struct Display {
+    planes: [Vec<bool>; PLANE_COUNT],
+    selected: u8,
+    width: usize,
+    height: usize,
+}
+
+impl Default for Display {
+    fn default() -> Display {
+        Display::new()
+    }
+}
+
+impl Display {
+    pub fn new() -> Display {
+        Display {
+            planes: [vec![false; WIDTH * HEIGHT], vec![false; WIDTH * HEIGHT]],
+            selected: 0x1,
+            width: WIDTH,
+            height: HEIGHT,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Pixel of the first plane.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.planes[0][y * self.width + x]
+    }
+
+    // All pixels of the first plane, row by row, so frontends can blit
+    // the whole screen at once.
+    pub fn pixels(&self) -> &[bool] {
+        &self.planes[0]
+    }
+
+    pub fn plane(&self, plane: usize) -> &[bool] {
+        &self.planes[plane]
+    }
+
+    // Colour index 0 to 3 of a pixel: bit 0 from the first plane, bit 1
+    // from the second.
+    pub fn colour(&self, x: usize, y: usize) -> u8 {
+        let index = y * self.width + x;
+        self.planes[0][index] as u8 | (self.planes[1][index] as u8) << 1
+    }
+
+    // Colour indexes of every pixel, row by row.
+    pub fn colours(&self) -> Vec<u8> {
+        self.planes[0].iter()
+            .zip(self.planes[1].iter())
+            .map(|(&first, &second)| first as u8 | (second as u8) << 1)
+            .collect()
+    }
+
+    // Bit mask of the planes drawn on: 0x1 first, 0x2 second, 0x3 both.
+    pub fn selected_planes(&self) -> u8 {
+        self.selected
+    }
+
+    pub fn select_planes(&mut self, mask: u8) {
+        self.selected = mask & 0x3;
+    }
+
+    pub fn clear(&mut self) {
+        for plane in self.selected_indexes() {
+            for pixel in self.planes[plane].iter_mut() {
+                *pixel = false;
+            }
+        }
+    }
+
+    pub fn is_high_resolution(&self) -> bool {
+        self.width == HIRES_WIDTH
+    }
+
+    // Switches between 64x32 and 128x64. Every plane is cleared.
+    pub fn set_high_resolution(&mut self, high: bool) {
+        if high {
+            self.set_resolution(HIRES_WIDTH, HIRES_HEIGHT);
+        } else {
+            self.set_resolution(WIDTH, HEIGHT);
+        }
+    }
+
+    // Any other screen size. Every plane is cleared.
+    pub fn set_resolution(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        for plane in self.planes.iter_mut() {
+            *plane = vec![false; width * height];
+        }
+    }
+
+    fn selected_indexes(&self) -> Vec<usize> {
+        (0..PLANE_COUNT).filter(|plane| self.selected & (1 << plane) != 0).collect()
+    }
+
+    // Moves the picture down by `rows`. Rows scrolled in are blank.
+    pub fn scroll_down(&mut self, rows: usize) {
+        let shift = rows.min(self.height) * self.width;
+        for plane in self.selected_indexes() {
+            let pixels = &mut self.planes[plane];
+            let length = pixels.len();
+            pixels.copy_within(0..length - shift, shift);
+            for pixel in pixels[..shift].iter_mut() {
+                *pixel = false;
+            }
+        }
+    }
+
+    // Moves the picture up by `rows`. Rows scrolled in are blank.
+    pub fn scroll_up(&mut self, rows: usize) {
+        let shift = rows.min(self.height) * self.width;
+        for plane in self.selected_indexes() {
+            let pixels = &mut self.planes[plane];
+            let length = pixels.len();
+            pixels.copy_within(shift.., 0);
+            for pixel in pixels[length - shift..].iter_mut() {
+                *pixel = false;
+            }
+        }
+    }
+
+    // Moves the picture right by `columns`. Columns scrolled in are blank.
+    pub fn scroll_right(&mut self, columns: usize) {
+        let shift = columns.min(self.width);
+        for plane in self.selected_indexes() {
+            for row in self.planes[plane].chunks_mut(self.width) {
+                let width = row.len();
+                row.copy_within(0..width - shift, shift);
+                for pixel in row[..shift].iter_mut() {
+                    *pixel = false;
+                }
+            }
+        }
+    }
+
+    // Moves the picture left by `columns`. Columns scrolled in are blank.
+    pub fn scroll_left(&mut self, columns: usize) {
+        let shift = columns.min(self.width);
+        for plane in self.selected_indexes() {
+            for row in self.planes[plane].chunks_mut(self.width) {
+                let width = row.len();
+                row.copy_within(shift.., 0);
+                for pixel in row[width - shift..].iter_mut() {
+                    *pixel = false;
+                }
+            }
+        }
+    }
+
+    // XORs an 8 pixel wide sprite onto the screen. Each byte is one row.
+    // The starting position wraps around the screen, but pixels that fall
+    // off the right or bottom edge are clipped, like on the COSMAC VIP.
+    // With both planes selected, `sprite` holds the first plane's rows
+    // followed by the second's.
+    // Returns true when any lit pixel was turned off.
+    pub fn draw_sprite(&mut self, x: u8, y: u8, sprite: &[u8]) -> bool {
+        self.draw(x, y, sprite, 1, true)
+    }
+
+    // Same as `draw_sprite`, but pixels falling off an edge reappear on
+    // the opposite side.
+    pub fn draw_sprite_wrapped(&mut self, x: u8, y: u8, sprite: &[u8]) -> bool {
+        self.draw(x, y, sprite, 1, false)
+    }
+
+    // SUPER-CHIP 16x16 sprite: 16 rows of two bytes each.
+    pub fn draw_large_sprite(&mut self, x: u8, y: u8, sprite: &[u8]) -> bool {
+        self.draw(x, y, sprite, 2, true)
+    }
+
+    pub fn draw_large_sprite_wrapped(&mut self, x: u8, y: u8, sprite: &[u8]) -> bool {
+        self.draw(x, y, sprite, 2, false)
+    }
+
+    fn draw(&mut self, x: u8, y: u8, sprite: &[u8], bytes_per_row: usize, clip: bool) -> bool {
+        let planes = self.selected_indexes();
+        if planes.is_empty() {
+            return false;
+        }
+        let plane_length = sprite.len() / planes.len();
+        let mut collision = false;
+
+        for (plane, data) in planes.into_iter().zip(sprite.chunks(plane_length.max(1))) {
+            collision |= self.draw_on_plane(plane, x, y, data, bytes_per_row, clip);
+        }
+
+        collision
+    }
+
+    fn draw_on_plane(&mut self, plane: usize, x: u8, y: u8, sprite: &[u8], bytes_per_row: usize, clip: bool) -> bool {
+        let origin_x = x as usize % self.width;
+        let origin_y = y as usize % self.height;
+        let pixels = &mut self.planes[plane];
+        let mut collision = false;
+
+        for (row, bytes) in sprite.chunks(bytes_per_row).enumerate() {
+            let py = origin_y + row;
+            if clip && py >= self.height {
+                break;
+            }
+            for column in 0..bytes_per_row * 8 {
+                let px = origin_x + column;
+                if clip && px >= self.width {
+                    break;
+                }
+                let bits = bytes.get(column / 8).cloned().unwrap_or(0);
+                if bits & (0x80 >> (column % 8)) != 0 {
+                    let index = (py % self.height) * self.width + px % self.width;
+                    collision |= pixels[index];
+                    pixels[index] ^= true;
+                }
+            }
+        }
+
+        collision
+    }
+}
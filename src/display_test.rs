@@ -0,0 +1,155 @@
+#[cfg(test)]
+mod display_test {
+
+    use display::{ Display, WIDTH, HEIGHT, HIRES_WIDTH, HIRES_HEIGHT };
+
+    #[test]
+    fn starts_with_all_pixels_off() {
+        let display = Display::new();
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                assert!(!display.pixel(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn draws_sprite_rows_from_most_significant_bit() {
+        let mut display = Display::new();
+        let collision = display.draw_sprite(0, 0, &[0x80, 0x01]);
+
+        assert!(!collision);
+        assert!(display.pixel(0, 0));
+        assert!(!display.pixel(1, 0));
+        assert!(display.pixel(7, 1));
+        assert!(!display.pixel(0, 1));
+    }
+
+    #[test]
+    fn drawing_twice_erases_and_reports_collision() {
+        let mut display = Display::new();
+        display.draw_sprite(10, 5, &[0xFF]);
+        let collision = display.draw_sprite(10, 5, &[0xFF]);
+
+        assert!(collision);
+        assert!(display.pixels().iter().all(|pixel| !pixel));
+    }
+
+    #[test]
+    fn starting_position_wraps_around_the_screen() {
+        let mut display = Display::new();
+        display.draw_sprite(WIDTH as u8 + 1, HEIGHT as u8 + 2, &[0x80]);
+
+        assert!(display.pixel(1, 2));
+    }
+
+    #[test]
+    fn pixels_past_the_edge_are_clipped() {
+        let mut display = Display::new();
+        display.draw_sprite(62, 31, &[0xFF, 0xFF]);
+
+        assert!(display.pixel(62, 31));
+        assert!(display.pixel(63, 31));
+        assert!(!display.pixel(0, 31));
+        assert!(!display.pixel(62, 0));
+    }
+
+    #[test]
+    fn clear_turns_every_pixel_off() {
+        let mut display = Display::new();
+        display.draw_sprite(0, 0, &[0xFF, 0xFF, 0xFF]);
+        display.clear();
+
+        assert!(display.pixels().iter().all(|pixel| !pixel));
+    }
+
+    #[test]
+    fn high_resolution_doubles_the_screen_and_clears_it() {
+        let mut display = Display::new();
+        display.draw_sprite(0, 0, &[0x80]);
+        display.set_high_resolution(true);
+
+        assert!(display.is_high_resolution());
+        assert_eq!(display.width(), HIRES_WIDTH);
+        assert_eq!(display.height(), HIRES_HEIGHT);
+        assert!(display.pixels().iter().all(|pixel| !pixel));
+
+        display.draw_sprite(127, 63, &[0x80]);
+        assert!(display.pixel(127, 63));
+    }
+
+    #[test]
+    fn scrolling_moves_pixels_and_blanks_the_gap() {
+        let mut display = Display::new();
+        display.draw_sprite(0, 0, &[0x80]);
+
+        display.scroll_down(3);
+        assert!(display.pixel(0, 3));
+        assert!(!display.pixel(0, 0));
+
+        display.scroll_right(4);
+        assert!(display.pixel(4, 3));
+        assert!(!display.pixel(0, 3));
+
+        display.scroll_left(4);
+        assert!(display.pixel(0, 3));
+        assert!(!display.pixel(4, 3));
+
+        display.scroll_left(1);
+        assert!(display.pixels().iter().all(|pixel| !pixel));
+    }
+
+    #[test]
+    fn large_sprites_are_sixteen_pixels_wide() {
+        let mut display = Display::new();
+        let sprite = [0x80, 0x01, 0x00, 0x00];
+        display.draw_large_sprite(0, 0, &sprite);
+
+        assert!(display.pixel(0, 0));
+        assert!(display.pixel(15, 0));
+        assert!(!display.pixel(8, 0));
+        assert!(display.draw_large_sprite(0, 0, &sprite));
+    }
+
+    #[test]
+    fn drawing_on_both_planes_gives_four_colours() {
+        let mut display = Display::new();
+        display.select_planes(0x3);
+        display.draw_sprite(0, 0, &[0xC0, 0xA0]);
+
+        assert_eq!(display.colour(0, 0), 3);
+        assert_eq!(display.colour(1, 0), 1);
+        assert_eq!(display.colour(2, 0), 2);
+        assert_eq!(display.colour(3, 0), 0);
+        assert_eq!(display.colours()[..4], [3, 1, 2, 0]);
+    }
+
+    #[test]
+    fn clear_and_scroll_only_touch_selected_planes() {
+        let mut display = Display::new();
+        display.select_planes(0x3);
+        display.draw_sprite(0, 0, &[0x80, 0x80]);
+
+        display.select_planes(0x2);
+        display.scroll_up(0);
+        display.scroll_down(1);
+        assert!(display.pixel(0, 0));
+        assert!(display.plane(1)[WIDTH]);
+
+        display.scroll_up(1);
+        assert!(display.plane(1)[0]);
+
+        display.clear();
+        assert!(display.pixel(0, 0));
+        assert!(!display.plane(1)[0]);
+    }
+
+    #[test]
+    fn nothing_is_drawn_without_a_selected_plane() {
+        let mut display = Display::new();
+        display.select_planes(0x0);
+
+        assert!(!display.draw_sprite(0, 0, &[0xFF]));
+        assert!(display.colours().iter().all(|&colour| colour == 0));
+    }
+}
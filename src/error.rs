@@ -0,0 +1,32 @@
+use std::error;
+use std::fmt;
+
+// Why the CPU could not execute an instruction. Addresses are those of the
+// offending instruction, so a frontend can point at it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmulatorError {
+    UnknownOpcode { opcode: u16, address: u16 },
+    UnsupportedOpcode { opcode: u16, address: u16 },
+    MemoryOutOfBounds { address: usize },
+    StackOverflow { address: u16 },
+    StackUnderflow { address: u16 },
+}
+
+impl fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EmulatorError::UnknownOpcode { opcode, address } =>
+                write!(f, "unknown opcode {:04X} at {:03X}", opcode, address),
+            EmulatorError::UnsupportedOpcode { opcode, address } =>
+                write!(f, "unsupported opcode {:04X} at {:03X}", opcode, address),
+            EmulatorError::MemoryOutOfBounds { address } =>
+                write!(f, "memory access out of bounds at {:03X}", address),
+            EmulatorError::StackOverflow { address } =>
+                write!(f, "stack overflow at {:03X}", address),
+            EmulatorError::StackUnderflow { address } =>
+                write!(f, "stack underflow at {:03X}", address),
+        }
+    }
+}
+
+impl error::Error for EmulatorError {}
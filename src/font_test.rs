@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod font_test {
+
+    use font::{ Font, SMALL_FONT_SIZE, BIG_DIGITS_SIZE, BIG_HEX_SIZE };
+
+    const FONTS: [Font; 5] = [Font::Vip, Font::Dream6800, Font::Eti660, Font::SuperChip, Font::Octo];
+
+    #[test]
+    fn every_font_has_sixteen_small_glyphs() {
+        for font in FONTS.iter() {
+            assert_eq!(font.small().len(), SMALL_FONT_SIZE, "{:?}", font);
+        }
+    }
+
+    #[test]
+    fn big_glyphs_come_only_with_super_chip_and_octo() {
+        assert_eq!(Font::SuperChip.big().len(), BIG_DIGITS_SIZE);
+        assert_eq!(Font::Octo.big().len(), BIG_HEX_SIZE);
+        assert!(Font::Vip.big().is_empty());
+        assert_eq!(Font::Octo.glyphs().len(), SMALL_FONT_SIZE + BIG_HEX_SIZE);
+    }
+
+    #[test]
+    fn fonts_differ_between_interpreters() {
+        // The 1 is a different shape on each of these.
+        let ones: Vec<&[u8]> = [Font::Vip, Font::Dream6800, Font::Eti660, Font::SuperChip].iter()
+            .map(|font| &font.small()[5..10])
+            .collect();
+        for (index, one) in ones.iter().enumerate() {
+            assert!(ones[index + 1..].iter().all(|other| other != one));
+        }
+    }
+
+    #[test]
+    fn font_binaries_must_be_small_glyphs_plus_optional_big_ones() {
+        assert!(Font::is_valid_binary(&[0; 80]));
+        assert!(Font::is_valid_binary(&[0; 180]));
+        assert!(Font::is_valid_binary(&[0; 240]));
+        assert!(!Font::is_valid_binary(&[0; 79]));
+        assert!(!Font::is_valid_binary(&[0; 100]));
+        assert!(!Font::is_valid_binary(&[]));
+    }
+}
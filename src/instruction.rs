@@ -1,4 +1,7 @@
 
+use platform::Variant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Instruction {
     raw: u16
 }
@@ -11,7 +14,7 @@ impl Instruction {
     }
 
     pub fn raw(self) -> u16{
-        self.raw as u16
+        self.raw
     }
 
     pub fn op(&self) -> u8{
@@ -27,7 +30,7 @@ impl Instruction {
     }
 
     pub fn nnn(&self) -> u16{
-        (self.raw & 0x0FFF) as u16
+        self.raw & 0x0FFF
     }
 
     pub fn nn(&self) -> u8{
@@ -35,6 +38,296 @@ impl Instruction {
     }
 
     pub fn n(&self) -> u16{
-        (self.raw & 0x000F) as u16
+        self.raw & 0x000F
+    }
+}
+impl Instruction {
+    // Decodes the raw word into the instruction it stands for, or None
+    // when it is not a CHIP-8 instruction.
+    pub fn decode(&self) -> Option<Opcode> {
+        let x = self.x();
+        let y = self.y();
+        let n = self.n() as u8;
+        let nn = self.nn();
+        let nnn = self.nnn();
+
+        let opcode = match (self.op(), x, y, n) {
+            (0x0, 0x0, 0xE, 0x0) => Opcode::ClearScreen,
+            (0x0, 0x0, 0xE, 0xE) => Opcode::Return,
+            (0x0, 0x0, 0xC, _) => Opcode::ScrollDown { n },
+            (0x0, 0x0, 0xD, _) => Opcode::ScrollUp { n },
+            (0x0, 0x0, 0xF, 0xB) => Opcode::ScrollRight,
+            (0x0, 0x0, 0xF, 0xC) => Opcode::ScrollLeft,
+            (0x0, 0x0, 0xF, 0xD) => Opcode::Exit,
+            (0x0, 0x0, 0xF, 0xE) => Opcode::LowResolution,
+            (0x0, 0x0, 0xF, 0xF) => Opcode::HighResolution,
+            (0x0, 0x2, 0xA, 0x0) => Opcode::CycleBackground,
+            (0x0, _, _, _) => Opcode::Sys { nnn },
+            (0x1, _, _, _) => Opcode::Jump { nnn },
+            (0x2, _, _, _) => Opcode::Call { nnn },
+            (0x3, _, _, _) => Opcode::SkipIfEqual { x, nn },
+            (0x4, _, _, _) => Opcode::SkipIfNotEqual { x, nn },
+            (0x5, _, _, 0x0) => Opcode::SkipIfRegistersEqual { x, y },
+            (0x5, _, _, 0x1) => Opcode::AddNibbles { x, y },
+            (0x5, _, _, 0x2) => Opcode::SaveRange { x, y },
+            (0x5, _, _, 0x3) => Opcode::LoadRange { x, y },
+            (0x6, _, _, _) => Opcode::Load { x, nn },
+            (0x7, _, _, _) => Opcode::Add { x, nn },
+            (0x8, _, _, 0x0) => Opcode::Move { x, y },
+            (0x8, _, _, 0x1) => Opcode::Or { x, y },
+            (0x8, _, _, 0x2) => Opcode::And { x, y },
+            (0x8, _, _, 0x3) => Opcode::Xor { x, y },
+            (0x8, _, _, 0x4) => Opcode::AddRegisters { x, y },
+            (0x8, _, _, 0x5) => Opcode::Subtract { x, y },
+            (0x8, _, _, 0x6) => Opcode::ShiftRight { x, y },
+            (0x8, _, _, 0x7) => Opcode::SubtractReversed { x, y },
+            (0x8, _, _, 0xE) => Opcode::ShiftLeft { x, y },
+            (0x9, _, _, 0x0) => Opcode::SkipIfRegistersNotEqual { x, y },
+            (0xA, _, _, _) => Opcode::LoadI { nnn },
+            (0xB, _, _, _) => Opcode::JumpWithOffset { nnn },
+            (0xC, _, _, _) => Opcode::Random { x, nn },
+            (0xD, _, _, _) => Opcode::Draw { x, y, n },
+            (0xE, _, 0x9, 0xE) => Opcode::SkipIfKeyPressed { x },
+            (0xE, _, 0xA, 0x1) => Opcode::SkipIfKeyNotPressed { x },
+            (0xE, _, 0xF, 0x2) => Opcode::SkipIfSecondKeyPressed { x },
+            (0xE, _, 0xF, 0x5) => Opcode::SkipIfSecondKeyNotPressed { x },
+            (0xF, 0x0, 0x0, 0x0) => Opcode::LoadLongI,
+            (0xF, _, 0x0, 0x1) => Opcode::SelectPlanes { n: x },
+            (0xF, 0x0, 0x0, 0x2) => Opcode::LoadAudio,
+            (0xF, _, 0x0, 0x7) => Opcode::ReadDelayTimer { x },
+            (0xF, _, 0x0, 0xA) => Opcode::WaitForKey { x },
+            (0xF, _, 0x1, 0x5) => Opcode::SetDelayTimer { x },
+            (0xF, _, 0x1, 0x8) => Opcode::SetSoundTimer { x },
+            (0xF, _, 0x1, 0xE) => Opcode::AddToI { x },
+            (0xF, _, 0x2, 0x9) => Opcode::LoadFont { x },
+            (0xF, _, 0x3, 0x0) => Opcode::LoadBigFont { x },
+            (0xF, _, 0x3, 0x3) => Opcode::StoreBcd { x },
+            (0xF, _, 0x3, 0xA) => Opcode::SetPitch { x },
+            (0xF, _, 0x5, 0x5) => Opcode::StoreRegisters { x },
+            (0xF, _, 0x6, 0x5) => Opcode::LoadRegisters { x },
+            (0xF, _, 0x7, 0x5) => Opcode::SaveFlags { x },
+            (0xF, _, 0x8, 0x5) => Opcode::LoadFlags { x },
+            (0xF, _, 0xF, 0x8) => Opcode::OutputPort { x },
+            (0xF, _, 0xF, 0xB) => Opcode::InputPort { x },
+            _ => return None,
+        };
+
+        Some(opcode)
     }
-}
\ No newline at end of file
+
+    // Like `decode`, for the instruction set of `variant`. CHIP-8X and
+    // MegaChip8 give some words a different meaning: on CHIP-8X, BXYN
+    // colours the screen instead of jumping, and MegaChip8 takes over part
+    // of the 0NNN machine code calls.
+    pub fn decode_for(&self, variant: Variant) -> Option<Opcode> {
+        let nn = self.nn();
+        let n = self.n() as u8;
+        match (variant, self.op(), self.x(), self.y()) {
+            (Variant::Chip8X, 0xB, x, y) if n == 0 => Some(Opcode::ColourZones { x, y }),
+            (Variant::Chip8X, 0xB, x, y) => Some(Opcode::ColourRows { x, y, n }),
+            (Variant::MegaChip, 0x0, 0x0, 0x1) if n == 0x0 => Some(Opcode::MegaOff),
+            (Variant::MegaChip, 0x0, 0x0, 0x1) if n == 0x1 => Some(Opcode::MegaOn),
+            (Variant::MegaChip, 0x0, 0x1, _) => Some(Opcode::LoadWideI { nn }),
+            (Variant::MegaChip, 0x0, 0x2, _) => Some(Opcode::LoadPalette { nn }),
+            (Variant::MegaChip, 0x0, 0x3, _) => Some(Opcode::SpriteWidth { nn }),
+            (Variant::MegaChip, 0x0, 0x4, _) => Some(Opcode::SpriteHeight { nn }),
+            (Variant::MegaChip, 0x0, 0x5, _) => Some(Opcode::ScreenAlpha { nn }),
+            (Variant::MegaChip, 0x0, 0x6, 0x0) => Some(Opcode::PlaySample { n }),
+            (Variant::MegaChip, 0x0, 0x7, 0x0) if n == 0x0 => Some(Opcode::StopSample),
+            (Variant::MegaChip, 0x0, 0x8, 0x0) => Some(Opcode::SetBlendMode { n }),
+            (Variant::MegaChip, 0x0, 0x9, _) => Some(Opcode::CollisionColour { nn }),
+            _ => self.decode(),
+        }
+    }
+}
+
+impl From<Opcode> for Instruction {
+    fn from(opcode: Opcode) -> Instruction {
+        Instruction::new(opcode.encode())
+    }
+}
+
+// Every CHIP-8, SUPER-CHIP, XO-CHIP, CHIP-8X and MegaChip8 instruction with
+// its operands. `x` and `y`
+// are register numbers, `nnn` an address, `nn` a byte and `n` a nibble.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Sys { nnn: u16 },                      // 0NNN
+    ClearScreen,                           // 00E0
+    Return,                                // 00EE
+    Jump { nnn: u16 },                     // 1NNN
+    Call { nnn: u16 },                     // 2NNN
+    SkipIfEqual { x: u8, nn: u8 },         // 3XNN
+    SkipIfNotEqual { x: u8, nn: u8 },      // 4XNN
+    SkipIfRegistersEqual { x: u8, y: u8 }, // 5XY0
+    Load { x: u8, nn: u8 },                // 6XNN
+    Add { x: u8, nn: u8 },                 // 7XNN
+    Move { x: u8, y: u8 },                 // 8XY0
+    Or { x: u8, y: u8 },                   // 8XY1
+    And { x: u8, y: u8 },                  // 8XY2
+    Xor { x: u8, y: u8 },                  // 8XY3
+    AddRegisters { x: u8, y: u8 },         // 8XY4
+    Subtract { x: u8, y: u8 },             // 8XY5
+    ShiftRight { x: u8, y: u8 },           // 8XY6
+    SubtractReversed { x: u8, y: u8 },     // 8XY7
+    ShiftLeft { x: u8, y: u8 },            // 8XYE
+    SkipIfRegistersNotEqual { x: u8, y: u8 }, // 9XY0
+    LoadI { nnn: u16 },                    // ANNN
+    JumpWithOffset { nnn: u16 },           // BNNN
+    Random { x: u8, nn: u8 },              // CXNN
+    Draw { x: u8, y: u8, n: u8 },          // DXYN
+    SkipIfKeyPressed { x: u8 },            // EX9E
+    SkipIfKeyNotPressed { x: u8 },         // EXA1
+    ReadDelayTimer { x: u8 },              // FX07
+    WaitForKey { x: u8 },                  // FX0A
+    SetDelayTimer { x: u8 },               // FX15
+    SetSoundTimer { x: u8 },               // FX18
+    AddToI { x: u8 },                      // FX1E
+    LoadFont { x: u8 },                    // FX29
+    StoreBcd { x: u8 },                    // FX33
+    StoreRegisters { x: u8 },              // FX55
+    LoadRegisters { x: u8 },               // FX65
+
+    // SUPER-CHIP
+    ScrollDown { n: u8 },                  // 00CN
+    ScrollRight,                           // 00FB
+    ScrollLeft,                            // 00FC
+    Exit,                                  // 00FD
+    LowResolution,                         // 00FE
+    HighResolution,                        // 00FF
+    LoadBigFont { x: u8 },                 // FX30
+    SaveFlags { x: u8 },                   // FX75
+    LoadFlags { x: u8 },                   // FX85
+
+    // XO-CHIP
+    ScrollUp { n: u8 },                    // 00DN
+    SaveRange { x: u8, y: u8 },            // 5XY2
+    LoadRange { x: u8, y: u8 },            // 5XY3
+    LoadLongI,                             // F000 NNNN, NNNN is the next word
+    SelectPlanes { n: u8 },                // FN01
+    LoadAudio,                             // F002
+    SetPitch { x: u8 },                    // FX3A
+
+    // CHIP-8X
+    CycleBackground,                       // 02A0
+    AddNibbles { x: u8, y: u8 },           // 5XY1
+    ColourZones { x: u8, y: u8 },          // BXY0
+    ColourRows { x: u8, y: u8, n: u8 },    // BXYN
+    SkipIfSecondKeyPressed { x: u8 },      // EXF2
+    SkipIfSecondKeyNotPressed { x: u8 },   // EXF5
+    OutputPort { x: u8 },                  // FXF8
+    InputPort { x: u8 },                   // FXFB
+
+    // MegaChip8
+    MegaOff,                               // 0010
+    MegaOn,                                // 0011
+    LoadWideI { nn: u8 },                  // 01NN NNNN, I = NN NNNN
+    LoadPalette { nn: u8 },                // 02NN
+    SpriteWidth { nn: u8 },                // 03NN
+    SpriteHeight { nn: u8 },               // 04NN
+    ScreenAlpha { nn: u8 },                // 05NN
+    PlaySample { n: u8 },                  // 060N
+    StopSample,                            // 0700
+    SetBlendMode { n: u8 },                // 080N
+    CollisionColour { nn: u8 },            // 09NN
+}
+
+fn xnn(op: u16, x: u8, nn: u8) -> u16 {
+    op << 12 | (x as u16) << 8 | nn as u16
+}
+
+fn xyn(op: u16, x: u8, y: u8, n: u8) -> u16 {
+    op << 12 | (x as u16) << 8 | (y as u16) << 4 | n as u16
+}
+
+impl Opcode {
+    // Encodes the instruction back into its 16 bit form.
+    pub fn encode(&self) -> u16 {
+        match *self {
+            Opcode::Sys { nnn } => nnn,
+            Opcode::ClearScreen => 0x00E0,
+            Opcode::Return => 0x00EE,
+            Opcode::Jump { nnn } => 0x1000 | nnn,
+            Opcode::Call { nnn } => 0x2000 | nnn,
+            Opcode::SkipIfEqual { x, nn } => xnn(0x3, x, nn),
+            Opcode::SkipIfNotEqual { x, nn } => xnn(0x4, x, nn),
+            Opcode::SkipIfRegistersEqual { x, y } => xyn(0x5, x, y, 0x0),
+            Opcode::Load { x, nn } => xnn(0x6, x, nn),
+            Opcode::Add { x, nn } => xnn(0x7, x, nn),
+            Opcode::Move { x, y } => xyn(0x8, x, y, 0x0),
+            Opcode::Or { x, y } => xyn(0x8, x, y, 0x1),
+            Opcode::And { x, y } => xyn(0x8, x, y, 0x2),
+            Opcode::Xor { x, y } => xyn(0x8, x, y, 0x3),
+            Opcode::AddRegisters { x, y } => xyn(0x8, x, y, 0x4),
+            Opcode::Subtract { x, y } => xyn(0x8, x, y, 0x5),
+            Opcode::ShiftRight { x, y } => xyn(0x8, x, y, 0x6),
+            Opcode::SubtractReversed { x, y } => xyn(0x8, x, y, 0x7),
+            Opcode::ShiftLeft { x, y } => xyn(0x8, x, y, 0xE),
+            Opcode::SkipIfRegistersNotEqual { x, y } => xyn(0x9, x, y, 0x0),
+            Opcode::LoadI { nnn } => 0xA000 | nnn,
+            Opcode::JumpWithOffset { nnn } => 0xB000 | nnn,
+            Opcode::Random { x, nn } => xnn(0xC, x, nn),
+            Opcode::Draw { x, y, n } => xyn(0xD, x, y, n),
+            Opcode::SkipIfKeyPressed { x } => xnn(0xE, x, 0x9E),
+            Opcode::SkipIfKeyNotPressed { x } => xnn(0xE, x, 0xA1),
+            Opcode::ReadDelayTimer { x } => xnn(0xF, x, 0x07),
+            Opcode::WaitForKey { x } => xnn(0xF, x, 0x0A),
+            Opcode::SetDelayTimer { x } => xnn(0xF, x, 0x15),
+            Opcode::SetSoundTimer { x } => xnn(0xF, x, 0x18),
+            Opcode::AddToI { x } => xnn(0xF, x, 0x1E),
+            Opcode::LoadFont { x } => xnn(0xF, x, 0x29),
+            Opcode::StoreBcd { x } => xnn(0xF, x, 0x33),
+            Opcode::StoreRegisters { x } => xnn(0xF, x, 0x55),
+            Opcode::LoadRegisters { x } => xnn(0xF, x, 0x65),
+            Opcode::ScrollDown { n } => 0x00C0 | n as u16,
+            Opcode::ScrollRight => 0x00FB,
+            Opcode::ScrollLeft => 0x00FC,
+            Opcode::Exit => 0x00FD,
+            Opcode::LowResolution => 0x00FE,
+            Opcode::HighResolution => 0x00FF,
+            Opcode::LoadBigFont { x } => xnn(0xF, x, 0x30),
+            Opcode::SaveFlags { x } => xnn(0xF, x, 0x75),
+            Opcode::LoadFlags { x } => xnn(0xF, x, 0x85),
+            Opcode::ScrollUp { n } => 0x00D0 | n as u16,
+            Opcode::SaveRange { x, y } => xyn(0x5, x, y, 0x2),
+            Opcode::LoadRange { x, y } => xyn(0x5, x, y, 0x3),
+            Opcode::LoadLongI => 0xF000,
+            Opcode::SelectPlanes { n } => xnn(0xF, n, 0x01),
+            Opcode::LoadAudio => 0xF002,
+            Opcode::SetPitch { x } => xnn(0xF, x, 0x3A),
+            Opcode::CycleBackground => 0x02A0,
+            Opcode::AddNibbles { x, y } => xyn(0x5, x, y, 0x1),
+            Opcode::ColourZones { x, y } => xyn(0xB, x, y, 0x0),
+            Opcode::ColourRows { x, y, n } => xyn(0xB, x, y, n),
+            Opcode::SkipIfSecondKeyPressed { x } => xnn(0xE, x, 0xF2),
+            Opcode::SkipIfSecondKeyNotPressed { x } => xnn(0xE, x, 0xF5),
+            Opcode::OutputPort { x } => xnn(0xF, x, 0xF8),
+            Opcode::InputPort { x } => xnn(0xF, x, 0xFB),
+            Opcode::MegaOff => 0x0010,
+            Opcode::MegaOn => 0x0011,
+            Opcode::LoadWideI { nn } => xnn(0x0, 0x1, nn),
+            Opcode::LoadPalette { nn } => xnn(0x0, 0x2, nn),
+            Opcode::SpriteWidth { nn } => xnn(0x0, 0x3, nn),
+            Opcode::SpriteHeight { nn } => xnn(0x0, 0x4, nn),
+            Opcode::ScreenAlpha { nn } => xnn(0x0, 0x5, nn),
+            Opcode::PlaySample { n } => 0x0600 | n as u16,
+            Opcode::StopSample => 0x0700,
+            Opcode::SetBlendMode { n } => 0x0800 | n as u16,
+            Opcode::CollisionColour { nn } => xnn(0x0, 0x9, nn),
+        }
+    }
+
+    // Conditional skips, which step over the whole of a following F000
+    // NNNN on XO-CHIP.
+    pub fn is_skip(&self) -> bool {
+        matches!(*self,
+            Opcode::SkipIfEqual { .. } |
+            Opcode::SkipIfNotEqual { .. } |
+            Opcode::SkipIfRegistersEqual { .. } |
+            Opcode::SkipIfRegistersNotEqual { .. } |
+            Opcode::SkipIfKeyPressed { .. } |
+            Opcode::SkipIfKeyNotPressed { .. } |
+            Opcode::SkipIfSecondKeyPressed { .. } |
+            Opcode::SkipIfSecondKeyNotPressed { .. })
+    }
+}
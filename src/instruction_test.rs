@@ -1,7 +1,8 @@
 #[cfg(test)]
 mod instruction_test {
 
-    use instruction::Instruction;
+    use instruction::{ Instruction, Opcode };
+    use platform::Variant;
 
     #[test]
     fn op_returns_first_4_bits() {
@@ -62,4 +63,131 @@ mod instruction_test {
             assert_eq!(instruction.n(), bytes);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn every_decoded_instruction_encodes_back_to_the_same_word() {
+        for value in 0x0000u32..=0xFFFFu32 {
+            let raw = value as u16;
+            if let Some(opcode) = Instruction::new(raw).decode() {
+                assert_eq!(opcode.encode(), raw, "{:?}", opcode);
+                assert_eq!(Instruction::from(opcode), Instruction::new(raw));
+            }
+        }
+    }
+
+    #[test]
+    fn chip8x_decodes_bxyn_as_colour_instructions() {
+        for value in 0xB000u32..=0xBFFFu32 {
+            let raw = value as u16;
+            let opcode = Instruction::new(raw).decode_for(Variant::Chip8X).unwrap();
+            assert_eq!(opcode.encode(), raw, "{:?}", opcode);
+        }
+        assert_eq!(Instruction::new(0xB120).decode_for(Variant::Chip8X), Some(Opcode::ColourZones { x: 0x1, y: 0x2 }));
+        assert_eq!(Instruction::new(0xB123).decode_for(Variant::Chip8X), Some(Opcode::ColourRows { x: 0x1, y: 0x2, n: 0x3 }));
+        assert_eq!(Instruction::new(0xB123).decode_for(Variant::Chip8), Some(Opcode::JumpWithOffset { nnn: 0x123 }));
+    }
+
+    #[test]
+    fn megachip_takes_over_part_of_0nnn() {
+        let mega = |raw: u16| Instruction::new(raw).decode_for(Variant::MegaChip);
+
+        assert_eq!(mega(0x0010), Some(Opcode::MegaOff));
+        assert_eq!(mega(0x0011), Some(Opcode::MegaOn));
+        assert_eq!(mega(0x01AB), Some(Opcode::LoadWideI { nn: 0xAB }));
+        assert_eq!(mega(0x02AB), Some(Opcode::LoadPalette { nn: 0xAB }));
+        assert_eq!(mega(0x03AB), Some(Opcode::SpriteWidth { nn: 0xAB }));
+        assert_eq!(mega(0x04AB), Some(Opcode::SpriteHeight { nn: 0xAB }));
+        assert_eq!(mega(0x05AB), Some(Opcode::ScreenAlpha { nn: 0xAB }));
+        assert_eq!(mega(0x0601), Some(Opcode::PlaySample { n: 0x1 }));
+        assert_eq!(mega(0x0700), Some(Opcode::StopSample));
+        assert_eq!(mega(0x0802), Some(Opcode::SetBlendMode { n: 0x2 }));
+        assert_eq!(mega(0x09AB), Some(Opcode::CollisionColour { nn: 0xAB }));
+        assert_eq!(mega(0x00E0), Some(Opcode::ClearScreen));
+        assert_eq!(mega(0x0612), Some(Opcode::Sys { nnn: 0x612 }));
+        assert_eq!(Instruction::new(0x0011).decode(), Some(Opcode::Sys { nnn: 0x011 }));
+
+        for value in 0x0000u32..=0x0FFFu32 {
+            let raw = value as u16;
+            let opcode = mega(raw).unwrap();
+            assert_eq!(opcode.encode(), raw, "{:?}", opcode);
+        }
+    }
+
+    #[test]
+    fn only_undefined_words_fail_to_decode() {
+        let unknown: Vec<u16> = (0x0000u32..=0xFFFFu32)
+            .map(|value| value as u16)
+            .filter(|&raw| Instruction::new(raw).decode().is_none())
+            .collect();
+
+        // 5XY4-F, 8XY8-D, 8XYF, 9XY1-F, EX00-FF except 9E, A1, F2 and F5,
+        // FX00-FF except the 15 FXNN ones, FN01, F000 and F002.
+        let expected = 256 * 12 + 256 * 7 + 256 * 15 + 16 * 252 + (16 * 256 - 16 * 15 - 16 - 2);
+        assert_eq!(unknown.len(), expected);
+        assert!(unknown.contains(&0x5004));
+        assert!(unknown.contains(&0x800F));
+        assert!(unknown.contains(&0xE000));
+        assert!(unknown.contains(&0xF0FF));
+    }
+
+    #[test]
+    fn decodes_operands_by_name() {
+        assert_eq!(Instruction::new(0x00E0).decode(), Some(Opcode::ClearScreen));
+        assert_eq!(Instruction::new(0x00EE).decode(), Some(Opcode::Return));
+        assert_eq!(Instruction::new(0x0123).decode(), Some(Opcode::Sys { nnn: 0x123 }));
+        assert_eq!(Instruction::new(0x00C5).decode(), Some(Opcode::ScrollDown { n: 0x5 }));
+        assert_eq!(Instruction::new(0x00FB).decode(), Some(Opcode::ScrollRight));
+        assert_eq!(Instruction::new(0x00FC).decode(), Some(Opcode::ScrollLeft));
+        assert_eq!(Instruction::new(0x00FD).decode(), Some(Opcode::Exit));
+        assert_eq!(Instruction::new(0x00FE).decode(), Some(Opcode::LowResolution));
+        assert_eq!(Instruction::new(0x00FF).decode(), Some(Opcode::HighResolution));
+        assert_eq!(Instruction::new(0x1ABC).decode(), Some(Opcode::Jump { nnn: 0xABC }));
+        assert_eq!(Instruction::new(0x2ABC).decode(), Some(Opcode::Call { nnn: 0xABC }));
+        assert_eq!(Instruction::new(0x3A12).decode(), Some(Opcode::SkipIfEqual { x: 0xA, nn: 0x12 }));
+        assert_eq!(Instruction::new(0x4A12).decode(), Some(Opcode::SkipIfNotEqual { x: 0xA, nn: 0x12 }));
+        assert_eq!(Instruction::new(0x5AB0).decode(), Some(Opcode::SkipIfRegistersEqual { x: 0xA, y: 0xB }));
+        assert_eq!(Instruction::new(0x6A12).decode(), Some(Opcode::Load { x: 0xA, nn: 0x12 }));
+        assert_eq!(Instruction::new(0x7A12).decode(), Some(Opcode::Add { x: 0xA, nn: 0x12 }));
+        assert_eq!(Instruction::new(0x8AB0).decode(), Some(Opcode::Move { x: 0xA, y: 0xB }));
+        assert_eq!(Instruction::new(0x8AB1).decode(), Some(Opcode::Or { x: 0xA, y: 0xB }));
+        assert_eq!(Instruction::new(0x8AB2).decode(), Some(Opcode::And { x: 0xA, y: 0xB }));
+        assert_eq!(Instruction::new(0x8AB3).decode(), Some(Opcode::Xor { x: 0xA, y: 0xB }));
+        assert_eq!(Instruction::new(0x8AB4).decode(), Some(Opcode::AddRegisters { x: 0xA, y: 0xB }));
+        assert_eq!(Instruction::new(0x8AB5).decode(), Some(Opcode::Subtract { x: 0xA, y: 0xB }));
+        assert_eq!(Instruction::new(0x8AB6).decode(), Some(Opcode::ShiftRight { x: 0xA, y: 0xB }));
+        assert_eq!(Instruction::new(0x8AB7).decode(), Some(Opcode::SubtractReversed { x: 0xA, y: 0xB }));
+        assert_eq!(Instruction::new(0x8ABE).decode(), Some(Opcode::ShiftLeft { x: 0xA, y: 0xB }));
+        assert_eq!(Instruction::new(0x9AB0).decode(), Some(Opcode::SkipIfRegistersNotEqual { x: 0xA, y: 0xB }));
+        assert_eq!(Instruction::new(0xA123).decode(), Some(Opcode::LoadI { nnn: 0x123 }));
+        assert_eq!(Instruction::new(0xB123).decode(), Some(Opcode::JumpWithOffset { nnn: 0x123 }));
+        assert_eq!(Instruction::new(0xCA12).decode(), Some(Opcode::Random { x: 0xA, nn: 0x12 }));
+        assert_eq!(Instruction::new(0xDAB5).decode(), Some(Opcode::Draw { x: 0xA, y: 0xB, n: 0x5 }));
+        assert_eq!(Instruction::new(0xEA9E).decode(), Some(Opcode::SkipIfKeyPressed { x: 0xA }));
+        assert_eq!(Instruction::new(0xEAA1).decode(), Some(Opcode::SkipIfKeyNotPressed { x: 0xA }));
+        assert_eq!(Instruction::new(0xFA07).decode(), Some(Opcode::ReadDelayTimer { x: 0xA }));
+        assert_eq!(Instruction::new(0xFA0A).decode(), Some(Opcode::WaitForKey { x: 0xA }));
+        assert_eq!(Instruction::new(0xFA15).decode(), Some(Opcode::SetDelayTimer { x: 0xA }));
+        assert_eq!(Instruction::new(0xFA18).decode(), Some(Opcode::SetSoundTimer { x: 0xA }));
+        assert_eq!(Instruction::new(0xFA1E).decode(), Some(Opcode::AddToI { x: 0xA }));
+        assert_eq!(Instruction::new(0xFA29).decode(), Some(Opcode::LoadFont { x: 0xA }));
+        assert_eq!(Instruction::new(0xFA33).decode(), Some(Opcode::StoreBcd { x: 0xA }));
+        assert_eq!(Instruction::new(0xFA55).decode(), Some(Opcode::StoreRegisters { x: 0xA }));
+        assert_eq!(Instruction::new(0xFA65).decode(), Some(Opcode::LoadRegisters { x: 0xA }));
+        assert_eq!(Instruction::new(0xFA30).decode(), Some(Opcode::LoadBigFont { x: 0xA }));
+        assert_eq!(Instruction::new(0xFA75).decode(), Some(Opcode::SaveFlags { x: 0xA }));
+        assert_eq!(Instruction::new(0xFA85).decode(), Some(Opcode::LoadFlags { x: 0xA }));
+        assert_eq!(Instruction::new(0x00D3).decode(), Some(Opcode::ScrollUp { n: 0x3 }));
+        assert_eq!(Instruction::new(0x5AB2).decode(), Some(Opcode::SaveRange { x: 0xA, y: 0xB }));
+        assert_eq!(Instruction::new(0x5AB3).decode(), Some(Opcode::LoadRange { x: 0xA, y: 0xB }));
+        assert_eq!(Instruction::new(0xF000).decode(), Some(Opcode::LoadLongI));
+        assert_eq!(Instruction::new(0xF301).decode(), Some(Opcode::SelectPlanes { n: 0x3 }));
+        assert_eq!(Instruction::new(0xF002).decode(), Some(Opcode::LoadAudio));
+        assert_eq!(Instruction::new(0xFA3A).decode(), Some(Opcode::SetPitch { x: 0xA }));
+        assert_eq!(Instruction::new(0x02A0).decode(), Some(Opcode::CycleBackground));
+        assert_eq!(Instruction::new(0x5AB1).decode(), Some(Opcode::AddNibbles { x: 0xA, y: 0xB }));
+        assert_eq!(Instruction::new(0xEAF2).decode(), Some(Opcode::SkipIfSecondKeyPressed { x: 0xA }));
+        assert_eq!(Instruction::new(0xEAF5).decode(), Some(Opcode::SkipIfSecondKeyNotPressed { x: 0xA }));
+        assert_eq!(Instruction::new(0xFAF8).decode(), Some(Opcode::OutputPort { x: 0xA }));
+        assert_eq!(Instruction::new(0xFAFB).decode(), Some(Opcode::InputPort { x: 0xA }));
+    }
+}
@@ -0,0 +1,48 @@
+pub const KEY_COUNT: usize = 16;
+
+// State of the 16 key hexadecimal keypad, 0x0 to 0xF.
+// The host presses and releases keys, the CPU only reads them.
+#[derive(Clone)]
+pub struct Keypad {
+    keys: [bool; KEY_COUNT],
+}
+
+impl Default for Keypad {
+    fn default() -> Keypad {
+        Keypad::new()
+    }
+}
+
+impl Keypad {
+    pub fn new() -> Keypad {
+        Keypad {
+            keys: [false; KEY_COUNT],
+        }
+    }
+
+    // Keys past 0xF are not on the keypad and are ignored.
+    pub fn press(&mut self, key: u8) {
+        if let Some(pressed) = self.keys.get_mut(key as usize) {
+            *pressed = true;
+        }
+    }
+
+    pub fn release(&mut self, key: u8) {
+        if let Some(pressed) = self.keys.get_mut(key as usize) {
+            *pressed = false;
+        }
+    }
+
+    pub fn release_all(&mut self) {
+        self.keys = [false; KEY_COUNT];
+    }
+
+    pub fn is_pressed(&self, key: u8) -> bool {
+        self.keys.get(key as usize).cloned().unwrap_or(false)
+    }
+
+    // Lowest key currently held down, if any.
+    pub fn first_pressed(&self) -> Option<u8> {
+        self.keys.iter().position(|&pressed| pressed).map(|key| key as u8)
+    }
+}
@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod keypad_test {
+
+    use keypad::{ Keypad, KEY_COUNT };
+
+    #[test]
+    fn starts_with_no_key_pressed() {
+        let keypad = Keypad::new();
+        for key in 0..KEY_COUNT {
+            assert!(!keypad.is_pressed(key as u8));
+        }
+        assert_eq!(keypad.first_pressed(), None);
+    }
+
+    #[test]
+    fn press_and_release_a_key() {
+        let mut keypad = Keypad::new();
+        keypad.press(0xA);
+        assert!(keypad.is_pressed(0xA));
+        assert!(!keypad.is_pressed(0xB));
+
+        keypad.release(0xA);
+        assert!(!keypad.is_pressed(0xA));
+    }
+
+    #[test]
+    fn keys_off_the_keypad_are_ignored() {
+        let mut keypad = Keypad::new();
+        keypad.press(0x10);
+        keypad.press(0xFF);
+
+        assert_eq!(keypad.first_pressed(), None);
+        assert!(!keypad.is_pressed(0x10));
+        keypad.release(0x10);
+    }
+
+    #[test]
+    fn first_pressed_returns_lowest_key() {
+        let mut keypad = Keypad::new();
+        keypad.press(0xC);
+        keypad.press(0x3);
+
+        assert_eq!(keypad.first_pressed(), Some(0x3));
+
+        keypad.release_all();
+        assert_eq!(keypad.first_pressed(), None);
+    }
+}
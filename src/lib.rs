@@ -1,11 +1,63 @@
+#![cfg_attr(test, allow(clippy::module_inception))]
+
 extern crate rand;
 
+pub use chip8::Chip8;
+pub use error::EmulatorError;
+
 #[macro_use]
 pub mod cpu;
+pub mod error;
 pub mod ram;
+pub mod font;
+pub mod display;
+pub mod stack;
+pub mod timer;
+pub mod keypad;
+pub mod quirks;
+pub mod bus;
+pub mod chip8;
 pub mod instruction;
+pub mod platform;
+pub mod audio;
+pub mod colour;
+pub mod port;
+pub mod megachip;
+pub mod cdp1802;
+pub mod vip;
+pub mod random;
+pub mod timing;
+pub mod disassembler;
+pub mod assembler;
+pub mod octo;
+pub mod decompiler;
+pub mod analysis;
 
 #[cfg(test)]
 mod cpu_test;
+// The baseline tests predate the clippy gate and are kept as written.
+#[allow(clippy::unnecessary_cast)]
 mod ram_test;
-mod instruction_test;
\ No newline at end of file
+mod display_test;
+mod stack_test;
+mod timer_test;
+mod keypad_test;
+mod quirks_test;
+mod chip8_test;
+#[allow(clippy::identity_op, clippy::unused_enumerate_index)]
+mod instruction_test;
+mod platform_test;
+mod audio_test;
+mod colour_test;
+mod megachip_test;
+mod cdp1802_test;
+mod vip_test;
+mod font_test;
+mod random_test;
+mod timing_test;
+mod conformance_test;
+mod disassembler_test;
+mod assembler_test;
+mod octo_test;
+mod decompiler_test;
+mod analysis_test;
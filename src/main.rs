@@ -1,4 +1,134 @@
+extern crate chip8;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use chip8::analysis;
+use chip8::assembler::{self, Assembly};
+use chip8::decompiler;
+use chip8::disassembler;
+use chip8::octo;
+use chip8::platform::Platform;
+
+const USAGE: &str = "usage: chip8 disasm [--platform NAME] [--load ADDRESS] ROM
+       chip8 decompile [--platform NAME] [--load ADDRESS] ROM
+       chip8 analyse [--platform NAME] [--load ADDRESS] [-o DOT] ROM
+       chip8 asm [-o ROM] SOURCE
+       chip8 octo [--platform NAME] [-o ROM] SOURCE";
+
+// What the command line asked for, past the command itself.
+struct Options {
+    platform: Platform,
+    // Overrides the platform's load address.
+    load_address: Option<u16>,
+    output: Option<String>,
+    files: Vec<String>,
+}
 
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("disasm") => parse_options(&args[1..]).and_then(|options| disasm(&options)),
+        Some("decompile") => parse_options(&args[1..]).and_then(|options| decompile(&options)),
+        Some("analyse") => parse_options(&args[1..]).and_then(|options| analyse(&options)),
+        Some("asm") => parse_options(&args[1..]).and_then(|options| asm(&options)),
+        Some("octo") => parse_options(&args[1..]).and_then(|options| build_octo(&options)),
+        _ => Err(USAGE.to_string()),
+    };
+    if let Err(message) = result {
+        eprintln!("chip8: {}", message);
+        process::exit(1);
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options { platform: Platform::chip8(), load_address: None, output: None, files: Vec::new() };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--platform" => {
+                let name = args.next().ok_or_else(|| USAGE.to_string())?;
+                options.platform = Platform::named(name).ok_or_else(|| format!("unknown platform {}", name))?;
+            }
+            "--load" => {
+                let address = args.next().ok_or_else(|| USAGE.to_string())?;
+                options.load_address = Some(parse_address(address)?);
+            }
+            "-o" => {
+                let output = args.next().ok_or_else(|| USAGE.to_string())?;
+                options.output = Some(output.clone());
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}\n{}", arg, USAGE)),
+            _ => options.files.push(arg.clone()),
+        }
+    }
+    Ok(options)
+}
+
+// Addresses are hex, with or without a 0x in front.
+fn parse_address(text: &str) -> Result<u16, String> {
+    let digits = text.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("bad address {}", text))
+}
+
+fn single_file(options: &Options) -> Result<&String, String> {
+    match options.files.as_slice() {
+        [path] => Ok(path),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn disassemble(options: &Options) -> Result<disassembler::Disassembly, String> {
+    let path = single_file(options)?;
+    let rom = fs::read(path).map_err(|error| format!("{}: {}", path, error))?;
+    let load_address = options.load_address.unwrap_or(options.platform.load_address);
+    Ok(disassembler::disassemble(&rom, load_address, options.platform.variant))
+}
+
+fn disasm(options: &Options) -> Result<(), String> {
+    print!("{}", disassemble(options)?.listing());
+    Ok(())
+}
+
+fn decompile(options: &Options) -> Result<(), String> {
+    print!("{}", decompiler::decompile(&disassemble(options)?));
+    Ok(())
+}
+
+// Prints the report, and writes the graph as DOT if asked to.
+fn analyse(options: &Options) -> Result<(), String> {
+    let disassembly = disassemble(options)?;
+    let analysis = analysis::analyse(&disassembly);
+    print!("{}", analysis.report());
+    if let Some(ref output) = options.output {
+        fs::write(output, analysis.dot()).map_err(|error| format!("{}: {}", output, error))?;
+    }
+    Ok(())
+}
+
+fn asm(options: &Options) -> Result<(), String> {
+    let source = Path::new(single_file(options)?);
+    let assembly = assembler::assemble_file(source).map_err(|error| error.to_string())?;
+    write_assembly(options, source, &assembly)
+}
+
+fn build_octo(options: &Options) -> Result<(), String> {
+    let source = Path::new(single_file(options)?);
+    let assembly = octo::compile_file(source, options.platform.variant).map_err(|error| error.to_string())?;
+    write_assembly(options, source, &assembly)
+}
+
+// Writes the ROM, by default next to the source with a .ch8 extension,
+// and its symbols next to the ROM with a .sym one.
+fn write_assembly(options: &Options, source: &Path, assembly: &Assembly) -> Result<(), String> {
+    let rom = match options.output {
+        Some(ref output) => Path::new(output).to_path_buf(),
+        None => source.with_extension("ch8"),
+    };
+    let symbols = rom.with_extension("sym");
+    fs::write(&rom, &assembly.rom).map_err(|error| format!("{}: {}", rom.display(), error))?;
+    fs::write(&symbols, assembly.symbol_file()).map_err(|error| format!("{}: {}", symbols.display(), error))?;
+    Ok(())
 }
@@ -0,0 +1,251 @@
+// MegaChip8 hardware: a 256x192 colour framebuffer with a 256 entry
+// palette, drawn beside the CHIP-8 one, and a digitised sound channel.
+pub const MEGA_WIDTH: usize = 256;
+pub const MEGA_HEIGHT: usize = 192;
+pub const PALETTE_SIZE: usize = 256;
+
+// Sample headers: 16 bit rate, 24 bit length and a reserved byte.
+pub const SAMPLE_HEADER_SIZE: u32 = 6;
+
+// How a sprite pixel is combined with the one already on screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    Normal,
+    Transparent25,
+    Transparent50,
+    Additive,
+    Multiply,
+}
+
+impl BlendMode {
+    // The mode selected by 080N. Unknown values fall back to normal.
+    pub fn from_code(code: u8) -> BlendMode {
+        match code {
+            1 => BlendMode::Transparent25,
+            2 => BlendMode::Transparent50,
+            3 => BlendMode::Additive,
+            4 => BlendMode::Multiply,
+            _ => BlendMode::Normal,
+        }
+    }
+
+    // Mixes `source` over `destination`, both 0xAARRGGBB. In normal mode
+    // the source's own alpha decides how much of it shows.
+    pub fn blend(&self, source: u32, destination: u32) -> u32 {
+        let alpha = source >> 24;
+        let mix = |shift: u32| {
+            let s = (source >> shift) & 0xFF;
+            let d = (destination >> shift) & 0xFF;
+            let value = match *self {
+                BlendMode::Normal => (s * alpha + d * (255 - alpha)) / 255,
+                BlendMode::Transparent25 => (s * 3 + d) / 4,
+                BlendMode::Transparent50 => (s + d) / 2,
+                BlendMode::Additive => (s + d).min(0xFF),
+                BlendMode::Multiply => s * d / 255,
+            };
+            value << shift
+        };
+        0xFF00_0000 | mix(16) | mix(8) | mix(0)
+    }
+}
+
+// Sprites are drawn into a back buffer that 00E0 shows and clears, so
+// frontends only ever see finished frames.
+#[derive(Clone)]
+pub struct MegaDisplay {
+    enabled: bool,
+    palette: [u32; PALETTE_SIZE],
+    sprite_width: usize,
+    sprite_height: usize,
+    blend_mode: BlendMode,
+    collision_colour: u8,
+    screen_alpha: u8,
+    indexes: Vec<u8>,
+    back: Vec<u32>,
+    front: Vec<u32>,
+}
+
+impl Default for MegaDisplay {
+    fn default() -> MegaDisplay {
+        MegaDisplay::new()
+    }
+}
+
+impl MegaDisplay {
+    pub fn new() -> MegaDisplay {
+        MegaDisplay {
+            enabled: false,
+            palette: [0; PALETTE_SIZE],
+            sprite_width: 0,
+            sprite_height: 0,
+            blend_mode: BlendMode::Normal,
+            collision_colour: 0,
+            screen_alpha: 0xFF,
+            indexes: vec![0; MEGA_WIDTH * MEGA_HEIGHT],
+            back: vec![0; MEGA_WIDTH * MEGA_HEIGHT],
+            front: vec![0; MEGA_WIDTH * MEGA_HEIGHT],
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // 0011 and 0010. Either way the screen starts out black.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.clear();
+        self.update();
+    }
+
+    pub fn palette(&self) -> &[u32; PALETTE_SIZE] {
+        &self.palette
+    }
+
+    pub fn set_palette_entry(&mut self, index: u8, colour: u32) {
+        self.palette[index as usize] = colour;
+    }
+
+    pub fn sprite_size(&self) -> (usize, usize) {
+        (self.sprite_width, self.sprite_height)
+    }
+
+    // 0 stands for 256.
+    pub fn set_sprite_width(&mut self, width: u8) {
+        self.sprite_width = if width == 0 { 256 } else { width as usize };
+    }
+
+    pub fn set_sprite_height(&mut self, height: u8) {
+        self.sprite_height = if height == 0 { 256 } else { height as usize };
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    pub fn collision_colour(&self) -> u8 {
+        self.collision_colour
+    }
+
+    pub fn set_collision_colour(&mut self, index: u8) {
+        self.collision_colour = index;
+    }
+
+    // Opacity frontends apply to the whole screen, for fades.
+    pub fn screen_alpha(&self) -> u8 {
+        self.screen_alpha
+    }
+
+    pub fn set_screen_alpha(&mut self, alpha: u8) {
+        self.screen_alpha = alpha;
+    }
+
+    // Draws a sprite of palette indexes, row by row, with the current
+    // sprite size. Index 0 is transparent and pixels off the screen are
+    // clipped. Returns true when a pixel of the collision colour was
+    // drawn over; empty pixels never collide.
+    pub fn draw(&mut self, x: u8, y: u8, sprite: &[u8]) -> bool {
+        let mut collision = false;
+        for row in 0..self.sprite_height {
+            let py = y as usize + row;
+            if py >= MEGA_HEIGHT {
+                break;
+            }
+            for column in 0..self.sprite_width {
+                let px = x as usize + column;
+                if px >= MEGA_WIDTH {
+                    break;
+                }
+                let index = sprite.get(row * self.sprite_width + column).cloned().unwrap_or(0);
+                if index == 0 {
+                    continue;
+                }
+                let pixel = py * MEGA_WIDTH + px;
+                let covered = self.indexes[pixel];
+                collision |= covered != 0 && covered == self.collision_colour;
+                self.indexes[pixel] = index;
+                self.back[pixel] = self.blend_mode.blend(self.palette[index as usize], self.back[pixel]);
+            }
+        }
+        collision
+    }
+
+    // Clears the back buffer.
+    pub fn clear(&mut self) {
+        for index in self.indexes.iter_mut() {
+            *index = 0;
+        }
+        for pixel in self.back.iter_mut() {
+            *pixel = 0;
+        }
+    }
+
+    // Shows the back buffer.
+    pub fn update(&mut self) {
+        self.front.copy_from_slice(&self.back);
+    }
+
+    // The frame on screen, 0xAARRGGBB row by row.
+    pub fn frame(&self) -> &[u32] {
+        &self.front
+    }
+}
+
+// Plays 8 bit unsigned samples started by 060N until they run out, or
+// until 0700 when looping.
+#[derive(Clone)]
+pub struct SamplePlayer {
+    samples: Vec<u8>,
+    rate: u16,
+    looping: bool,
+    playing: bool,
+}
+
+impl Default for SamplePlayer {
+    fn default() -> SamplePlayer {
+        SamplePlayer::new()
+    }
+}
+
+impl SamplePlayer {
+    pub fn new() -> SamplePlayer {
+        SamplePlayer {
+            samples: Vec::new(),
+            rate: 0,
+            looping: false,
+            playing: false,
+        }
+    }
+
+    pub fn play(&mut self, samples: Vec<u8>, rate: u16, looping: bool) {
+        self.samples = samples;
+        self.rate = rate;
+        self.looping = looping;
+        self.playing = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    // Samples per second.
+    pub fn rate(&self) -> u16 {
+        self.rate
+    }
+
+    pub fn samples(&self) -> &[u8] {
+        &self.samples
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod megachip_test {
+
+    use megachip::{ MegaDisplay, SamplePlayer, BlendMode, MEGA_WIDTH };
+
+    #[test]
+    fn sprites_are_palette_indexes_with_zero_transparent() {
+        let mut display = MegaDisplay::new();
+        display.set_enabled(true);
+        display.set_palette_entry(1, 0xFFFF0000);
+        display.set_sprite_width(2);
+        display.set_sprite_height(1);
+
+        assert!(!display.draw(10, 0, &[1, 0]));
+        display.update();
+        assert_eq!(display.frame()[10], 0xFFFF0000);
+        assert_eq!(display.frame()[11], 0x00000000);
+    }
+
+    #[test]
+    fn frames_show_only_after_update() {
+        let mut display = MegaDisplay::new();
+        display.set_palette_entry(1, 0xFF00FF00);
+        display.set_sprite_width(1);
+        display.set_sprite_height(1);
+        display.draw(0, 1, &[1]);
+
+        assert_eq!(display.frame()[MEGA_WIDTH], 0);
+        display.update();
+        assert_eq!(display.frame()[MEGA_WIDTH], 0xFF00FF00);
+    }
+
+    #[test]
+    fn drawing_over_the_collision_colour_collides() {
+        let mut display = MegaDisplay::new();
+        display.set_palette_entry(1, 0xFFFFFFFF);
+        display.set_sprite_width(1);
+        display.set_sprite_height(1);
+        display.set_collision_colour(1);
+
+        assert!(!display.draw(0, 0, &[1]));
+        assert!(display.draw(0, 0, &[1]));
+    }
+
+    #[test]
+    fn blend_modes_mix_source_and_destination() {
+        let white = 0xFFFFFFFF;
+        let grey = 0xFF808080;
+
+        assert_eq!(BlendMode::Normal.blend(grey, white), grey);
+        assert_eq!(BlendMode::Normal.blend(0x00000000, white), white);
+        assert_eq!(BlendMode::Transparent50.blend(0xFF000000, white), 0xFF7F7F7F);
+        assert_eq!(BlendMode::Transparent25.blend(0xFF000000, white), 0xFF3F3F3F);
+        assert_eq!(BlendMode::Additive.blend(grey, grey), white);
+        assert_eq!(BlendMode::Multiply.blend(grey, white), grey);
+        assert_eq!(BlendMode::from_code(9), BlendMode::Normal);
+    }
+
+    #[test]
+    fn zero_sprite_size_means_256() {
+        let mut display = MegaDisplay::new();
+        display.set_sprite_width(0);
+        display.set_sprite_height(0);
+
+        assert_eq!(display.sprite_size(), (256, 256));
+    }
+
+    #[test]
+    fn sample_player_plays_until_stopped() {
+        let mut player = SamplePlayer::new();
+        player.play(vec![0x80, 0xFF], 8000, true);
+
+        assert!(player.is_playing());
+        assert!(player.is_looping());
+        assert_eq!(player.rate(), 8000);
+        assert_eq!(player.samples(), &[0x80, 0xFF]);
+
+        player.stop();
+        assert!(!player.is_playing());
+    }
+}
@@ -0,0 +1,947 @@
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::f64::consts;
+use std::fs;
+use std::path::Path;
+
+use assembler::{AssembleError, Assembly};
+use cpu::START;
+use instruction::Opcode;
+use platform::Variant;
+use ram::{MEMORY_SIZE, XO_MEMORY_SIZE, MEGA_MEMORY_SIZE};
+
+// Compiles Octo, the language most CHIP-8 homebrew is written in, into a
+// ROM loaded at 0x200. Execution starts at the `main` label: unless it
+// comes before any code or data, 0x200 holds a jump to it.
+//
+// As in Octo, tokens are separated by whitespace, `#` starts a comment,
+// labels may be used before they are defined and `:calc` expressions are
+// worked out right to left, without precedence.
+
+pub fn compile(source: &str, variant: Variant) -> Result<Assembly, AssembleError> {
+    Compiler::new(source, "<source>", variant).run()
+}
+
+pub fn compile_file(path: &Path, variant: Variant) -> Result<Assembly, AssembleError> {
+    let file = path.display().to_string();
+    match fs::read_to_string(path) {
+        Ok(source) => Compiler::new(&source, &file, variant).run(),
+        Err(error) => Err(AssembleError { file, line: 0, column: 0, message: format!("could not read: {}", error) }),
+    }
+}
+
+// Macros expanding into macros deeper than this are taken to be a loop.
+const MAX_EXPANSIONS: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for (number, line) in source.lines().enumerate() {
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i].is_whitespace() {
+                i += 1;
+                continue;
+            }
+            if chars[i] == '#' {
+                break;
+            }
+            let start = i;
+            while i < chars.len() && !chars[i].is_whitespace() {
+                i += 1;
+            }
+            tokens.push(Token { text: chars[start..i].iter().collect(), line: number + 1, column: start + 1 });
+        }
+    }
+    tokens
+}
+
+fn number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+        i64::from_str_radix(binary, 2).ok()?
+    } else {
+        digits.parse().ok()?
+    };
+    Some(if negative { -value } else { value })
+}
+
+struct Macro {
+    parameters: Vec<String>,
+    body: Vec<Token>,
+}
+
+// What a word in the source stands for, as far as is known yet.
+enum Value {
+    Known(i64),
+    // A label yet to be defined.
+    Forward(String),
+}
+
+// How to fill in a reference to a label once it is defined.
+#[derive(Clone, Copy)]
+enum Fixup {
+    // The low 12 bits of the word at the address.
+    Address,
+    // The whole word at the address.
+    Long,
+    // The byte at the address, which `:unpack` loads from: with the
+    // nibble, the top 4 bits of the address; without, its high byte.
+    UnpackHigh(Option<u8>),
+    UnpackLow,
+}
+
+// A test `if`, `while` and friends turn into: whether VX equals NN or VY
+// or, for keys, whether the key in VX is down.
+#[derive(Clone, Copy)]
+enum Test {
+    Byte(u8, u8),
+    Register(u8, u8),
+    Key(u8),
+}
+
+// The instruction skipping the next when `test` comes out as `outcome`.
+fn skip_when(test: Test, outcome: bool) -> Opcode {
+    match (test, outcome) {
+        (Test::Byte(x, nn), true) => Opcode::SkipIfEqual { x, nn },
+        (Test::Byte(x, nn), false) => Opcode::SkipIfNotEqual { x, nn },
+        (Test::Register(x, y), true) => Opcode::SkipIfRegistersEqual { x, y },
+        (Test::Register(x, y), false) => Opcode::SkipIfRegistersNotEqual { x, y },
+        (Test::Key(x), true) => Opcode::SkipIfKeyPressed { x },
+        (Test::Key(x), false) => Opcode::SkipIfKeyNotPressed { x },
+    }
+}
+
+// The memory a program for `variant` can fill: 64 KB for XO-CHIP, 16 MB
+// for MegaChip8 and 4 KB for the rest.
+fn memory_size(variant: Variant) -> usize {
+    match variant {
+        Variant::XoChip => XO_MEMORY_SIZE,
+        Variant::MegaChip => MEGA_MEMORY_SIZE,
+        _ => MEMORY_SIZE,
+    }
+}
+
+// Open `loop`s, with the jumps out of them their `while`s left to fill in.
+struct Loop {
+    start: u32,
+    exits: Vec<u32>,
+}
+
+struct Compiler {
+    file: String,
+    variant: Variant,
+    // Code and data have to end below this.
+    memory_size: usize,
+    // Still to be read, last first.
+    tokens: Vec<Token>,
+    // The last token read, for errors at the end of the source.
+    last: Token,
+    expansions: usize,
+    // Whether 0x200 still has to get a jump to main, which it does
+    // unless main is what is there.
+    entry_pending: bool,
+    // From START on.
+    rom: Vec<u8>,
+    here: u32,
+    labels: HashMap<String, u32>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<(u32, Fixup, String, Token)>,
+    loops: Vec<Loop>,
+    // Jumps `else` and `end` have to fill in.
+    branches: Vec<u32>,
+}
+
+impl Compiler {
+    fn new(source: &str, file: &str, variant: Variant) -> Compiler {
+        let mut tokens = tokenize(source);
+        tokens.reverse();
+        let mut aliases = HashMap::new();
+        aliases.insert("unpack-hi".to_string(), 0x0);
+        aliases.insert("unpack-lo".to_string(), 0x1);
+        aliases.insert("compare-temp".to_string(), 0xF);
+        Compiler {
+            file: file.to_string(),
+            variant,
+            memory_size: memory_size(variant),
+            tokens,
+            last: Token { text: String::new(), line: 1, column: 1 },
+            expansions: 0,
+            entry_pending: true,
+            rom: Vec::new(),
+            here: START as u32,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases,
+            macros: HashMap::new(),
+            fixups: Vec::new(),
+            loops: Vec::new(),
+            branches: Vec::new(),
+        }
+    }
+
+    fn error<T>(&self, token: &Token, message: String) -> Result<T, AssembleError> {
+        Err(AssembleError { file: self.file.clone(), line: token.line, column: token.column, message })
+    }
+
+    fn next(&mut self) -> Result<Token, AssembleError> {
+        match self.tokens.pop() {
+            Some(token) => {
+                self.last = token.clone();
+                Ok(token)
+            }
+            None => {
+                let last = self.last.clone();
+                self.error(&last, "unexpected end of source".to_string())
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.last().map(|token| token.text.as_str())
+    }
+
+    fn expect(&mut self, text: &str) -> Result<Token, AssembleError> {
+        let token = self.next()?;
+        if token.text != text {
+            return self.error(&token, format!("expected {}, found {}", text, token.text));
+        }
+        Ok(token)
+    }
+
+    fn run(mut self) -> Result<Assembly, AssembleError> {
+        while !self.tokens.is_empty() {
+            self.statement()?;
+        }
+        if let Some(&start) = self.loops.last().map(|open| &open.start) {
+            let last = self.last.clone();
+            return self.error(&last, format!("loop at {:03X} has no again", start));
+        }
+        if !self.branches.is_empty() {
+            let last = self.last.clone();
+            return self.error(&last, "if ... begin has no end".to_string());
+        }
+
+        for (address, fixup, name, token) in self.fixups.clone() {
+            let target = match self.labels.get(&name) {
+                Some(&target) => target,
+                None => return self.error(&token, format!("undefined label {}", name)),
+            };
+            self.patch(address, fixup, target, &token)?;
+        }
+
+        let mut symbols: BTreeMap<String, i64> = self.labels.iter()
+            .map(|(name, &address)| (name.clone(), address as i64))
+            .collect();
+        for (name, &value) in &self.constants {
+            symbols.insert(name.clone(), value as i64);
+        }
+        Ok(Assembly { origin: START as u32, rom: self.rom, symbols })
+    }
+
+    fn statement(&mut self) -> Result<(), AssembleError> {
+        let token = self.next()?;
+        let text = token.text.clone();
+        match text.as_str() {
+            ":" => {
+                let name = self.name()?;
+                self.define_label(&name, 0)
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.known_value()?;
+                self.define_constant(&name, value as f64)
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+                Ok(())
+            }
+            ":calc" => {
+                let name = self.name()?;
+                let value = self.calc()?;
+                self.define_constant(&name, value)
+            }
+            ":byte" => {
+                let value = if self.peek() == Some("{") { self.calc()? as i64 } else { self.known_value()? };
+                let byte = self.byte(value, &token)?;
+                self.emit_bytes(&[byte])
+            }
+            ":org" => {
+                let value = if self.peek() == Some("{") { self.calc()? as i64 } else { self.known_value()? };
+                if value < START as i64 || value >= self.memory_size as i64 {
+                    return self.error(&token, format!(":org {:X} is outside the program", value));
+                }
+                self.here = value as u32;
+                Ok(())
+            }
+            ":next" => {
+                let name = self.name()?;
+                self.define_label(&name, 1)
+            }
+            ":unpack" => self.unpack(&token),
+            ":macro" => self.define_macro(),
+            ":call" => {
+                let target = self.next()?;
+                self.call(&target)
+            }
+            ":breakpoint" => self.next().map(|_| ()),
+            ":monitor" => {
+                self.next()?;
+                self.next().map(|_| ())
+            }
+            "return" | ";" => self.emit(Opcode::Return, &token),
+            "clear" => self.emit(Opcode::ClearScreen, &token),
+            "hires" => self.emit(Opcode::HighResolution, &token),
+            "lores" => self.emit(Opcode::LowResolution, &token),
+            "exit" => self.emit(Opcode::Exit, &token),
+            "scroll-left" => self.emit(Opcode::ScrollLeft, &token),
+            "scroll-right" => self.emit(Opcode::ScrollRight, &token),
+            "scroll-down" => {
+                let n = self.nibble_value()?;
+                self.emit(Opcode::ScrollDown { n }, &token)
+            }
+            "scroll-up" => {
+                let n = self.nibble_value()?;
+                self.emit(Opcode::ScrollUp { n }, &token)
+            }
+            "plane" => {
+                let n = self.nibble_value()?;
+                self.emit(Opcode::SelectPlanes { n }, &token)
+            }
+            "audio" => self.emit(Opcode::LoadAudio, &token),
+            "bcd" => {
+                let x = self.register()?;
+                self.emit(Opcode::StoreBcd { x }, &token)
+            }
+            "saveflags" => {
+                let x = self.register()?;
+                self.emit(Opcode::SaveFlags { x }, &token)
+            }
+            "loadflags" => {
+                let x = self.register()?;
+                self.emit(Opcode::LoadFlags { x }, &token)
+            }
+            "save" | "load" => {
+                let x = self.register()?;
+                let save = text == "save";
+                if self.peek() == Some("-") {
+                    self.next()?;
+                    let y = self.register()?;
+                    let opcode = if save { Opcode::SaveRange { x, y } } else { Opcode::LoadRange { x, y } };
+                    return self.emit(opcode, &token);
+                }
+                let opcode = if save { Opcode::StoreRegisters { x } } else { Opcode::LoadRegisters { x } };
+                self.emit(opcode, &token)
+            }
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.nibble_value()?;
+                self.emit(Opcode::Draw { x, y, n }, &token)
+            }
+            "jump" | "jump0" | "native" => {
+                let target = self.next()?;
+                let value = self.value(&target)?;
+                let opcode = match text.as_str() {
+                    "jump" => Opcode::Jump { nnn: 0 },
+                    "jump0" => Opcode::JumpWithOffset { nnn: 0 },
+                    _ => Opcode::Sys { nnn: 0 },
+                };
+                self.emit_address(opcode, value, &target)
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register()?;
+                let opcode = match text.as_str() {
+                    "delay" => Opcode::SetDelayTimer { x },
+                    "buzzer" => Opcode::SetSoundTimer { x },
+                    _ => Opcode::SetPitch { x },
+                };
+                self.emit(opcode, &token)
+            }
+            "i" => self.index(&token),
+            "if" => self.conditional(&token),
+            "else" => {
+                let pending = match self.branches.pop() {
+                    Some(pending) => pending,
+                    None => return self.error(&token, "else without if ... begin".to_string()),
+                };
+                self.branches.push(self.here);
+                self.emit(Opcode::Jump { nnn: 0 }, &token)?;
+                let here = self.here;
+                self.patch(pending, Fixup::Address, here, &token)
+            }
+            "end" => {
+                let pending = match self.branches.pop() {
+                    Some(pending) => pending,
+                    None => return self.error(&token, "end without if ... begin".to_string()),
+                };
+                let here = self.here;
+                self.patch(pending, Fixup::Address, here, &token)
+            }
+            "loop" => {
+                self.loops.push(Loop { start: self.here, exits: Vec::new() });
+                Ok(())
+            }
+            "while" => {
+                if self.loops.is_empty() {
+                    return self.error(&token, "while outside a loop".to_string());
+                }
+                let (prelude, test, outcome) = self.condition()?;
+                for opcode in prelude {
+                    self.emit(opcode, &token)?;
+                }
+                self.emit(skip_when(test, outcome), &token)?;
+                let exit = self.here;
+                self.loops.last_mut().unwrap().exits.push(exit);
+                self.emit(Opcode::Jump { nnn: 0 }, &token)
+            }
+            "again" => {
+                let open = match self.loops.pop() {
+                    Some(open) => open,
+                    None => return self.error(&token, "again without loop".to_string()),
+                };
+                self.emit_address(Opcode::Jump { nnn: 0 }, Value::Known(open.start as i64), &token)?;
+                let here = self.here;
+                for exit in open.exits {
+                    self.patch(exit, Fixup::Address, here, &token)?;
+                }
+                Ok(())
+            }
+            _ => {
+                if let Some(value) = number(&text) {
+                    let byte = self.byte(value, &token)?;
+                    return self.emit_bytes(&[byte]);
+                }
+                if self.macros.contains_key(&text) {
+                    return self.expand(&token);
+                }
+                if self.register_named(&text).is_some() {
+                    return self.assignment(&token);
+                }
+                if let Some(&value) = self.constants.get(&text) {
+                    let byte = self.byte(value as i64, &token)?;
+                    return self.emit_bytes(&[byte]);
+                }
+                self.call(&token)
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String, AssembleError> {
+        let token = self.next()?;
+        if number(&token.text).is_some() || token.text.starts_with(':') || self.register_named(&token.text).is_some() {
+            return self.error(&token, format!("{} cannot be a name", token.text));
+        }
+        Ok(token.text)
+    }
+
+    // Defines `name` as `offset` bytes past here.
+    fn define_label(&mut self, name: &str, offset: u32) -> Result<(), AssembleError> {
+        if self.labels.contains_key(name) {
+            let last = self.last.clone();
+            return self.error(&last, format!("{} is already defined", name));
+        }
+        if self.here == START as u32 {
+            if name == "main" && offset == 0 {
+                self.entry_pending = false;
+            } else {
+                self.reserve_entry();
+            }
+        }
+        self.labels.insert(name.to_string(), self.here + offset);
+        Ok(())
+    }
+
+    fn define_constant(&mut self, name: &str, value: f64) -> Result<(), AssembleError> {
+        if self.labels.contains_key(name) {
+            let last = self.last.clone();
+            return self.error(&last, format!("{} is already a label", name));
+        }
+        self.constants.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    fn register_named(&self, text: &str) -> Option<u8> {
+        if let Some(&register) = self.aliases.get(text) {
+            return Some(register);
+        }
+        let lower = text.to_ascii_lowercase();
+        if lower.len() == 2 && lower.starts_with('v') {
+            return u8::from_str_radix(&lower[1..], 16).ok();
+        }
+        None
+    }
+
+    fn register(&mut self) -> Result<u8, AssembleError> {
+        let token = self.next()?;
+        match self.register_named(&token.text) {
+            Some(register) => Ok(register),
+            None => self.error(&token, format!("expected a register, found {}", token.text)),
+        }
+    }
+
+    // A number, constant or label, which may be a label still to come.
+    fn value(&self, token: &Token) -> Result<Value, AssembleError> {
+        if let Some(value) = number(&token.text) {
+            return Ok(Value::Known(value));
+        }
+        if let Some(&value) = self.constants.get(&token.text) {
+            return Ok(Value::Known(value as i64));
+        }
+        if let Some(&address) = self.labels.get(&token.text) {
+            return Ok(Value::Known(address as i64));
+        }
+        if self.register_named(&token.text).is_some() || token.text.starts_with(':') {
+            return self.error(token, format!("expected a value, found {}", token.text));
+        }
+        Ok(Value::Forward(token.text.clone()))
+    }
+
+    fn known_value(&mut self) -> Result<i64, AssembleError> {
+        let token = self.next()?;
+        match self.value(&token)? {
+            Value::Known(value) => Ok(value),
+            Value::Forward(name) => self.error(&token, format!("undefined name {}", name)),
+        }
+    }
+
+    fn byte(&self, value: i64, token: &Token) -> Result<u8, AssembleError> {
+        if !(-0x80..=0xFF).contains(&value) {
+            return self.error(token, format!("{} does not fit in a byte", value));
+        }
+        Ok(value as u8)
+    }
+
+    fn byte_value(&mut self) -> Result<u8, AssembleError> {
+        let value = self.known_value()?;
+        let last = self.last.clone();
+        self.byte(value, &last)
+    }
+
+    fn nibble_value(&mut self) -> Result<u8, AssembleError> {
+        let value = self.known_value()?;
+        if !(0..=0xF).contains(&value) {
+            let last = self.last.clone();
+            return self.error(&last, format!("{} does not fit in a nibble", value));
+        }
+        Ok(value as u8)
+    }
+
+    fn emit_bytes(&mut self, bytes: &[u8]) -> Result<(), AssembleError> {
+        self.reserve_entry();
+        if self.here as usize + bytes.len() > self.memory_size {
+            let last = self.last.clone();
+            return self.error(&last, format!("{:X} is past the end of memory", self.here));
+        }
+        let offset = (self.here - START as u32) as usize;
+        if self.rom.len() < offset + bytes.len() {
+            self.rom.resize(offset + bytes.len(), 0);
+        }
+        self.rom[offset..offset + bytes.len()].copy_from_slice(bytes);
+        self.here += bytes.len() as u32;
+        Ok(())
+    }
+
+    // Puts the jump to main at 0x200, once it is clear main is not there.
+    fn reserve_entry(&mut self) {
+        if !self.entry_pending {
+            return;
+        }
+        self.entry_pending = false;
+        let token = self.last.clone();
+        if self.rom.len() < 2 {
+            self.rom.resize(2, 0);
+        }
+        self.rom[0] = (Opcode::Jump { nnn: 0 }.encode() >> 8) as u8;
+        self.reference(START as u32, Fixup::Address, "main", &token);
+        if self.here == START as u32 {
+            self.here += 2;
+        }
+    }
+
+    fn emit_word(&mut self, word: u16) -> Result<(), AssembleError> {
+        self.emit_bytes(&[(word >> 8) as u8, word as u8])
+    }
+
+    fn emit(&mut self, opcode: Opcode, token: &Token) -> Result<(), AssembleError> {
+        if !self.variant.supports(&opcode) {
+            return self.error(token, format!("{} is not available on {:?}", token.text, self.variant));
+        }
+        self.emit_word(opcode.encode())
+    }
+
+    // Emits `opcode` with `value` as its NNN.
+    fn emit_address(&mut self, opcode: Opcode, value: Value, token: &Token) -> Result<(), AssembleError> {
+        let address = self.here;
+        self.emit(opcode, token)?;
+        match value {
+            Value::Known(target) => self.patch(address, Fixup::Address, target as u32, token),
+            Value::Forward(name) => {
+                self.reference(address, Fixup::Address, &name, token);
+                Ok(())
+            }
+        }
+    }
+
+    fn reference(&mut self, address: u32, fixup: Fixup, name: &str, token: &Token) {
+        self.fixups.push((address, fixup, name.to_string(), token.clone()));
+    }
+
+    fn patch(&mut self, address: u32, fixup: Fixup, target: u32, token: &Token) -> Result<(), AssembleError> {
+        let offset = (address - START as u32) as usize;
+        match fixup {
+            Fixup::Address => {
+                if target > 0xFFF {
+                    return self.error(token, format!("{:X} is out of reach of 12 bit addresses", target));
+                }
+                self.rom[offset] = (self.rom[offset] & 0xF0) | (target >> 8) as u8;
+                self.rom[offset + 1] = target as u8;
+            }
+            Fixup::Long => {
+                self.rom[offset] = (target >> 8) as u8;
+                self.rom[offset + 1] = target as u8;
+            }
+            Fixup::UnpackHigh(Some(nibble)) => self.rom[offset] = nibble << 4 | ((target >> 8) & 0xF) as u8,
+            Fixup::UnpackHigh(None) => self.rom[offset] = (target >> 8) as u8,
+            Fixup::UnpackLow => self.rom[offset] = target as u8,
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, token: &Token) -> Result<(), AssembleError> {
+        let value = self.value(token)?;
+        self.emit_address(Opcode::Call { nnn: 0 }, value, token)
+    }
+
+    fn expand(&mut self, token: &Token) -> Result<(), AssembleError> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return self.error(token, format!("macro {} expands forever", token.text));
+        }
+        let count = self.macros[&token.text].parameters.len();
+        let mut arguments = HashMap::new();
+        for index in 0..count {
+            let argument = self.next()?;
+            arguments.insert(self.macros[&token.text].parameters[index].clone(), argument.text);
+        }
+        let body: Vec<Token> = self.macros[&token.text].body.iter()
+            .map(|body_token| Token {
+                text: arguments.get(&body_token.text).cloned().unwrap_or_else(|| body_token.text.clone()),
+                ..body_token.clone()
+            })
+            .collect();
+        self.tokens.extend(body.into_iter().rev());
+        Ok(())
+    }
+
+    fn define_macro(&mut self) -> Result<(), AssembleError> {
+        let name = self.name()?;
+        let mut parameters = Vec::new();
+        loop {
+            let token = self.next()?;
+            if token.text == "{" {
+                break;
+            }
+            parameters.push(token.text);
+        }
+        let mut body = Vec::new();
+        let mut depth = 1;
+        loop {
+            let token = self.next()?;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" if depth == 1 => break,
+                "}" => depth -= 1,
+                _ => {}
+            }
+            body.push(token);
+        }
+        self.macros.insert(name, Macro { parameters, body });
+        Ok(())
+    }
+
+    // `:unpack N label` loads NL and LL, the label split in bytes with N
+    // in the top nibble, into unpack-hi and unpack-lo. `:unpack long
+    // label` loads the whole 16 bit address.
+    fn unpack(&mut self, token: &Token) -> Result<(), AssembleError> {
+        let nibble = if self.peek() == Some("long") {
+            self.next()?;
+            None
+        } else {
+            Some(self.nibble_value()?)
+        };
+        let target = self.next()?;
+        let value = self.value(&target)?;
+        let high = self.aliases["unpack-hi"];
+        let low = self.aliases["unpack-lo"];
+        let high_address = self.here + 1;
+        self.emit(Opcode::Load { x: high, nn: 0 }, token)?;
+        let low_address = self.here + 1;
+        self.emit(Opcode::Load { x: low, nn: 0 }, token)?;
+        match value {
+            Value::Known(address) => {
+                self.patch(high_address, Fixup::UnpackHigh(nibble), address as u32, &target)?;
+                self.patch(low_address, Fixup::UnpackLow, address as u32, &target)
+            }
+            Value::Forward(name) => {
+                self.reference(high_address, Fixup::UnpackHigh(nibble), &name, &target);
+                self.reference(low_address, Fixup::UnpackLow, &name, &target);
+                Ok(())
+            }
+        }
+    }
+
+    fn index(&mut self, token: &Token) -> Result<(), AssembleError> {
+        let operator = self.next()?;
+        match operator.text.as_str() {
+            "+=" => {
+                let x = self.register()?;
+                self.emit(Opcode::AddToI { x }, token)
+            }
+            ":=" => {
+                let source = self.next()?;
+                match source.text.as_str() {
+                    "hex" => {
+                        let x = self.register()?;
+                        self.emit(Opcode::LoadFont { x }, &source)
+                    }
+                    "bighex" => {
+                        let x = self.register()?;
+                        self.emit(Opcode::LoadBigFont { x }, &source)
+                    }
+                    "long" => {
+                        let target = self.next()?;
+                        let value = self.value(&target)?;
+                        self.emit(Opcode::LoadLongI, &source)?;
+                        let address = self.here;
+                        self.emit_word(0)?;
+                        match value {
+                            Value::Known(long) if (0..=0xFFFF).contains(&long) =>
+                                self.patch(address, Fixup::Long, long as u32, &target),
+                            Value::Known(long) => self.error(&target, format!("{:X} does not fit in 16 bits", long)),
+                            Value::Forward(name) => {
+                                self.reference(address, Fixup::Long, &name, &target);
+                                Ok(())
+                            }
+                        }
+                    }
+                    _ => {
+                        let value = self.value(&source)?;
+                        self.emit_address(Opcode::LoadI { nnn: 0 }, value, &source)
+                    }
+                }
+            }
+            _ => self.error(&operator, format!("expected := or +=, found {}", operator.text)),
+        }
+    }
+
+    fn assignment(&mut self, token: &Token) -> Result<(), AssembleError> {
+        let x = self.register_named(&token.text).unwrap();
+        let operator = self.next()?;
+        let source = self.next()?;
+        let y = self.register_named(&source.text);
+        let opcode = match (operator.text.as_str(), source.text.as_str(), y) {
+            (":=", _, Some(y)) => Opcode::Move { x, y },
+            (":=", "key", None) => Opcode::WaitForKey { x },
+            (":=", "delay", None) => Opcode::ReadDelayTimer { x },
+            (":=", "random", None) => Opcode::Random { x, nn: self.byte_value()? },
+            (":=", _, None) => Opcode::Load { x, nn: self.source_byte(&source)? },
+            ("+=", _, Some(y)) => Opcode::AddRegisters { x, y },
+            ("+=", _, None) => Opcode::Add { x, nn: self.source_byte(&source)? },
+            ("-=", _, Some(y)) => Opcode::Subtract { x, y },
+            ("-=", _, None) => Opcode::Add { x, nn: self.source_byte(&source)?.wrapping_neg() },
+            ("=-", _, Some(y)) => Opcode::SubtractReversed { x, y },
+            ("|=", _, Some(y)) => Opcode::Or { x, y },
+            ("&=", _, Some(y)) => Opcode::And { x, y },
+            ("^=", _, Some(y)) => Opcode::Xor { x, y },
+            (">>=", _, Some(y)) => Opcode::ShiftRight { x, y },
+            ("<<=", _, Some(y)) => Opcode::ShiftLeft { x, y },
+            _ => return self.error(&operator, format!("cannot use {} with {}", operator.text, source.text)),
+        };
+        self.emit(opcode, token)
+    }
+
+    fn source_byte(&self, source: &Token) -> Result<u8, AssembleError> {
+        match self.value(source)? {
+            Value::Known(value) => self.byte(value, source),
+            Value::Forward(name) => self.error(source, format!("undefined name {}", name)),
+        }
+    }
+
+    // `if CONDITION then STATEMENT` skips the statement unless the
+    // condition holds. `if CONDITION begin ... else ... end` jumps.
+    fn conditional(&mut self, token: &Token) -> Result<(), AssembleError> {
+        let (prelude, test, outcome) = self.condition()?;
+        for opcode in prelude {
+            self.emit(opcode, token)?;
+        }
+        let keyword = self.next()?;
+        match keyword.text.as_str() {
+            "then" => {
+                // The statement the skip is for has to follow.
+                if self.peek().is_none() {
+                    return self.error(&keyword, "unexpected end of source".to_string());
+                }
+                self.emit(skip_when(test, !outcome), token)
+            }
+            "begin" => {
+                self.emit(skip_when(test, outcome), token)?;
+                self.branches.push(self.here);
+                self.emit(Opcode::Jump { nnn: 0 }, token)
+            }
+            _ => self.error(&keyword, format!("expected then or begin, found {}", keyword.text)),
+        }
+    }
+
+    // Reads a condition, returning the instructions that work it out, the
+    // test they leave and the outcome of the test that means it holds.
+    // Ordering comparisons subtract into compare-temp (VF) and test the
+    // borrow.
+    fn condition(&mut self) -> Result<(Vec<Opcode>, Test, bool), AssembleError> {
+        let x = self.register()?;
+        let operator = self.next()?;
+        match operator.text.as_str() {
+            "key" => return Ok((Vec::new(), Test::Key(x), true)),
+            "-key" => return Ok((Vec::new(), Test::Key(x), false)),
+            _ => {}
+        }
+        let source = self.next()?;
+        let y = self.register_named(&source.text);
+        let temp = self.aliases["compare-temp"];
+        let condition = match (operator.text.as_str(), y) {
+            ("==", Some(y)) => (Vec::new(), Test::Register(x, y), true),
+            ("!=", Some(y)) => (Vec::new(), Test::Register(x, y), false),
+            ("==", None) => (Vec::new(), Test::Byte(x, self.source_byte(&source)?), true),
+            ("!=", None) => (Vec::new(), Test::Byte(x, self.source_byte(&source)?), false),
+            (ordering @ "<", _) | (ordering @ ">=", _) | (ordering @ ">", _) | (ordering @ "<=", _) => {
+                // compare-temp ends up as the no borrow flag of X - Y, for
+                // < and >=, or of Y - X, for > and <=. It is 0 exactly when
+                // the strict comparison holds.
+                let below = ordering == "<" || ordering == ">=";
+                let prelude = match (y, below) {
+                    (Some(y), true) => vec![Opcode::Move { x: temp, y: x }, Opcode::Subtract { x: temp, y }],
+                    (Some(y), false) => vec![Opcode::Move { x: temp, y: x }, Opcode::SubtractReversed { x: temp, y }],
+                    (None, true) => vec![Opcode::Load { x: temp, nn: self.source_byte(&source)? },
+                                         Opcode::SubtractReversed { x: temp, y: x }],
+                    (None, false) => vec![Opcode::Load { x: temp, nn: self.source_byte(&source)? },
+                                          Opcode::Subtract { x: temp, y: x }],
+                };
+                let strict = ordering == "<" || ordering == ">";
+                (prelude, Test::Byte(temp, 0), strict)
+            }
+            _ => return self.error(&operator, format!("unknown comparison {}", operator.text)),
+        };
+        Ok(condition)
+    }
+
+    // `{ ... }`, worked out right to left: `1 + 2 * 3` is 7, but
+    // `2 * 3 + 1` is 8.
+    fn calc(&mut self) -> Result<f64, AssembleError> {
+        self.expect("{")?;
+        let value = self.calc_expression()?;
+        self.expect("}")?;
+        Ok(value)
+    }
+
+    fn calc_expression(&mut self) -> Result<f64, AssembleError> {
+        let left = self.calc_term()?;
+        let operator = match self.peek() {
+            Some(text) if BINARY_OPERATORS.contains(&text) => self.next()?,
+            _ => return Ok(left),
+        };
+        let right = self.calc_expression()?;
+        let value = match operator.text.as_str() {
+            "+" => left + right,
+            "-" => left - right,
+            "*" => left * right,
+            "/" => left / right,
+            "%" => left % right,
+            "&" => (left as i64 & right as i64) as f64,
+            "|" => (left as i64 | right as i64) as f64,
+            "^" => (left as i64 ^ right as i64) as f64,
+            "<<" | ">>" => {
+                let shifted = u32::try_from(right as i64).ok().and_then(|amount| match operator.text.as_str() {
+                    "<<" => (left as i64).checked_shl(amount),
+                    _ => (left as i64).checked_shr(amount),
+                });
+                match shifted {
+                    Some(shifted) => shifted as f64,
+                    None => return self.error(&operator, format!("cannot shift by {}", right)),
+                }
+            }
+            "pow" => left.powf(right),
+            "min" => left.min(right),
+            "max" => left.max(right),
+            "<" => (left < right) as i64 as f64,
+            ">" => (left > right) as i64 as f64,
+            "<=" => (left <= right) as i64 as f64,
+            ">=" => (left >= right) as i64 as f64,
+            "==" => (left == right) as i64 as f64,
+            _ => (left != right) as i64 as f64,
+        };
+        Ok(value)
+    }
+
+    fn calc_term(&mut self) -> Result<f64, AssembleError> {
+        let token = self.next()?;
+        let value = match token.text.as_str() {
+            "(" => {
+                let value = self.calc_expression()?;
+                self.expect(")")?;
+                value
+            }
+            "-" => -self.calc_term()?,
+            "~" => !(self.calc_term()? as i64) as f64,
+            "!" => (self.calc_term()? == 0.0) as i64 as f64,
+            "floor" => self.calc_term()?.floor(),
+            "ceil" => self.calc_term()?.ceil(),
+            "abs" => self.calc_term()?.abs(),
+            "sqrt" => self.calc_term()?.sqrt(),
+            "sin" => self.calc_term()?.sin(),
+            "cos" => self.calc_term()?.cos(),
+            "log" => self.calc_term()?.ln(),
+            "exp" => self.calc_term()?.exp(),
+            // The byte already compiled at an address.
+            "@" => {
+                let address = self.calc_term()? as i64;
+                let offset = address - START as i64;
+                if offset < 0 || offset as usize >= self.rom.len() {
+                    return self.error(&token, format!("nothing compiled at {:X}", address));
+                }
+                self.rom[offset as usize] as f64
+            }
+            "HERE" => self.here as f64,
+            "PI" => consts::PI,
+            "E" => consts::E,
+            _ => match self.constants.get(&token.text) {
+                Some(&value) => value,
+                None => match self.value(&token)? {
+                    Value::Known(value) => value as f64,
+                    Value::Forward(name) => return self.error(&token, format!("undefined name {}", name)),
+                },
+            },
+        };
+        Ok(value)
+    }
+}
+
+const BINARY_OPERATORS: &[&str] = &[
+    "+", "-", "*", "/", "%", "&", "|", "^", "<<", ">>", "pow", "min", "max", "<", ">", "<=", ">=", "==", "!=",
+];
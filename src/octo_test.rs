@@ -0,0 +1,133 @@
+#[cfg(test)]
+mod octo_test {
+
+    use assembler::AssembleError;
+    use chip8::Chip8;
+    use octo::compile;
+    use platform::{ Platform, Variant };
+
+    fn rom(source: &str) -> Vec<u8> {
+        compile(source, Variant::Chip8).unwrap_or_else(|error| panic!("{}", error)).rom
+    }
+
+    // Runs `source` for `steps` instructions and returns V0 to VF.
+    fn run(source: &str, steps: usize) -> Vec<u8> {
+        let mut chip8 = Chip8::with_platform(Platform::chip8());
+        chip8.load_rom(&rom(source)).unwrap();
+        for _ in 0..steps {
+            chip8.step().unwrap();
+        }
+        (0..16).map(|x| chip8.cpu().read_vx(x)).collect()
+    }
+
+    #[test]
+    fn compiles_statements_from_main() {
+        assert_eq!(rom(": main  v0 := 5  v0 += 3  v1 := v0  i := 0x300  sprite v0 v1 5  loop again"),
+                   vec![0x60, 0x05, 0x70, 0x03, 0x81, 0x00, 0xA3, 0x00, 0xD0, 0x15, 0x12, 0x0A]);
+    }
+
+    #[test]
+    fn jumps_to_main_when_it_does_not_come_first() {
+        assert_eq!(rom(": ball 0b11000000 0xC0\n: main i := ball return"),
+                   vec![0x12, 0x04, 0xC0, 0xC0, 0xA2, 0x02, 0x00, 0xEE]);
+    }
+
+    #[test]
+    fn labels_are_called_by_name_before_they_are_defined() {
+        assert_eq!(rom(": main draw jump main : draw ;"), vec![0x22, 0x04, 0x12, 0x00, 0x00, 0xEE]);
+    }
+
+    #[test]
+    fn if_then_skips_the_next_statement_unless_the_condition_holds() {
+        assert_eq!(rom(": main if v0 == 3 then v1 := 1  if v0 != v2 then v1 := 2  if v3 key then ;"),
+                   vec![0x40, 0x03, 0x61, 0x01, 0x50, 0x20, 0x61, 0x02, 0xE3, 0xA1, 0x00, 0xEE]);
+    }
+
+    #[test]
+    fn if_begin_else_end_jumps_around_the_branches() {
+        assert_eq!(rom(": main if v0 == 1 begin v1 := 1 else v1 := 2 end ;"),
+                   vec![0x30, 0x01, 0x12, 0x08, 0x61, 0x01, 0x12, 0x0A, 0x61, 0x02, 0x00, 0xEE]);
+    }
+
+    #[test]
+    fn while_leaves_the_loop_once_its_condition_fails() {
+        assert_eq!(run(": main loop while v0 != 5 v0 += 1 again v1 := 9 loop again", 20)[..2], [5, 9]);
+    }
+
+    #[test]
+    fn ordering_comparisons_hold_when_they_should() {
+        let source = ": main v0 := 3 v1 := 7
+                      if v0 < v1 then v2 := 1
+                      if v1 > v0 then v3 := 1
+                      if v0 >= v1 then v4 := 1
+                      if v0 <= 3 then v5 := 1
+                      if v1 > 7 then v6 := 1
+                      loop again";
+        let registers = run(source, 20);
+        assert_eq!(registers[2..7], [1, 1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn macros_constants_and_calc_expand_at_compile_time() {
+        let source = ":const SPEED 2
+                      :calc DOUBLE { 2 * 3 + 1 }
+                      :alias x v4
+                      :macro bump register amount { register += amount }
+                      : main bump x SPEED  x := DOUBLE";
+        assert_eq!(rom(source), vec![0x74, 0x02, 0x64, 0x08]);
+    }
+
+    #[test]
+    fn calc_rejects_shifts_out_of_range() {
+        assert_eq!(compile(":calc X { 1 << 3 } : main v0 := X", Variant::Chip8).unwrap().rom, vec![0x60, 0x08]);
+        for source in &[":calc X { 1 << 64 }", ":calc X { 1 >> -1 }"] {
+            let error = compile(source, Variant::Chip8).unwrap_err();
+            assert_eq!((error.line, error.column), (1, 13), "{}", error);
+            assert!(error.message.starts_with("cannot shift by"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn unpack_next_and_org_place_addresses() {
+        let source = ": main :unpack 0xA data  :next target v2 := 0  :org 0x300 : data";
+        let assembly = compile(source, Variant::Chip8).unwrap();
+
+        assert_eq!(assembly.rom[..6], [0x60, 0xA3, 0x61, 0x00, 0x62, 0x00]);
+        assert_eq!(assembly.symbols["target"], 0x205);
+        assert_eq!(assembly.symbols["data"], 0x300);
+    }
+
+    #[test]
+    fn xo_chip_instructions_need_xo_chip() {
+        let source = ": main i := long data  save v1 - v3  plane 3 : data";
+        assert_eq!(compile(source, Variant::XoChip).unwrap().rom,
+                   vec![0xF0, 0x00, 0x02, 0x08, 0x51, 0x32, 0xF3, 0x01]);
+        assert_eq!(compile(source, Variant::Chip8), Err(AssembleError {
+            file: "<source>".to_string(), line: 1, column: 13, message: "long is not available on Chip8".to_string(),
+        }));
+    }
+
+    #[test]
+    fn undefined_labels_are_reported_where_they_are_used() {
+        let error = compile(": main\n  jump nowhere", Variant::Chip8).unwrap_err();
+
+        assert_eq!((error.line, error.column, error.message.as_str()), (2, 8, "undefined label nowhere"));
+    }
+
+    #[test]
+    fn code_and_data_must_fit_in_memory() {
+        let error = compile(": main :org 0xFFFF 1", Variant::Chip8).unwrap_err();
+        assert_eq!(error.message, ":org FFFF is outside the program");
+        let error = compile(": main :org 0xFFF 1 2", Variant::Chip8).unwrap_err();
+        assert_eq!(error.message, "1000 is past the end of memory");
+
+        assert_eq!(compile(": main :org 0xFFFF 1", Variant::XoChip).unwrap().rom.len(), 0xFE00);
+    }
+
+    #[test]
+    fn then_needs_a_statement_after_it() {
+        let error = compile(": main if v0 == 1 then", Variant::Chip8).unwrap_err();
+
+        assert_eq!((error.column, error.message.as_str()), (19, "unexpected end of source"));
+    }
+}
@@ -0,0 +1,237 @@
+use quirks::Quirks;
+use instruction::Opcode;
+use ram::{MEMORY_SIZE, XO_MEMORY_SIZE, MEGA_MEMORY_SIZE, FONT_ADDRESS};
+use cpu::START;
+use stack::STACK_DEPTH;
+use font::Font;
+
+pub const CHIP8X_START: u16 = 0x300;
+
+// The ETI-660 monitor and interpreter take up memory up to 0x5FF.
+pub const ETI660_START: u16 = 0x600;
+
+// The VIP interpreter keeps 12 return addresses at 0xEA0, below its
+// variables and the display page.
+pub const VIP_STACK_DEPTH: usize = 12;
+pub const VIP_STACK_ADDRESS: u16 = 0xEA0;
+
+// CHIP-48 and SUPER-CHIP keep 16 return addresses in the calculator's
+// own memory, outside the 4 KB the program sees, so all of it up from
+// 0x200 is free for programs.
+pub const HP48_STACK_DEPTH: usize = 16;
+
+// The usual DREAM 6800 with its RAM expanded to 2 KB.
+pub const DREAM6800_MEMORY_SIZE: usize = 0x800;
+
+// Instruction set understood by the CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Chip8,
+    // CHIP-8 with the 64x64 Hi-Res interpreter patch: 0230 clears the
+    // screen and the opening 1260 enters two-page mode.
+    HiRes,
+    // CHIP-8X, for the VP-590 colour board and VP-580 second keypad.
+    Chip8X,
+    SuperChip,
+    XoChip,
+    // SUPER-CHIP plus the MegaChip8 colour display and sampled sound.
+    MegaChip,
+}
+
+impl Variant {
+    // SUPER-CHIP and the instruction sets built on it.
+    pub fn extends_super_chip(&self) -> bool {
+        matches!(*self, Variant::SuperChip | Variant::XoChip | Variant::MegaChip)
+    }
+
+    // Whether `opcode` exists in this instruction set. XO-CHIP and
+    // MegaChip8 include all of SUPER-CHIP.
+    pub fn supports(&self, opcode: &Opcode) -> bool {
+        match *opcode {
+            Opcode::ScrollUp { .. } |
+            Opcode::SaveRange { .. } |
+            Opcode::LoadRange { .. } |
+            Opcode::LoadLongI |
+            Opcode::SelectPlanes { .. } |
+            Opcode::LoadAudio |
+            Opcode::SetPitch { .. } => *self == Variant::XoChip,
+            Opcode::CycleBackground |
+            Opcode::AddNibbles { .. } |
+            Opcode::ColourZones { .. } |
+            Opcode::ColourRows { .. } |
+            Opcode::SkipIfSecondKeyPressed { .. } |
+            Opcode::SkipIfSecondKeyNotPressed { .. } |
+            Opcode::OutputPort { .. } |
+            Opcode::InputPort { .. } => *self == Variant::Chip8X,
+            Opcode::MegaOff |
+            Opcode::MegaOn |
+            Opcode::LoadWideI { .. } |
+            Opcode::LoadPalette { .. } |
+            Opcode::SpriteWidth { .. } |
+            Opcode::SpriteHeight { .. } |
+            Opcode::ScreenAlpha { .. } |
+            Opcode::PlaySample { .. } |
+            Opcode::StopSample |
+            Opcode::SetBlendMode { .. } |
+            Opcode::CollisionColour { .. } => *self == Variant::MegaChip,
+            Opcode::ScrollDown { .. } |
+            Opcode::ScrollRight |
+            Opcode::ScrollLeft |
+            Opcode::Exit |
+            Opcode::LowResolution |
+            Opcode::HighResolution |
+            Opcode::LoadBigFont { .. } |
+            Opcode::SaveFlags { .. } |
+            Opcode::LoadFlags { .. } => self.extends_super_chip(),
+            _ => true,
+        }
+    }
+}
+
+// A machine the emulator can pretend to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Platform {
+    pub variant: Variant,
+    pub quirks: Quirks,
+    // Size of the address space, a power of two.
+    pub memory_size: usize,
+    // Where the ROM is copied to and execution starts.
+    pub load_address: u16,
+    // Nested calls allowed before 2NNN overflows.
+    pub stack_depth: usize,
+    // Where the interpreter keeps its stack, when it is in program
+    // memory. Programs must end below it.
+    pub stack_address: Option<u16>,
+    pub font: Font,
+    // The small font goes here, the big one right after it.
+    pub font_address: u16,
+}
+
+impl Default for Platform {
+    fn default() -> Platform {
+        Platform::chip8()
+    }
+}
+
+impl Platform {
+    // Original CHIP-8 behaviour with the whole 4 KB free for programs.
+    pub fn chip8() -> Platform {
+        Platform {
+            variant: Variant::Chip8,
+            quirks: Quirks::cosmac_vip(),
+            memory_size: MEMORY_SIZE,
+            load_address: START,
+            stack_depth: STACK_DEPTH,
+            stack_address: None,
+            font: Font::default(),
+            font_address: FONT_ADDRESS,
+        }
+    }
+
+    // CHIP-8 on a 4 KB COSMAC VIP, stack and font and all.
+    pub fn cosmac_vip() -> Platform {
+        Platform {
+            stack_depth: VIP_STACK_DEPTH,
+            stack_address: Some(VIP_STACK_ADDRESS),
+            font: Font::Vip,
+            ..Platform::chip8()
+        }
+    }
+
+    // CHIP-8 on the ETI-660, where programs start at 0x600, with its font.
+    pub fn eti660() -> Platform {
+        Platform {
+            load_address: ETI660_START,
+            font: Font::Eti660,
+            ..Platform::chip8()
+        }
+    }
+
+    // CHIPOS on the DREAM 6800, with its own font.
+    pub fn dream6800() -> Platform {
+        Platform {
+            memory_size: DREAM6800_MEMORY_SIZE,
+            font: Font::Dream6800,
+            ..Platform::chip8()
+        }
+    }
+
+    // CHIP-48 on the HP48 calculators.
+    pub fn hp48() -> Platform {
+        Platform {
+            variant: Variant::Chip8,
+            quirks: Quirks::chip48(),
+            memory_size: MEMORY_SIZE,
+            load_address: START,
+            stack_depth: HP48_STACK_DEPTH,
+            stack_address: None,
+            font: Font::SuperChip,
+            font_address: FONT_ADDRESS,
+        }
+    }
+
+    // Hi-Res CHIP-8 on the COSMAC VIP, with a 64x64 screen.
+    pub fn chip8_hires() -> Platform {
+        Platform {
+            variant: Variant::HiRes,
+            ..Platform::chip8()
+        }
+    }
+
+    // CHIP-8X on a COSMAC VIP with colour board. Its larger interpreter
+    // pushes programs up to 0x300.
+    pub fn chip8x() -> Platform {
+        Platform {
+            variant: Variant::Chip8X,
+            load_address: CHIP8X_START,
+            ..Platform::chip8()
+        }
+    }
+
+    // SUPER-CHIP 1.1 on the HP48.
+    pub fn super_chip() -> Platform {
+        Platform {
+            variant: Variant::SuperChip,
+            quirks: Quirks::super_chip(),
+            ..Platform::hp48()
+        }
+    }
+
+    // MegaChip8, with its 24 bit I.
+    pub fn megachip() -> Platform {
+        Platform {
+            variant: Variant::MegaChip,
+            memory_size: MEGA_MEMORY_SIZE,
+            ..Platform::super_chip()
+        }
+    }
+
+    // XO-CHIP, as implemented by Octo.
+    pub fn xo_chip() -> Platform {
+        Platform {
+            variant: Variant::XoChip,
+            quirks: Quirks::xo_chip(),
+            memory_size: XO_MEMORY_SIZE,
+            font: Font::Octo,
+            ..Platform::chip8()
+        }
+    }
+
+    // The profile called `name`, for command lines.
+    pub fn named(name: &str) -> Option<Platform> {
+        let platform = match name {
+            "chip8" => Platform::chip8(),
+            "vip" => Platform::cosmac_vip(),
+            "eti660" => Platform::eti660(),
+            "dream6800" => Platform::dream6800(),
+            "hp48" => Platform::hp48(),
+            "hires" => Platform::chip8_hires(),
+            "chip8x" => Platform::chip8x(),
+            "schip" => Platform::super_chip(),
+            "megachip" => Platform::megachip(),
+            "xochip" => Platform::xo_chip(),
+            _ => return None,
+        };
+        Some(platform)
+    }
+}
@@ -0,0 +1,88 @@
+#[cfg(test)]
+mod platform_test {
+
+    use chip8::Chip8;
+    use platform::{ Platform, Variant };
+    use quirks::Quirks;
+    use instruction::Opcode;
+    use font::Font;
+
+    #[test]
+    fn default_is_chip8_on_the_cosmac_vip() {
+        assert_eq!(Platform::default(), Platform::chip8());
+        assert_eq!(Platform::chip8().quirks, Quirks::cosmac_vip());
+        assert_eq!(Platform::super_chip().quirks, Quirks::super_chip());
+    }
+
+    #[test]
+    fn only_super_chip_supports_its_extensions() {
+        assert!(Variant::Chip8.supports(&Opcode::ClearScreen));
+        assert!(Variant::SuperChip.supports(&Opcode::ClearScreen));
+        assert!(!Variant::Chip8.supports(&Opcode::HighResolution));
+        assert!(Variant::SuperChip.supports(&Opcode::HighResolution));
+        assert!(!Variant::Chip8.supports(&Opcode::SaveFlags { x: 0 }));
+    }
+
+    #[test]
+    fn xo_chip_extends_super_chip_with_64k_of_memory() {
+        let xo = Platform::xo_chip();
+
+        assert_eq!(xo.memory_size, 0x10000);
+        assert!(Variant::XoChip.supports(&Opcode::HighResolution));
+        assert!(Variant::XoChip.supports(&Opcode::LoadLongI));
+        assert!(!Variant::SuperChip.supports(&Opcode::LoadLongI));
+    }
+
+    #[test]
+    fn hires_is_chip8_with_the_same_load_address() {
+        let hires = Platform::chip8_hires();
+
+        assert_eq!(hires.load_address, Platform::chip8().load_address);
+        assert_eq!(hires.quirks, Quirks::cosmac_vip());
+        assert!(!Variant::HiRes.supports(&Opcode::HighResolution));
+    }
+
+    #[test]
+    fn megachip_is_super_chip_with_16mb_of_memory() {
+        let mega = Platform::megachip();
+
+        assert_eq!(mega.memory_size, 0x1000000);
+        assert_eq!(mega.quirks, Quirks::super_chip());
+        assert!(Variant::MegaChip.supports(&Opcode::HighResolution));
+        assert!(Variant::MegaChip.supports(&Opcode::MegaOn));
+        assert!(!Variant::SuperChip.supports(&Opcode::MegaOn));
+    }
+
+    #[test]
+    fn memory_map_profiles_differ_where_the_machines_do() {
+        let vip = Platform::cosmac_vip();
+        assert_eq!(vip.stack_depth, 12);
+        assert_eq!(vip.stack_address, Some(0xEA0));
+
+        assert_eq!(Platform::eti660().load_address, 0x600);
+        assert_eq!(Platform::dream6800().font, Font::Dream6800);
+        assert_eq!(Platform::hp48().quirks, Quirks::chip48());
+        assert_eq!(Platform::super_chip().stack_depth, 16);
+        assert_eq!(Platform::chip8().stack_address, None);
+    }
+
+    #[test]
+    fn hp48_keeps_its_stack_outside_program_memory() {
+        let hp48 = Platform::hp48();
+        assert_eq!(hp48.stack_depth, 16);
+        assert_eq!(hp48.stack_address, None);
+        assert_eq!((hp48.memory_size, hp48.load_address), (4096, 0x200));
+        assert_eq!(Chip8::with_platform(hp48).max_rom_size(), 4096 - 0x200);
+
+        let super_chip = Platform::super_chip();
+        assert_eq!((super_chip.stack_depth, super_chip.stack_address), (hp48.stack_depth, hp48.stack_address));
+    }
+
+    #[test]
+    fn profiles_can_be_picked_by_name() {
+        assert_eq!(Platform::named("schip"), Some(Platform::super_chip()));
+        assert_eq!(Platform::named("xochip"), Some(Platform::xo_chip()));
+        assert_eq!(Platform::named("eti660"), Some(Platform::eti660()));
+        assert_eq!(Platform::named("pdp11"), None);
+    }
+}
@@ -0,0 +1,41 @@
+// The COSMAC VIP's byte-wide I/O port, reached by CHIP-8X FXF8 and FXFB.
+// The program writes `output`; the host hands bytes in with `send`.
+#[derive(Clone)]
+pub struct Port {
+    output: u8,
+    input: Option<u8>,
+}
+
+impl Default for Port {
+    fn default() -> Port {
+        Port::new()
+    }
+}
+
+impl Port {
+    pub fn new() -> Port {
+        Port {
+            output: 0,
+            input: None,
+        }
+    }
+
+    // Last byte the program wrote.
+    pub fn output(&self) -> u8 {
+        self.output
+    }
+
+    pub fn write(&mut self, value: u8) {
+        self.output = value;
+    }
+
+    // Makes a byte available to the next FXFB.
+    pub fn send(&mut self, value: u8) {
+        self.input = Some(value);
+    }
+
+    // The byte sent by the host, if any. It can only be read once.
+    pub fn read(&mut self) -> Option<u8> {
+        self.input.take()
+    }
+}
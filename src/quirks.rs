@@ -0,0 +1,84 @@
+// How FX55 and FX65 leave I once they are done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadStoreIncrement {
+    // I ends up past the last register, I + X + 1 (COSMAC VIP, XO-CHIP).
+    XPlusOne,
+    // I ends up on the last register, I + X (CHIP-48).
+    X,
+    // I is left untouched (SUPER-CHIP).
+    None,
+}
+
+// Behaviour that differs between interpreters. Programs written for one
+// platform often break on another unless these match what they expect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    // 8XY6 and 8XYE shift VY into VX. Otherwise VX is shifted in place.
+    pub shift_uses_vy: bool,
+    pub load_store_increment: LoadStoreIncrement,
+    // BNNN jumps to XNN + VX instead of NNN + V0.
+    pub jump_uses_vx: bool,
+    // 8XY1, 8XY2 and 8XY3 set VF to 0.
+    pub logic_resets_vf: bool,
+    // Sprites are cut at the screen edges. Otherwise they wrap around.
+    pub clip_sprites: bool,
+    // DXYN waits for the next vertical blank, so at most one sprite is
+    // drawn per frame.
+    pub display_wait: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Quirks {
+        Quirks::cosmac_vip()
+    }
+}
+
+impl Quirks {
+    // The original interpreter on the RCA COSMAC VIP.
+    pub fn cosmac_vip() -> Quirks {
+        Quirks {
+            shift_uses_vy: true,
+            load_store_increment: LoadStoreIncrement::XPlusOne,
+            jump_uses_vx: false,
+            logic_resets_vf: true,
+            clip_sprites: true,
+            display_wait: true,
+        }
+    }
+
+    // CHIP-48 on the HP48 calculators.
+    pub fn chip48() -> Quirks {
+        Quirks {
+            shift_uses_vy: false,
+            load_store_increment: LoadStoreIncrement::X,
+            jump_uses_vx: true,
+            logic_resets_vf: false,
+            clip_sprites: true,
+            display_wait: false,
+        }
+    }
+
+    // SUPER-CHIP 1.1.
+    pub fn super_chip() -> Quirks {
+        Quirks {
+            shift_uses_vy: false,
+            load_store_increment: LoadStoreIncrement::None,
+            jump_uses_vx: true,
+            logic_resets_vf: false,
+            clip_sprites: true,
+            display_wait: false,
+        }
+    }
+
+    // XO-CHIP, as implemented by Octo.
+    pub fn xo_chip() -> Quirks {
+        Quirks {
+            shift_uses_vy: true,
+            load_store_increment: LoadStoreIncrement::XPlusOne,
+            jump_uses_vx: false,
+            logic_resets_vf: false,
+            clip_sprites: false,
+            display_wait: false,
+        }
+    }
+}
@@ -0,0 +1,30 @@
+#[cfg(test)]
+mod quirks_test {
+
+    use quirks::{ Quirks, LoadStoreIncrement };
+
+    #[test]
+    fn default_is_the_cosmac_vip() {
+        assert_eq!(Quirks::default(), Quirks::cosmac_vip());
+    }
+
+    #[test]
+    fn presets_differ_where_the_platforms_do() {
+        let vip = Quirks::cosmac_vip();
+        let chip48 = Quirks::chip48();
+        let schip = Quirks::super_chip();
+        let xo = Quirks::xo_chip();
+
+        assert!(vip.shift_uses_vy && xo.shift_uses_vy);
+        assert!(!chip48.shift_uses_vy && !schip.shift_uses_vy);
+
+        assert_eq!(vip.load_store_increment, LoadStoreIncrement::XPlusOne);
+        assert_eq!(chip48.load_store_increment, LoadStoreIncrement::X);
+        assert_eq!(schip.load_store_increment, LoadStoreIncrement::None);
+
+        assert!(chip48.jump_uses_vx && schip.jump_uses_vx);
+        assert!(vip.logic_resets_vf && !xo.logic_resets_vf);
+        assert!(vip.clip_sprites && !xo.clip_sprites);
+        assert!(vip.display_wait && !schip.display_wait);
+    }
+}
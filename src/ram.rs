@@ -1,47 +1,150 @@
+use std::cmp;
+
+use error::EmulatorError;
+use font::Font;
+use platform::Platform;
+
+// The whole CHIP-8 address space, 0x000 to 0xFFF. Addresses beyond 0xFFF
+// wrap around to the start of memory, as they do on the 4K COSMAC VIP
+// where the upper address lines are not decoded. Machines with less RAM
+// than that have nothing behind the missing addresses.
+pub const MEMORY_SIZE : usize = 4096;
+pub const ADDRESS_MASK : u16 = 0x0FFF;
+
+// XO-CHIP widens the address space to 64 KB.
+pub const XO_MEMORY_SIZE : usize = 0x10000;
+
+// MegaChip8's 24 bit I reaches 16 MB.
+pub const MEGA_MEMORY_SIZE : usize = 0x1000000;
+
+// The interpreter area (0x000 to 0x1FF) is free on modern machines, so the
+// hex font lives there at the same address most emulators use.
+pub const FONT_ADDRESS : u16 = 0x050;
+pub const FONT_SPRITE_SIZE : u16 = 5;
+
+// 8x10 digits, right after the small font.
+pub const BIG_FONT_ADDRESS : u16 = 0x0A0;
+pub const BIG_FONT_SPRITE_SIZE : u16 = 10;
+
+#[derive(Clone)]
 pub struct Ram {
-    main: [u8; 3584],
-    sprites: [[u8; 5]; 16],
+    memory: Vec<u8>,
+    address_mask: u32,
 }
 
-pub const MEMORY_SIZE : usize = 3584;
+impl Default for Ram {
+    fn default() -> Ram {
+        Ram::new()
+    }
+}
 
 impl Ram {
     pub fn new() -> Ram {
-        let mut memory = Ram {
-            main: [0; MEMORY_SIZE],
-            sprites: [[0; 5]; 16],
-        };
+        Ram::with_size(MEMORY_SIZE)
+    }
+
+    // RAM covering only the first `size` bytes of the address space.
+    // It must be large enough to hold the font.
+    pub fn with_size(size: usize) -> Ram {
+        let mut memory = Ram::blank(size, ADDRESS_MASK as u32);
+        memory.load_font(Font::default(), FONT_ADDRESS);
+        memory
+    }
+
+    // RAM filling an address space of `size` bytes, a power of two up to
+    // 16 MB. Addresses wrap around at its end.
+    pub fn with_address_space(size: usize) -> Ram {
+        let mut memory = Ram::with_size(size);
+        memory.address_mask = (size - 1) as u32;
+        memory
+    }
+
+    // The memory map of `platform`: its address space with its font at
+    // its font address. Smaller machines still decode 12 bit addresses,
+    // so those past their RAM are out of bounds rather than wrapping.
+    pub fn for_platform(platform: &Platform) -> Ram {
+        let address_space = cmp::max(platform.memory_size, MEMORY_SIZE);
+        let mut memory = Ram::blank(platform.memory_size, (address_space - 1) as u32);
+        memory.load_font(platform.font, platform.font_address);
+        memory
+    }
+
+    fn blank(size: usize, address_mask: u32) -> Ram {
+        Ram {
+            memory: vec![0; size],
+            address_mask,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.memory.len()
+    }
 
-        memory.load_sprites();
-        return memory;
+    // Bits of an address that select a byte; the rest are ignored.
+    pub fn address_mask(&self) -> u32 {
+        self.address_mask
     }
 
+    pub fn read(&self, address: u16) -> Result<u8, EmulatorError> {
+        self.read_long(address as u32)
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) -> Result<(), EmulatorError> {
+        self.write_long(address as u32, value)
+    }
+
+    // Like `read`, with the wider addresses of a MegaChip8 I.
+    pub fn read_long(&self, address: u32) -> Result<u8, EmulatorError> {
+        let index = self.index(address)?;
+        Ok(self.memory[index])
+    }
+
+    pub fn write_long(&mut self, address: u32, value: u8) -> Result<(), EmulatorError> {
+        let index = self.index(address)?;
+        self.memory[index] = value;
+        Ok(())
+    }
+
+    // Like `read`, for host code that knows the address exists.
+    // Panics on an address outside of RAM.
     pub fn read_bytes(&self, address: u16) -> u8{
-        return self.main[address as usize];
+        self.read(address).unwrap()
     }
 
+    // Like `write`, for host code that knows the address exists.
+    // Panics on an address outside of RAM.
     pub fn write_bytes(&mut self, address: u16, value: u8){
-        self.main[address as usize] = value;
-    }
-
-    fn load_sprites(&mut self) {
-        self.sprites = [
-            [0xF0, 0x90, 0x90, 0x90, 0xF0],
-            [0x20, 0x60, 0x20, 0x20, 0x70],
-            [0xF0, 0x10, 0xF0, 0x80, 0xF0],
-            [0xF0, 0x10, 0xF0, 0x10, 0xF0],
-            [0x90, 0x90, 0xF0, 0x10, 0x10],
-            [0xF0, 0x80, 0xF0, 0x10, 0xF0],
-            [0xF0, 0x80, 0xF0, 0x90, 0xF0],
-            [0xF0, 0x10, 0x20, 0x40, 0x40],
-            [0xF0, 0x90, 0xF0, 0x90, 0xF0],
-            [0xF0, 0x90, 0xF0, 0x10, 0xF0],
-            [0xF0, 0x90, 0xF0, 0x90, 0x90],
-            [0xE0, 0x90, 0xE0, 0x90, 0xE0],
-            [0xF0, 0x80, 0x80, 0x80, 0xF0],
-            [0xE0, 0x90, 0x90, 0x90, 0xE0],
-            [0xF0, 0x80, 0xF0, 0x80, 0xF0],
-            [0xF0, 0x80, 0xF0, 0x80, 0x80],
-        ]
-    }
-}
\ No newline at end of file
+        self.write(address, value).unwrap()
+    }
+
+    // Address of the 4x5 sprite for the hex digit in the low nibble.
+    pub fn font_address(digit: u8) -> u16 {
+        FONT_ADDRESS + (digit & 0x0F) as u16 * FONT_SPRITE_SIZE
+    }
+
+    // Address of the 8x10 sprite for the decimal digit in the low nibble.
+    pub fn big_font_address(digit: u8) -> u16 {
+        BIG_FONT_ADDRESS + (digit & 0x0F) as u16 * BIG_FONT_SPRITE_SIZE
+    }
+
+    fn index(&self, address: u32) -> Result<usize, EmulatorError> {
+        let index = (address & self.address_mask) as usize;
+        if index < self.memory.len() {
+            Ok(index)
+        } else {
+            Err(EmulatorError::MemoryOutOfBounds { address: index })
+        }
+    }
+
+    // Copies the small glyphs of `font` to `address` and its big ones,
+    // if it has any, right after them.
+    pub fn load_font(&mut self, font: Font, address: u16) {
+        self.load_glyphs(&font.glyphs(), address);
+    }
+
+    // Same, for a font binary laid out like the built-in ones.
+    pub fn load_glyphs(&mut self, glyphs: &[u8], address: u16) {
+        let start = address as usize;
+        self.memory[start..start + glyphs.len()].copy_from_slice(glyphs);
+    }
+}
@@ -1,12 +1,20 @@
 #[cfg(test)]
 mod ram_test {
 
-    use ram::{ Ram, MEMORY_SIZE };
+    use ram::{ Ram, MEMORY_SIZE, FONT_ADDRESS, FONT_SPRITE_SIZE, BIG_FONT_ADDRESS, BIG_FONT_SPRITE_SIZE, XO_MEMORY_SIZE };
+    use error::EmulatorError;
+
+    // Both the small font and the SUPER-CHIP big font that follows it.
+    fn is_font(address: usize) -> bool {
+        let start = FONT_ADDRESS as usize;
+        let end = BIG_FONT_ADDRESS as usize + 10 * BIG_FONT_SPRITE_SIZE as usize;
+        address >= start && address < end
+    }
 
     #[test]
     fn starts_with_empty_memory() {
         let ram = Ram::new();
-        for (_, address) in (0..MEMORY_SIZE).enumerate() {
+        for address in (0..MEMORY_SIZE).filter(|&address| !is_font(address)) {
             let bytes = ram.read_bytes(address as u16);
             assert_eq!(bytes, 0 as u8);
         }
@@ -19,9 +27,52 @@ mod ram_test {
         let bytes = ram.read_bytes(0);
         assert_eq!(bytes, 1);
 
-        for (_, address) in (1..MEMORY_SIZE).enumerate() {
+        for address in (1..MEMORY_SIZE).filter(|&address| !is_font(address)) {
             let bytes = ram.read_bytes(address as u16);
             assert_eq!(bytes, 0 as u8);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn font_is_mapped_into_memory() {
+        let ram = Ram::new();
+        let zero = Ram::font_address(0x0);
+        let f = Ram::font_address(0xF);
+
+        assert_eq!(zero, FONT_ADDRESS);
+        assert_eq!(f, FONT_ADDRESS + 15 * FONT_SPRITE_SIZE);
+        assert_eq!(ram.read_bytes(zero), 0xF0);
+        assert_eq!(ram.read_bytes(zero + 1), 0x90);
+        assert_eq!(ram.read_bytes(f + 4), 0x80);
+    }
+
+    #[test]
+    fn addresses_beyond_0xfff_wrap_around() {
+        let mut ram = Ram::new();
+        ram.write_bytes(0x1002, 0xAB);
+
+        assert_eq!(ram.read_bytes(0x002), 0xAB);
+        assert_eq!(ram.read_bytes(0xF002), 0xAB);
+    }
+
+    #[test]
+    fn smaller_ram_rejects_addresses_past_its_end() {
+        let mut ram = Ram::with_size(0x800);
+
+        assert_eq!(ram.size(), 0x800);
+        assert_eq!(ram.write(0x7FF, 0x12), Ok(()));
+        assert_eq!(ram.read(0x7FF), Ok(0x12));
+        assert_eq!(ram.read(0x800), Err(EmulatorError::MemoryOutOfBounds { address: 0x800 }));
+        assert_eq!(ram.write(0xFFF, 0x12), Err(EmulatorError::MemoryOutOfBounds { address: 0xFFF }));
+    }
+
+    #[test]
+    fn xo_chip_address_space_covers_64k() {
+        let mut ram = Ram::with_address_space(XO_MEMORY_SIZE);
+        ram.write_bytes(0xFFFF, 0x42);
+
+        assert_eq!(ram.address_mask(), 0xFFFF);
+        assert_eq!(ram.read_bytes(0xFFFF), 0x42);
+        assert_eq!(ram.read_bytes(0x0FFF), 0x00);
+    }
+}
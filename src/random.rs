@@ -0,0 +1,110 @@
+use rand;
+
+// Where CXNN gets its random bytes. The state must capture everything
+// the next bytes depend on, so a snapshot can replay them.
+pub trait RandomSource {
+    // The next byte. Any value from 0 to 255 can come up.
+    fn next_byte(&mut self) -> u8;
+
+    // Called at the end of every frame, for generators that advance with
+    // time as well as with use.
+    fn tick(&mut self) {}
+
+    fn state(&self) -> u64;
+    fn set_state(&mut self, state: u64);
+}
+
+// xorshift64*, which is quick and good enough for games. The same seed
+// always gives the same bytes.
+pub struct SeededRandom {
+    state: u64,
+}
+
+// xorshift gets stuck on 0, so that seed is swapped for this one.
+const ZERO_SEED_REPLACEMENT: u64 = 0x9E37_79B9_7F4A_7C15;
+
+impl Default for SeededRandom {
+    fn default() -> SeededRandom {
+        SeededRandom::new()
+    }
+}
+
+impl SeededRandom {
+    // Seeded from the operating system, so every run differs.
+    pub fn new() -> SeededRandom {
+        SeededRandom::with_seed(rand::random())
+    }
+
+    pub fn with_seed(seed: u64) -> SeededRandom {
+        let mut random = SeededRandom { state: 0 };
+        random.set_state(seed);
+        random
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn next_byte(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+
+    fn state(&self) -> u64 {
+        self.state
+    }
+
+    fn set_state(&mut self, state: u64) {
+        self.state = if state == 0 { ZERO_SEED_REPLACEMENT } else { state };
+    }
+}
+
+// The COSMAC VIP interpreter's generator. It keeps a 16 bit value in R9:
+// the low byte counts up every call and every frame, and picks a byte of
+// the interpreter's second page which is added into the high byte. The
+// high byte, rotated right through the carry, is the result. As on the
+// real machine, the bytes depend on the interpreter image and on timing.
+pub struct VipRandom {
+    table: Vec<u8>,
+    low: u8,
+    high: u8,
+}
+
+// The page of the interpreter the bytes are picked from.
+const VIP_TABLE_ADDRESS: usize = 0x100;
+const VIP_TABLE_SIZE: usize = 0x100;
+
+impl VipRandom {
+    // `interpreter` is the image loaded at address 0. Bytes it lacks
+    // count as 0.
+    pub fn new(interpreter: &[u8]) -> VipRandom {
+        let mut table = vec![0; VIP_TABLE_SIZE];
+        for (entry, byte) in table.iter_mut().zip(interpreter.iter().skip(VIP_TABLE_ADDRESS)) {
+            *entry = *byte;
+        }
+        VipRandom { table, low: 0, high: 0 }
+    }
+}
+
+impl RandomSource for VipRandom {
+    fn next_byte(&mut self) -> u8 {
+        self.low = self.low.wrapping_add(1);
+        let sum = self.high as u16 + self.table[self.low as usize] as u16;
+        let carry = (sum >> 8) as u8;
+        self.high = (sum as u8) >> 1 | carry << 7;
+        self.high
+    }
+
+    fn tick(&mut self) {
+        self.low = self.low.wrapping_add(1);
+    }
+
+    fn state(&self) -> u64 {
+        (self.high as u64) << 8 | self.low as u64
+    }
+
+    fn set_state(&mut self, state: u64) {
+        self.low = state as u8;
+        self.high = (state >> 8) as u8;
+    }
+}
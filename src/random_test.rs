@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod random_test {
+
+    use random::{ RandomSource, SeededRandom, VipRandom };
+
+    #[test]
+    fn same_seed_gives_same_bytes() {
+        let mut first = SeededRandom::with_seed(42);
+        let mut second = SeededRandom::with_seed(42);
+
+        for _ in 0..100 {
+            assert_eq!(first.next_byte(), second.next_byte());
+        }
+    }
+
+    #[test]
+    fn every_byte_value_comes_up() {
+        let mut random = SeededRandom::with_seed(1);
+        let mut seen = [false; 256];
+        for _ in 0..10000 {
+            seen[random.next_byte() as usize] = true;
+        }
+
+        assert!(seen.iter().all(|&value| value));
+    }
+
+    #[test]
+    fn zero_seed_does_not_get_stuck() {
+        let mut random = SeededRandom::with_seed(0);
+        let bytes: Vec<u8> = (0..8).map(|_| random.next_byte()).collect();
+
+        assert!(bytes.iter().any(|&byte| byte != bytes[0]));
+    }
+
+    #[test]
+    fn restoring_state_replays_the_sequence() {
+        let mut random = SeededRandom::with_seed(7);
+        random.next_byte();
+        let state = random.state();
+        let expected: Vec<u8> = (0..5).map(|_| random.next_byte()).collect();
+
+        random.set_state(state);
+        let replayed: Vec<u8> = (0..5).map(|_| random.next_byte()).collect();
+        assert_eq!(replayed, expected);
+    }
+
+    #[test]
+    fn vip_generator_mixes_interpreter_bytes_into_r9() {
+        let mut interpreter = vec![0; 0x200];
+        interpreter[0x101] = 0x80;
+        interpreter[0x102] = 0xC0;
+        let mut random = VipRandom::new(&interpreter);
+
+        // 0x00 + 0x80 = 0x80, rotated: 0x40
+        assert_eq!(random.next_byte(), 0x40);
+        // 0x40 + 0xC0 = 0x100, rotated with the carry: 0x80
+        assert_eq!(random.next_byte(), 0x80);
+        assert_eq!(random.state(), 0x8002);
+    }
+
+    #[test]
+    fn vip_generator_advances_with_frames() {
+        let mut interpreter = vec![0; 0x200];
+        interpreter[0x102] = 0x10;
+        let mut random = VipRandom::new(&interpreter);
+
+        random.tick();
+        assert_eq!(random.next_byte(), 0x08);
+    }
+}
@@ -0,0 +1,68 @@
+pub const STACK_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackError {
+    Overflow,
+    Underflow,
+}
+
+// Return addresses pushed by 2NNN and popped by 00EE.
+// The depth is bounded, as it is on every real interpreter.
+#[derive(Clone)]
+pub struct Stack {
+    frames: Vec<u16>,
+    depth: usize,
+}
+
+impl Default for Stack {
+    fn default() -> Stack {
+        Stack::new()
+    }
+}
+
+impl Stack {
+    pub fn new() -> Stack {
+        Stack::with_depth(STACK_DEPTH)
+    }
+
+    pub fn with_depth(depth: usize) -> Stack {
+        Stack {
+            frames: Vec::with_capacity(depth),
+            depth,
+        }
+    }
+
+    pub fn push(&mut self, address: u16) -> Result<(), StackError> {
+        if self.frames.len() == self.depth {
+            return Err(StackError::Overflow);
+        }
+        self.frames.push(address);
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Result<u16, StackError> {
+        self.frames.pop().ok_or(StackError::Underflow)
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    // Maximum number of nested calls.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // Pushed return addresses, oldest first.
+    pub fn frames(&self) -> &[u16] {
+        &self.frames
+    }
+}
@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod stack_test {
+
+    use stack::{ Stack, StackError, STACK_DEPTH };
+
+    #[test]
+    fn starts_empty_with_default_depth() {
+        let stack = Stack::new();
+        assert!(stack.is_empty());
+        assert_eq!(stack.depth(), STACK_DEPTH);
+    }
+
+    #[test]
+    fn pops_addresses_in_reverse_order() {
+        let mut stack = Stack::new();
+        stack.push(0x202).unwrap();
+        stack.push(0x304).unwrap();
+
+        assert_eq!(stack.frames(), &[0x202, 0x304]);
+        assert_eq!(stack.pop(), Ok(0x304));
+        assert_eq!(stack.pop(), Ok(0x202));
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn pushing_past_depth_overflows() {
+        let mut stack = Stack::with_depth(2);
+        stack.push(0x200).unwrap();
+        stack.push(0x200).unwrap();
+
+        assert_eq!(stack.push(0x200), Err(StackError::Overflow));
+        assert_eq!(stack.len(), 2);
+    }
+
+    #[test]
+    fn popping_empty_stack_underflows() {
+        let mut stack = Stack::new();
+        assert_eq!(stack.pop(), Err(StackError::Underflow));
+    }
+}
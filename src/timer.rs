@@ -0,0 +1,51 @@
+// Timers count down at this rate, no matter how fast the CPU runs.
+pub const TIMER_FREQUENCY: u32 = 60;
+
+// Delay and sound timers. Both decrement once per tick until they reach 0.
+// The buzzer sounds for as long as the sound timer is not 0.
+#[derive(Clone)]
+pub struct Timers {
+    delay: u8,
+    sound: u8,
+}
+
+impl Default for Timers {
+    fn default() -> Timers {
+        Timers::new()
+    }
+}
+
+impl Timers {
+    pub fn new() -> Timers {
+        Timers {
+            delay: 0,
+            sound: 0,
+        }
+    }
+
+    pub fn delay(&self) -> u8 {
+        self.delay
+    }
+
+    pub fn sound(&self) -> u8 {
+        self.sound
+    }
+
+    pub fn set_delay(&mut self, value: u8) {
+        self.delay = value;
+    }
+
+    pub fn set_sound(&mut self, value: u8) {
+        self.sound = value;
+    }
+
+    pub fn sound_active(&self) -> bool {
+        self.sound > 0
+    }
+
+    // Advances both timers by one 60 Hz frame.
+    pub fn tick(&mut self) {
+        self.delay = self.delay.saturating_sub(1);
+        self.sound = self.sound.saturating_sub(1);
+    }
+}
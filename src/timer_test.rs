@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod timer_test {
+
+    use timer::Timers;
+
+    #[test]
+    fn starts_stopped_and_silent() {
+        let timers = Timers::new();
+        assert_eq!(timers.delay(), 0);
+        assert_eq!(timers.sound(), 0);
+        assert!(!timers.sound_active());
+    }
+
+    #[test]
+    fn tick_decrements_both_timers() {
+        let mut timers = Timers::new();
+        timers.set_delay(3);
+        timers.set_sound(2);
+
+        timers.tick();
+
+        assert_eq!(timers.delay(), 2);
+        assert_eq!(timers.sound(), 1);
+    }
+
+    #[test]
+    fn timers_stop_at_zero() {
+        let mut timers = Timers::new();
+        timers.set_delay(1);
+        timers.set_sound(1);
+        assert!(timers.sound_active());
+
+        timers.tick();
+        timers.tick();
+
+        assert_eq!(timers.delay(), 0);
+        assert_eq!(timers.sound(), 0);
+        assert!(!timers.sound_active());
+    }
+}
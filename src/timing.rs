@@ -0,0 +1,86 @@
+use instruction::Opcode;
+use vip::{CYCLES_PER_FRAME, DMA_CYCLES_PER_LINE, VIDEO_HEIGHT};
+
+// How long instructions take on the COSMAC VIP, in 1802 machine cycles
+// of 8 clocks (about 4.5 us at the VIP's 1.76 MHz). The figures are
+// the execution times in Laurence Scotford's instruction index for the
+// original interpreter ("Chip-8 on the COSMAC VIP", laurencescotford.net).
+// The fetch and decode loop every instruction goes through is not
+// counted.
+
+// What the interpreter gets of each frame once the 1861 has taken its
+// DMA cycles.
+pub const VIP_CYCLES_PER_FRAME: u32 = CYCLES_PER_FRAME - DMA_CYCLES_PER_LINE * VIDEO_HEIGHT as u32;
+
+// Skips cost this much more when taken.
+pub const SKIP_TAKEN_CYCLES: u32 = 4;
+
+// DXYN has a fixed setup cost plus a cost per row which grows with how
+// far the sprite is from a byte boundary, since the VIP shifts each row
+// into place one bit at a time. 16x16 sprites count as 32 rows. The
+// index has no single figure for DXYN, so this is a model of it.
+const DRAW_CYCLES: u32 = 34;
+const DRAW_ROW_CYCLES: u32 = 20;
+const DRAW_SHIFT_CYCLES: u32 = 8;
+
+// FX55 and FX65 move one register per loop.
+const LOAD_STORE_CYCLES: u32 = 14;
+const LOAD_STORE_REGISTER_CYCLES: u32 = 14;
+
+// FX33 finds each digit by repeated subtraction, one loop per unit.
+const BCD_CYCLES: u32 = 80;
+const BCD_UNIT_CYCLES: u32 = 16;
+
+// Instructions the VIP never had are given a cost like that of a
+// similar one.
+const OTHER_CYCLES: u32 = 10;
+
+// Cycles taken by `opcode` when it is not skipping. `vx` is the value of
+// its X register, which decides how long a draw or FX33 takes.
+pub fn cycles(opcode: &Opcode, vx: u8) -> u32 {
+    match *opcode {
+        Opcode::ClearScreen => 3078,
+        Opcode::Return => 10,
+        Opcode::Jump { .. } | Opcode::LoadI { .. } => 12,
+        Opcode::Call { .. } => 26,
+        Opcode::JumpWithOffset { .. } => 22,
+        Opcode::SkipIfEqual { .. } | Opcode::SkipIfNotEqual { .. } => 10,
+        Opcode::SkipIfRegistersEqual { .. } |
+        Opcode::SkipIfRegistersNotEqual { .. } |
+        Opcode::SkipIfKeyPressed { .. } |
+        Opcode::SkipIfKeyNotPressed { .. } => 14,
+        Opcode::Load { .. } => 6,
+        Opcode::Add { .. } |
+        Opcode::ReadDelayTimer { .. } |
+        Opcode::WaitForKey { .. } |
+        Opcode::SetDelayTimer { .. } |
+        Opcode::SetSoundTimer { .. } => 10,
+        Opcode::Move { .. } |
+        Opcode::Or { .. } |
+        Opcode::And { .. } |
+        Opcode::Xor { .. } |
+        Opcode::AddRegisters { .. } |
+        Opcode::Subtract { .. } |
+        Opcode::ShiftRight { .. } |
+        Opcode::SubtractReversed { .. } |
+        Opcode::ShiftLeft { .. } => 20,
+        Opcode::Random { .. } => 36,
+        Opcode::Draw { n, .. } => {
+            let rows = if n == 0 { 32 } else { n as u32 };
+            DRAW_CYCLES + rows * (DRAW_ROW_CYCLES + DRAW_SHIFT_CYCLES * (vx % 8) as u32)
+        }
+        Opcode::AddToI { .. } => 12,
+        Opcode::LoadFont { .. } => 16,
+        Opcode::StoreBcd { .. } => {
+            let units = vx / 100 + vx / 10 % 10 + vx % 10;
+            BCD_CYCLES + BCD_UNIT_CYCLES * units as u32
+        }
+        Opcode::StoreRegisters { x } | Opcode::LoadRegisters { x } =>
+            LOAD_STORE_CYCLES + LOAD_STORE_REGISTER_CYCLES * (x as u32 + 1),
+        Opcode::ScrollDown { .. } |
+        Opcode::ScrollUp { .. } |
+        Opcode::ScrollRight |
+        Opcode::ScrollLeft => 24,
+        _ => OTHER_CYCLES,
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod timing_test {
+
+    use timing::{ cycles, SKIP_TAKEN_CYCLES, VIP_CYCLES_PER_FRAME };
+    use instruction::Opcode;
+
+    // Laurence Scotford's COSMAC VIP instruction index.
+    #[test]
+    fn costs_match_the_vip_interpreter() {
+        assert_eq!(cycles(&Opcode::ClearScreen, 0), 3078);
+        assert_eq!(cycles(&Opcode::Return, 0), 10);
+        assert_eq!(cycles(&Opcode::Jump { nnn: 0x200 }, 0), 12);
+        assert_eq!(cycles(&Opcode::Call { nnn: 0x200 }, 0), 26);
+        assert_eq!(cycles(&Opcode::JumpWithOffset { nnn: 0x200 }, 0), 22);
+        assert_eq!(cycles(&Opcode::SkipIfEqual { x: 0, nn: 0 }, 0), 10);
+        assert_eq!(cycles(&Opcode::SkipIfRegistersEqual { x: 0, y: 1 }, 0), 14);
+        assert_eq!(SKIP_TAKEN_CYCLES, 4);
+        assert_eq!(cycles(&Opcode::Load { x: 0, nn: 0 }, 0), 6);
+        assert_eq!(cycles(&Opcode::Xor { x: 0, y: 1 }, 0), 20);
+        assert_eq!(cycles(&Opcode::Random { x: 0, nn: 0xFF }, 0), 36);
+        assert_eq!(cycles(&Opcode::StoreRegisters { x: 2 }, 0), 14 + 14 * 3);
+    }
+
+    #[test]
+    fn bcd_costs_more_for_bigger_digits() {
+        assert_eq!(cycles(&Opcode::StoreBcd { x: 0 }, 0), 80);
+        assert_eq!(cycles(&Opcode::StoreBcd { x: 0 }, 123), 80 + 16 * 6);
+    }
+
+    #[test]
+    fn draws_cost_more_with_more_rows() {
+        let short = cycles(&Opcode::Draw { x: 0, y: 0, n: 1 }, 0);
+        let tall = cycles(&Opcode::Draw { x: 0, y: 0, n: 15 }, 0);
+
+        assert!(tall > short);
+    }
+
+    #[test]
+    fn draws_off_a_byte_boundary_cost_more() {
+        let aligned = cycles(&Opcode::Draw { x: 0, y: 0, n: 5 }, 8);
+        let shifted = cycles(&Opcode::Draw { x: 0, y: 0, n: 5 }, 11);
+
+        assert!(shifted > aligned);
+    }
+
+    #[test]
+    fn register_loads_cost_more_the_more_registers_they_move() {
+        assert!(cycles(&Opcode::StoreRegisters { x: 15 }, 0) > cycles(&Opcode::StoreRegisters { x: 0 }, 0));
+    }
+
+    #[test]
+    fn the_interpreter_gets_what_dma_leaves_of_a_frame() {
+        assert_eq!(VIP_CYCLES_PER_FRAME, 3668 - 1024);
+    }
+}
@@ -0,0 +1,275 @@
+use cdp1802::{ Cdp1802, SystemBus };
+use chip8::LoadError;
+use cpu::START;
+use keypad::Keypad;
+use ram::MEMORY_SIZE;
+
+// A whole RCA COSMAC VIP: an 1802, RAM from address 0, the monitor ROM at
+// 0x8000, the CDP1861 video chip and the hex keypad. Instead of emulating
+// CHIP-8 instructions it runs the original interpreter, which the user
+// supplies, so timing and 0NNN machine code routines behave exactly as on
+// the real machine.
+
+// The interpreter lives in the first two pages; programs follow it.
+pub const INTERPRETER_SIZE: usize = START as usize;
+
+// The interpreter keeps its stack, variables and display page in the last
+// 352 bytes of RAM, 0xEA0-0xFFF on a 4 KB machine.
+pub const WORK_AREA_SIZE: usize = 0x160;
+
+pub const MONITOR_ADDRESS: u16 = 0x8000;
+pub const MONITOR_SIZE: usize = 0x200;
+
+// RAM sits below the monitor, so a VIP takes at most 32 KB.
+pub const MAX_MEMORY_SIZE: usize = MONITOR_ADDRESS as usize;
+
+// The 1861 at the VIP's 1.76 MHz clock: 262 lines of 14 machine cycles.
+pub const CLOCK_RATE: u32 = 1_760_640;
+pub const CYCLES_PER_LINE: u32 = 14;
+pub const LINES_PER_FRAME: usize = 262;
+pub const CYCLES_PER_FRAME: u32 = CYCLES_PER_LINE * LINES_PER_FRAME as u32;
+
+// 128 lines of 64 pixels are shown, each fetched by eight DMA cycles at
+// the start of the line. The interrupt comes two lines before the first
+// one, and EF1 is asserted for the four lines before the display starts
+// and before it ends.
+pub const VIDEO_WIDTH: usize = 64;
+pub const VIDEO_HEIGHT: usize = 128;
+pub const FIRST_VIDEO_LINE: usize = 80;
+pub const DMA_CYCLES_PER_LINE: u32 = 8;
+const INTERRUPT_LINES: usize = 2;
+const EF1_LINES: usize = 4;
+
+struct VipBus {
+    ram: Vec<u8>,
+    monitor: Option<Vec<u8>>,
+    // After a reset the monitor also shows up at address 0, until the
+    // first access with A15 set.
+    monitor_at_zero: bool,
+    video_on: bool,
+    display_flag: bool,
+    keypad: Keypad,
+    latched_key: u8,
+}
+
+impl SystemBus for VipBus {
+    fn read(&mut self, address: u16) -> u8 {
+        if address & MONITOR_ADDRESS != 0 {
+            self.monitor_at_zero = false;
+        }
+        if address & MONITOR_ADDRESS != 0 || self.monitor_at_zero {
+            let offset = address as usize % MONITOR_SIZE;
+            return self.monitor.as_ref().and_then(|rom| rom.get(offset).cloned()).unwrap_or(0xFF);
+        }
+        self.ram[address as usize % self.ram.len()]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if address & MONITOR_ADDRESS != 0 {
+            self.monitor_at_zero = false;
+            return;
+        }
+        let length = self.ram.len();
+        self.ram[address as usize % length] = value;
+    }
+
+    // OUT 1 turns the 1861 off; OUT 2 latches the key EF3 reports on.
+    fn output(&mut self, port: u8, value: u8) {
+        match port {
+            1 => self.video_on = false,
+            2 => self.latched_key = value & 0x0F,
+            _ => {}
+        }
+    }
+
+    // INP 1 turns the 1861 on. Nothing drives the data bus.
+    fn input(&mut self, port: u8) -> u8 {
+        if port == 1 {
+            self.video_on = true;
+        }
+        0xFF
+    }
+
+    fn flag(&self, line: u8) -> bool {
+        match line {
+            1 => self.display_flag,
+            3 => self.keypad.is_pressed(self.latched_key),
+            _ => false,
+        }
+    }
+}
+
+pub struct Vip {
+    cpu: Cdp1802,
+    bus: VipBus,
+    interpreter: Vec<u8>,
+    rom: Vec<u8>,
+    frame: Vec<bool>,
+    // Machine cycles the CPU ran past the end of the last line.
+    overrun: u32,
+    cycles: u64,
+}
+
+impl Vip {
+    // A 4 KB VIP running `interpreter`, at most 512 bytes loaded at 0.
+    pub fn new(interpreter: &[u8]) -> Result<Vip, LoadError> {
+        Vip::with_memory_size(interpreter, MEMORY_SIZE)
+    }
+
+    // VIPs came with 2 KB and could be expanded up to 32 KB.
+    // Memory must at least hold the interpreter.
+    pub fn with_memory_size(interpreter: &[u8], size: usize) -> Result<Vip, LoadError> {
+        if !(INTERPRETER_SIZE..=MAX_MEMORY_SIZE).contains(&size) {
+            return Err(LoadError::InvalidMemorySize { size, min: INTERPRETER_SIZE, max: MAX_MEMORY_SIZE });
+        }
+        check_size(interpreter, INTERPRETER_SIZE)?;
+        let mut vip = Vip {
+            cpu: Cdp1802::new(),
+            bus: VipBus {
+                ram: vec![0; size],
+                monitor: None,
+                monitor_at_zero: false,
+                video_on: false,
+                display_flag: false,
+                keypad: Keypad::new(),
+                latched_key: 0,
+            },
+            interpreter: interpreter.to_vec(),
+            rom: Vec::new(),
+            frame: vec![false; VIDEO_WIDTH * VIDEO_HEIGHT],
+            overrun: 0,
+            cycles: 0,
+        };
+        vip.reset();
+        Ok(vip)
+    }
+
+    // With a monitor ROM the machine starts in it, as on power on.
+    pub fn load_monitor(&mut self, monitor: &[u8]) -> Result<(), LoadError> {
+        check_size(monitor, MONITOR_SIZE)?;
+        self.bus.monitor = Some(monitor.to_vec());
+        self.reset();
+        Ok(())
+    }
+
+    // Loads a CHIP-8 program at 0x200 and restarts the machine. The
+    // program has to end below the interpreter's work area.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), LoadError> {
+        check_size(rom, self.bus.ram.len().saturating_sub(START as usize + WORK_AREA_SIZE))?;
+        self.rom = rom.to_vec();
+        self.reset();
+        Ok(())
+    }
+
+    // Clears RAM, reloads the interpreter and program and restarts.
+    // Without a monitor ROM the interpreter is entered directly, with R1
+    // pointing at the last byte of RAM as the monitor leaves it.
+    pub fn reset(&mut self) {
+        for byte in self.bus.ram.iter_mut() {
+            *byte = 0;
+        }
+        self.bus.ram[..self.interpreter.len()].copy_from_slice(&self.interpreter);
+        let start = START as usize;
+        self.bus.ram[start..start + self.rom.len()].copy_from_slice(&self.rom);
+        self.bus.video_on = false;
+        self.bus.display_flag = false;
+        self.bus.latched_key = 0;
+        self.bus.keypad.release_all();
+
+        self.cpu = Cdp1802::new();
+        self.bus.monitor_at_zero = self.bus.monitor.is_some();
+        if !self.bus.monitor_at_zero {
+            self.cpu.set_register(1, (self.bus.ram.len() - 1) as u16);
+        }
+        for pixel in self.frame.iter_mut() {
+            *pixel = false;
+        }
+        self.overrun = 0;
+        self.cycles = 0;
+    }
+
+    // Runs one 1861 frame, about 1/60 of a second.
+    pub fn run_frame(&mut self) {
+        for line in 0..LINES_PER_FRAME {
+            self.run_line(line);
+        }
+    }
+
+    fn run_line(&mut self, line: usize) {
+        let last_video_line = FIRST_VIDEO_LINE + VIDEO_HEIGHT;
+        let video_on = self.bus.video_on;
+        self.bus.display_flag = video_on
+            && ((FIRST_VIDEO_LINE - EF1_LINES..FIRST_VIDEO_LINE).contains(&line)
+                || (last_video_line - EF1_LINES..last_video_line).contains(&line));
+
+        let mut budget = CYCLES_PER_LINE;
+        if video_on && (FIRST_VIDEO_LINE..last_video_line).contains(&line) {
+            let row = (line - FIRST_VIDEO_LINE) * VIDEO_WIDTH;
+            for byte in 0..VIDEO_WIDTH / 8 {
+                let value = self.cpu.dma_out(&mut self.bus);
+                for bit in 0..8 {
+                    self.frame[row + byte * 8 + bit] = value & (0x80 >> bit) != 0;
+                }
+            }
+            budget -= DMA_CYCLES_PER_LINE;
+        }
+
+        let interrupt = video_on && (FIRST_VIDEO_LINE - INTERRUPT_LINES..FIRST_VIDEO_LINE).contains(&line);
+        let mut used = self.overrun;
+        while used < budget {
+            if interrupt && self.cpu.interrupt() {
+                used += 1;
+                continue;
+            }
+            used += self.cpu.step(&mut self.bus);
+        }
+        self.overrun = used - budget;
+        self.cycles += CYCLES_PER_LINE as u64;
+    }
+
+    pub fn press_key(&mut self, key: u8) {
+        self.bus.keypad.press(key);
+    }
+
+    pub fn release_key(&mut self, key: u8) {
+        self.bus.keypad.release(key);
+    }
+
+    // The beeper follows Q.
+    pub fn sound_active(&self) -> bool {
+        self.cpu.q()
+    }
+
+    pub fn is_video_on(&self) -> bool {
+        self.bus.video_on
+    }
+
+    // The last frame drawn by the 1861, 64x128 row by row. The CHIP-8
+    // interpreter repeats each of its 32 rows on four lines.
+    pub fn frame(&self) -> &[bool] {
+        &self.frame
+    }
+
+    // Machine cycles run since the last reset.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn cpu(&self) -> &Cdp1802 {
+        &self.cpu
+    }
+
+    pub fn ram(&self) -> &[u8] {
+        &self.bus.ram
+    }
+}
+
+fn check_size(image: &[u8], max: usize) -> Result<(), LoadError> {
+    if image.is_empty() {
+        return Err(LoadError::Empty);
+    }
+    if image.len() > max {
+        return Err(LoadError::TooLarge { size: image.len(), max });
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+#[cfg(test)]
+mod vip_test {
+
+    use chip8::LoadError;
+    use vip::{ Vip, VIDEO_WIDTH, CYCLES_PER_FRAME };
+
+    // Points R1 at an interrupt routine, turns the 1861 on and loops with
+    // R3 as program counter, leaving R0 to the DMA. The routine points R0
+    // at 0x0C00 and waits for EF1 to drop, which is when the display
+    // starts, then returns through the RET before it so that R1 is back at
+    // its start, like the real interpreter's routine.
+    const VIDEO_INTERPRETER: [u8; 0x2C] = [
+        0xF8, 0x00, 0xB1, 0xF8, 0x20, 0xA1, 0xF8, 0x0E, 0xB2, 0xE2, 0x69, 0xF8, 0x00, 0xB3, 0xF8, 0x12,
+        0xA3, 0xD3, 0x30, 0x12, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x70,
+        0x22, 0x78, 0xF8, 0x0C, 0xB0, 0xF8, 0x00, 0xA0, 0x34, 0x28, 0x30, 0x1F,
+    ];
+
+    #[test]
+    fn interpreter_and_program_are_loaded_in_ram() {
+        let mut vip = Vip::new(&[0x30, 0x00]).unwrap();
+        vip.load_rom(&[0x12, 0x34]).unwrap();
+
+        assert_eq!(&vip.ram()[..2], &[0x30, 0x00]);
+        assert_eq!(&vip.ram()[0x200..0x202], &[0x12, 0x34]);
+        assert_eq!(vip.ram().len(), 4096);
+    }
+
+    #[test]
+    fn interpreter_must_fit_below_the_program() {
+        match Vip::new(&[0; 0x201]) {
+            Err(LoadError::TooLarge { size, max }) => assert_eq!((size, max), (0x201, 0x200)),
+            _ => panic!("expected TooLarge"),
+        }
+        assert!(Vip::new(&[]).is_err());
+    }
+
+    #[test]
+    fn memory_must_hold_the_interpreter_and_stay_below_the_monitor() {
+        for &size in &[0, 0x100, 0x1FF, 0x8001] {
+            match Vip::with_memory_size(&[0x30, 0x00], size) {
+                Err(LoadError::InvalidMemorySize { min, max, .. }) => assert_eq!((min, max), (0x200, 0x8000)),
+                _ => panic!("expected InvalidMemorySize for {:#X}", size),
+            }
+        }
+        let mut vip = Vip::with_memory_size(&[0x30, 0x00], 0x200).unwrap();
+        assert!(vip.load_rom(&[0x12]).is_err());
+        assert!(Vip::with_memory_size(&[0x30, 0x00], 0x8000).is_ok());
+    }
+
+    #[test]
+    fn programs_must_end_below_the_work_area() {
+        let mut vip = Vip::new(&[0x30, 0x00]).unwrap();
+        assert!(vip.load_rom(&[0; 0xCA0]).is_ok());
+        match vip.load_rom(&[0; 0xCA1]) {
+            Err(LoadError::TooLarge { size, max }) => assert_eq!((size, max), (0xCA1, 0xCA0)),
+            _ => panic!("expected TooLarge"),
+        }
+    }
+
+    #[test]
+    fn interpreter_starts_with_r1_at_the_top_of_ram() {
+        let vip = Vip::with_memory_size(&[0x30, 0x00], 2048).unwrap();
+
+        assert_eq!(vip.cpu().register(1), 0x07FF);
+        assert_eq!(vip.cpu().p(), 0);
+    }
+
+    #[test]
+    fn machine_code_in_the_program_runs() {
+        // Interpreter jumps to 0x200 with SEP 3, which sets Q and loops.
+        let mut vip = Vip::new(&[0xF8, 0x02, 0xB3, 0xF8, 0x00, 0xA3, 0xD3]).unwrap();
+        vip.load_rom(&[0x7B, 0x30, 0x01]).unwrap();
+
+        vip.run_frame();
+        assert!(vip.sound_active());
+        assert_eq!(vip.cycles(), CYCLES_PER_FRAME as u64);
+    }
+
+    #[test]
+    fn video_is_fetched_by_dma_from_r0() {
+        let mut vip = Vip::new(&VIDEO_INTERPRETER).unwrap();
+        let mut rom = vec![0; 0xA09];
+        rom[0xA00] = 0x80;
+        rom[0xA08] = 0x01;
+        vip.load_rom(&rom).unwrap();
+
+        vip.run_frame();
+        vip.run_frame();
+        assert!(vip.is_video_on());
+        assert!(vip.frame()[0]);
+        assert!(!vip.frame()[1]);
+        eprintln!("{:02X?}", &vip.ram()[0xC00..0xC10]);
+        for r in 0..128 { eprintln!("{:3} {}", r, vip.frame()[r*64..r*64+64].iter().map(|&p| if p {"#"} else {"."}).collect::<String>()); }
+        assert!(vip.frame()[VIDEO_WIDTH + 7]);
+        assert_eq!(vip.frame()[..32 * VIDEO_WIDTH].iter().filter(|&&pixel| pixel).count(), 2);
+    }
+
+    #[test]
+    fn video_stays_dark_until_the_1861_is_on() {
+        let mut vip = Vip::new(&[0x30, 0x00]).unwrap();
+        vip.load_rom(&[0xFF; 0x10]).unwrap();
+
+        vip.run_frame();
+        assert!(!vip.is_video_on());
+        assert!(vip.frame().iter().all(|&pixel| !pixel));
+    }
+
+    #[test]
+    fn ef3_reports_the_latched_key() {
+        // OUT 2 latches key 5, then B3 waits for it and sets Q.
+        let mut vip = Vip::new(&[0x62, 0x05, 0x36, 0x06, 0x30, 0x02, 0x7B, 0x30, 0x07]).unwrap();
+
+        vip.press_key(4);
+        vip.run_frame();
+        assert!(!vip.sound_active());
+
+        vip.press_key(5);
+        vip.run_frame();
+        assert!(vip.sound_active());
+    }
+
+    #[test]
+    fn monitor_is_mirrored_at_zero_until_a15_is_used() {
+        // LBR 8003; SEQ; BR 04
+        let mut vip = Vip::new(&[0x30, 0x00]).unwrap();
+        vip.load_monitor(&[0xC0, 0x80, 0x03, 0x7B, 0x30, 0x04]).unwrap();
+
+        vip.run_frame();
+        assert!(vip.sound_active());
+        assert_eq!(vip.cpu().register(0), 0x8004);
+    }
+}